@@ -1,4 +1,4 @@
-use polymarket_rtds::{MessageType, RealTimeDataClient, Subscription, Topic};
+use polymarket_rtds::{CandleAggregator, MessageType, RealTimeDataClient, Subscription, Topic};
 use std::collections::HashMap;
 
 #[tokio::main]
@@ -19,6 +19,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut latest_prices: HashMap<String, f64> = HashMap::new();
     let mut update_count: u64 = 0;
+    let mut candles = CandleAggregator::default();
 
     while let Some(message) = client.recv().await {
         match message {
@@ -40,6 +41,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         
                         latest_prices.insert(symbol_lower.clone(), crypto.value);
 
+                        #[allow(clippy::unnecessary_cast)]
                         let time = chrono::DateTime::from_timestamp_millis(crypto.timestamp as i64)
                             .map(|dt| dt.format("%H:%M:%S").to_string())
                             .unwrap_or_default();
@@ -73,6 +75,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             time,
                             update_count
                         );
+
+                        for candle in candles.record(&symbol_lower, crypto.timestamp, crypto.value, 0.0) {
+                            println!(
+                                "  ── {} 1m bar │ O {:.2}  H {:.2}  L {:.2}  C {:.2} │ {} ticks",
+                                symbol_display, candle.open, candle.high, candle.low, candle.close, candle.ticks
+                            );
+                        }
                     }
                 }
             }
@@ -0,0 +1,413 @@
+//! Per-topic subscription builders.
+//!
+//! Each builder only offers the message types and filters the server accepts for its
+//! topic, so invalid combinations such as a `symbol` filter on `clob_user` do not
+//! compile. Builders convert into the regular [`Subscription`] wire format.
+
+use crate::model::{
+    ClobApiKeyCreds, GammaAuth, MessageType, Subscription, SubscriptionFilter, Topic,
+};
+
+/// Subscription to the `activity` topic.
+#[derive(Debug, Clone)]
+pub struct ActivitySubscription {
+    message_type: MessageType,
+    filter: Option<SubscriptionFilter>,
+}
+
+impl ActivitySubscription {
+    /// All activity messages
+    pub fn all() -> Self {
+        Self::with_type(MessageType::All)
+    }
+
+    /// Real-time trades
+    pub fn trades() -> Self {
+        Self::with_type(MessageType::Trades)
+    }
+
+    /// Matched orders
+    pub fn orders_matched() -> Self {
+        Self::with_type(MessageType::OrdersMatched)
+    }
+
+    fn with_type(message_type: MessageType) -> Self {
+        Self {
+            message_type,
+            filter: None,
+        }
+    }
+
+    /// Only receive activity for an event, replacing any market filter
+    pub fn event_slug(mut self, slug: impl Into<String>) -> Self {
+        self.filter = Some(SubscriptionFilter::event_slug(slug));
+        self
+    }
+
+    /// Only receive activity for a market, replacing any event filter
+    pub fn market_slug(mut self, slug: impl Into<String>) -> Self {
+        self.filter = Some(SubscriptionFilter::market_slug(slug));
+        self
+    }
+
+    /// Build the wire subscription
+    pub fn build(self) -> Subscription {
+        subscription(Topic::Activity, self.message_type, self.filter)
+    }
+}
+
+/// Subscription to the `comments` topic.
+#[derive(Debug, Clone)]
+pub struct CommentsSubscription {
+    message_type: MessageType,
+    filter: Option<SubscriptionFilter>,
+    gamma_auth: Option<GammaAuth>,
+}
+
+impl CommentsSubscription {
+    /// All comment and reaction messages
+    pub fn all() -> Self {
+        Self::with_type(MessageType::All)
+    }
+
+    /// New comments
+    pub fn comment_created() -> Self {
+        Self::with_type(MessageType::CommentCreated)
+    }
+
+    /// Removed comments
+    pub fn comment_removed() -> Self {
+        Self::with_type(MessageType::CommentRemoved)
+    }
+
+    /// New reactions
+    pub fn reaction_created() -> Self {
+        Self::with_type(MessageType::ReactionCreated)
+    }
+
+    /// Removed reactions
+    pub fn reaction_removed() -> Self {
+        Self::with_type(MessageType::ReactionRemoved)
+    }
+
+    fn with_type(message_type: MessageType) -> Self {
+        Self {
+            message_type,
+            filter: None,
+            gamma_auth: None,
+        }
+    }
+
+    /// Only receive comments for an event or series
+    pub fn parent_entity(mut self, id: u64, entity_type: impl Into<String>) -> Self {
+        self.filter = Some(SubscriptionFilter::parent_entity(id, entity_type));
+        self
+    }
+
+    /// Set Gamma authentication credentials
+    pub fn gamma_auth(mut self, auth: GammaAuth) -> Self {
+        self.gamma_auth = Some(auth);
+        self
+    }
+
+    /// Build the wire subscription
+    pub fn build(self) -> Subscription {
+        let subscription = subscription(Topic::Comments, self.message_type, self.filter);
+        match self.gamma_auth {
+            Some(auth) => subscription.with_gamma_auth(auth),
+            None => subscription,
+        }
+    }
+}
+
+/// Subscription to the `rfq` topic, which takes no filters.
+#[derive(Debug, Clone)]
+pub struct RfqSubscription {
+    message_type: MessageType,
+}
+
+impl RfqSubscription {
+    /// All RFQ messages
+    pub fn all() -> Self {
+        Self::with_type(MessageType::All)
+    }
+
+    /// RFQ request created
+    pub fn request_created() -> Self {
+        Self::with_type(MessageType::RequestCreated)
+    }
+
+    /// RFQ request edited
+    pub fn request_edited() -> Self {
+        Self::with_type(MessageType::RequestEdited)
+    }
+
+    /// RFQ request canceled
+    pub fn request_canceled() -> Self {
+        Self::with_type(MessageType::RequestCanceled)
+    }
+
+    /// RFQ request expired
+    pub fn request_expired() -> Self {
+        Self::with_type(MessageType::RequestExpired)
+    }
+
+    /// RFQ quote created
+    pub fn quote_created() -> Self {
+        Self::with_type(MessageType::QuoteCreated)
+    }
+
+    /// RFQ quote edited
+    pub fn quote_edited() -> Self {
+        Self::with_type(MessageType::QuoteEdited)
+    }
+
+    /// RFQ quote canceled
+    pub fn quote_canceled() -> Self {
+        Self::with_type(MessageType::QuoteCanceled)
+    }
+
+    /// RFQ quote expired
+    pub fn quote_expired() -> Self {
+        Self::with_type(MessageType::QuoteExpired)
+    }
+
+    fn with_type(message_type: MessageType) -> Self {
+        Self { message_type }
+    }
+
+    /// Build the wire subscription
+    pub fn build(self) -> Subscription {
+        subscription(Topic::Rfq, self.message_type, None)
+    }
+}
+
+/// Subscription to one of the price topics, filtered by symbol.
+#[derive(Debug, Clone)]
+pub struct PriceSubscription {
+    topic: Topic,
+    filter: Option<SubscriptionFilter>,
+}
+
+impl PriceSubscription {
+    fn with_topic(topic: Topic) -> Self {
+        Self {
+            topic,
+            filter: None,
+        }
+    }
+
+    /// Only receive updates for a symbol
+    pub fn symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.filter = Some(SubscriptionFilter::symbol(symbol));
+        self
+    }
+
+    /// Build the wire subscription
+    pub fn build(self) -> Subscription {
+        subscription(self.topic, MessageType::Update, self.filter)
+    }
+}
+
+/// Subscription to the `crypto_prices` topic.
+pub struct CryptoPriceSubscription;
+
+impl CryptoPriceSubscription {
+    /// Crypto price updates
+    pub fn update() -> PriceSubscription {
+        PriceSubscription::with_topic(Topic::CryptoPrices)
+    }
+}
+
+/// Subscription to the `crypto_prices_chainlink` topic.
+pub struct ChainlinkPriceSubscription;
+
+impl ChainlinkPriceSubscription {
+    /// Chainlink crypto price updates
+    pub fn update() -> PriceSubscription {
+        PriceSubscription::with_topic(Topic::CryptoPricesChainlink)
+    }
+}
+
+/// Subscription to the `equity_prices` topic.
+pub struct EquityPriceSubscription;
+
+impl EquityPriceSubscription {
+    /// Equity price updates
+    pub fn update() -> PriceSubscription {
+        PriceSubscription::with_topic(Topic::EquityPrices)
+    }
+}
+
+/// Subscription to the authenticated `clob_user` topic, which takes no filters.
+///
+/// Pass `None` as the credentials to have the client's
+/// [`CredentialProvider`](crate::CredentialProvider) supply them when the
+/// subscription is sent.
+#[derive(Debug, Clone)]
+pub struct ClobUserSubscription {
+    message_type: MessageType,
+    auth: Option<ClobApiKeyCreds>,
+}
+
+impl ClobUserSubscription {
+    /// All user order and trade updates
+    pub fn all(auth: impl Into<Option<ClobApiKeyCreds>>) -> Self {
+        Self {
+            message_type: MessageType::All,
+            auth: auth.into(),
+        }
+    }
+
+    /// User order updates
+    pub fn orders(auth: impl Into<Option<ClobApiKeyCreds>>) -> Self {
+        Self {
+            message_type: MessageType::Order,
+            auth: auth.into(),
+        }
+    }
+
+    /// User trade updates
+    pub fn trades(auth: impl Into<Option<ClobApiKeyCreds>>) -> Self {
+        Self {
+            message_type: MessageType::Trade,
+            auth: auth.into(),
+        }
+    }
+
+    /// Build the wire subscription
+    pub fn build(self) -> Subscription {
+        let subscription = subscription(Topic::ClobUser, self.message_type, None);
+        match self.auth {
+            Some(auth) => subscription.with_clob_auth(auth),
+            None => subscription,
+        }
+    }
+}
+
+/// Subscription to the `clob_market` market lifecycle messages, which take no filter.
+///
+/// Book-level message types are built by [`ClobBookSubscription`], returned from the
+/// token-level constructors.
+#[derive(Debug, Clone)]
+pub struct ClobMarketSubscription {
+    message_type: MessageType,
+}
+
+impl ClobMarketSubscription {
+    /// Market price changes, which must be filtered by token ID; add more with
+    /// [`ClobBookSubscription::token_id`]
+    pub fn price_change(token_id: impl Into<String>) -> ClobBookSubscription {
+        ClobBookSubscription::with_type(MessageType::PriceChange).token_id(token_id)
+    }
+
+    /// Aggregated orderbook for all markets, unless narrowed to token IDs
+    pub fn agg_orderbook() -> ClobBookSubscription {
+        ClobBookSubscription::with_type(MessageType::AggOrderbook)
+    }
+
+    /// Last trade price for all markets, unless narrowed to token IDs
+    pub fn last_trade_price() -> ClobBookSubscription {
+        ClobBookSubscription::with_type(MessageType::LastTradePrice)
+    }
+
+    /// Tick size changes for all markets, unless narrowed to token IDs
+    pub fn tick_size_change() -> ClobBookSubscription {
+        ClobBookSubscription::with_type(MessageType::TickSizeChange)
+    }
+
+    /// New markets
+    pub fn market_created() -> Self {
+        Self {
+            message_type: MessageType::MarketCreated,
+        }
+    }
+
+    /// Resolved markets
+    pub fn market_resolved() -> Self {
+        Self {
+            message_type: MessageType::MarketResolved,
+        }
+    }
+
+    /// Build the wire subscription
+    pub fn build(self) -> Subscription {
+        subscription(Topic::ClobMarket, self.message_type, None)
+    }
+}
+
+/// Subscription to the book-level `clob_market` messages, filtered by token ID.
+///
+/// Token IDs can only be added, so the filter is never an empty list.
+#[derive(Debug, Clone)]
+pub struct ClobBookSubscription {
+    message_type: MessageType,
+    token_ids: Vec<String>,
+}
+
+impl ClobBookSubscription {
+    fn with_type(message_type: MessageType) -> Self {
+        Self {
+            message_type,
+            token_ids: Vec::new(),
+        }
+    }
+
+    /// Also receive messages for a token ID
+    pub fn token_id(mut self, token_id: impl Into<String>) -> Self {
+        self.token_ids.push(token_id.into());
+        self
+    }
+
+    /// Also receive messages for several token IDs
+    pub fn token_ids<I>(mut self, token_ids: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.token_ids.extend(token_ids.into_iter().map(Into::into));
+        self
+    }
+
+    /// Build the wire subscription
+    pub fn build(self) -> Subscription {
+        let filter =
+            (!self.token_ids.is_empty()).then(|| SubscriptionFilter::token_ids(self.token_ids));
+        subscription(Topic::ClobMarket, self.message_type, filter)
+    }
+}
+
+fn subscription(
+    topic: Topic,
+    message_type: MessageType,
+    filter: Option<SubscriptionFilter>,
+) -> Subscription {
+    let subscription = Subscription::new(topic, message_type);
+    // The typed filters are plain strings and numbers, so serialization cannot fail.
+    match filter.and_then(|filter| filter.to_json_string().ok()) {
+        Some(filter) => subscription.with_raw_filter(filter),
+        None => subscription,
+    }
+}
+
+macro_rules! impl_into_subscription {
+    ($($builder:ty),*) => {
+        $(
+            impl From<$builder> for Subscription {
+                fn from(builder: $builder) -> Self {
+                    builder.build()
+                }
+            }
+        )*
+    };
+}
+
+impl_into_subscription!(
+    ActivitySubscription,
+    CommentsSubscription,
+    RfqSubscription,
+    PriceSubscription,
+    ClobUserSubscription,
+    ClobMarketSubscription,
+    ClobBookSubscription
+);
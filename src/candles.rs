@@ -0,0 +1,230 @@
+//! OHLCV candle aggregation.
+//!
+//! A [`CandleAggregator`] turns price ticks into bars of a fixed interval, aligned to
+//! wall-clock boundaries (a one-minute bar starts on the minute). A bar stays open
+//! for a grace window after its end to absorb late ticks, and is returned once when
+//! it closes.
+
+use crate::model::{Message, MessageType, Topic};
+use crate::types::{LastTradePrice, PriceUpdate, TradeActivity};
+use crate::util::parse_number;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_GRACE: Duration = Duration::from_secs(2);
+
+/// Settings for a [`CandleAggregator`].
+#[derive(Debug, Clone)]
+pub struct CandleConfig {
+    /// Length of each bar
+    pub interval: Duration,
+    /// How long a bar accepts late ticks after its end
+    pub grace: Duration,
+}
+
+impl Default for CandleConfig {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_INTERVAL,
+            grace: DEFAULT_GRACE,
+        }
+    }
+}
+
+/// An OHLCV bar.
+#[derive(Debug, Clone)]
+pub struct Candle {
+    /// Symbol or asset ID the bar is for
+    pub key: String,
+    /// Start of the bar in UNIX milliseconds
+    pub start: i64,
+    /// Length of the bar
+    pub interval: Duration,
+    /// First price
+    pub open: f64,
+    /// Highest price
+    pub high: f64,
+    /// Lowest price
+    pub low: f64,
+    /// Last price
+    pub close: f64,
+    /// Traded size; zero for price feeds without sizes
+    pub volume: f64,
+    /// Number of ticks in the bar
+    pub ticks: u64,
+    /// Times of the ticks that set `open` and `close`, so late ticks do not move them
+    first_at: i64,
+    last_at: i64,
+}
+
+impl Candle {
+    /// End of the bar in UNIX milliseconds (exclusive).
+    pub fn end(&self) -> i64 {
+        self.start + self.interval.as_millis() as i64
+    }
+}
+
+#[derive(Default)]
+struct Series {
+    /// Open bars by start time
+    open: BTreeMap<i64, Candle>,
+    /// Latest tick time seen
+    watermark: i64,
+    /// Ticks before this time belong to closed intervals
+    closed_until: i64,
+}
+
+/// Builds OHLCV bars per symbol or asset from price ticks.
+///
+/// Crypto and equity prices are keyed by symbol, and `last_trade_price` and activity
+/// trades by asset ID. Bars close when a later tick for the same key, or a call to
+/// [`CandleAggregator::close_due`], moves time past their end plus the grace window.
+/// Ticks for bars that already closed are dropped. Intervals without ticks produce
+/// no bar.
+#[derive(Default)]
+pub struct CandleAggregator {
+    config: CandleConfig,
+    series: HashMap<String, Series>,
+    late_ticks: u64,
+}
+
+impl CandleAggregator {
+    /// Creates an aggregator.
+    pub fn new(config: CandleConfig) -> Self {
+        Self {
+            config,
+            series: HashMap::new(),
+            late_ticks: 0,
+        }
+    }
+
+    /// Applies a price message, returning the bars it closed.
+    ///
+    /// Uses `crypto_prices`, `crypto_prices_chainlink` and `equity_prices` updates,
+    /// `clob_market` `last_trade_price` and `activity` `trades`; anything else is
+    /// ignored.
+    pub fn apply(&mut self, message: &Message) -> Result<Vec<Candle>, String> {
+        match (&message.topic, &message.message_type) {
+            (
+                Topic::CryptoPrices | Topic::CryptoPricesChainlink | Topic::EquityPrices,
+                MessageType::Update,
+            ) => match message.price_update()? {
+                PriceUpdate::CryptoPrice(price) => {
+                    Ok(self.record(&price.symbol, price.timestamp, price.value, 0.0))
+                }
+                PriceUpdate::EquityPrice(price) => {
+                    Ok(self.record(&price.symbol, price.timestamp, price.value, 0.0))
+                }
+                _ => Ok(Vec::new()),
+            },
+            (Topic::ClobMarket, MessageType::LastTradePrice) => {
+                let trade: LastTradePrice = message.decode()?;
+                let price = parse_number("price", &trade.price)?;
+                let size = parse_number("size", &trade.size)?;
+                Ok(self.record(&trade.asset_id, message.timestamp as i64, price, size))
+            }
+            (Topic::Activity, MessageType::Trades) => {
+                let trade: TradeActivity = message.decode()?;
+                Ok(self.record(
+                    &trade.asset,
+                    trade.timestamp * 1000,
+                    trade.price,
+                    trade.size as f64,
+                ))
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Adds a tick at `timestamp` (UNIX milliseconds), returning the bars it closed.
+    pub fn record(&mut self, key: &str, timestamp: i64, price: f64, volume: f64) -> Vec<Candle> {
+        let interval = self.config.interval.as_millis().max(1) as i64;
+        let series = self.series.entry(key.to_string()).or_default();
+        if timestamp < series.closed_until {
+            self.late_ticks += 1;
+            return Vec::new();
+        }
+
+        let start = timestamp - timestamp.rem_euclid(interval);
+        series
+            .open
+            .entry(start)
+            .and_modify(|candle| {
+                candle.high = candle.high.max(price);
+                candle.low = candle.low.min(price);
+                if timestamp < candle.first_at {
+                    candle.open = price;
+                    candle.first_at = timestamp;
+                }
+                if timestamp >= candle.last_at {
+                    candle.close = price;
+                    candle.last_at = timestamp;
+                }
+                candle.volume += volume;
+                candle.ticks += 1;
+            })
+            .or_insert_with(|| Candle {
+                key: key.to_string(),
+                start,
+                interval: self.config.interval,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume,
+                ticks: 1,
+                first_at: timestamp,
+                last_at: timestamp,
+            });
+        series.watermark = series.watermark.max(timestamp);
+
+        let watermark = series.watermark;
+        close_series(series, watermark, &self.config)
+    }
+
+    /// Closes every bar whose end plus the grace window is at or before `now`, for
+    /// keys that have stopped ticking.
+    pub fn close_due(&mut self, now: SystemTime) -> Vec<Candle> {
+        let now = now
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as i64)
+            .unwrap_or(0);
+        let mut closed = Vec::new();
+        for series in self.series.values_mut() {
+            closed.extend(close_series(series, now, &self.config));
+        }
+        closed
+    }
+
+    /// Returns the latest open bar of a key.
+    pub fn current(&self, key: &str) -> Option<&Candle> {
+        self.series.get(key)?.open.values().next_back()
+    }
+
+    /// Number of ticks dropped because their bar had already closed.
+    pub fn late_ticks(&self) -> u64 {
+        self.late_ticks
+    }
+}
+
+/// Closes the bars of a series that are past their grace window at `now`, oldest
+/// first.
+///
+/// Intervals past their grace window are closed even if they never had a bar, so a
+/// late tick cannot open a bar older than one already returned.
+fn close_series(series: &mut Series, now: i64, config: &CandleConfig) -> Vec<Candle> {
+    let interval = config.interval.as_millis().max(1) as i64;
+    let cutoff = now - config.grace.as_millis() as i64;
+    let mut closed = Vec::new();
+    while let Some(entry) = series.open.first_entry() {
+        if entry.get().end() > cutoff {
+            break;
+        }
+        closed.push(entry.remove());
+    }
+    series.closed_until = series
+        .closed_until
+        .max(cutoff - cutoff.rem_euclid(interval));
+    closed
+}
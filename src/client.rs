@@ -1,16 +1,93 @@
-use crate::model::{Message, SubscriptionMessage};
+use crate::credentials::CredentialProvider;
+use crate::deflate::{self, DeflateStream};
+use crate::filter::{server_accepts, CompositeFilter};
+use crate::manager::SubscriptionManager;
+use crate::metrics::{ClientMetrics, MetricsHandle, MetricsSnapshot};
+use crate::model::{ClobApiKeyCreds, Message, Subscription, SubscriptionMessage, Topic};
+use crate::proxy::ProxyConfig;
+use crate::ratelimit::{Frame, Outbox, RateLimitConfig};
+use crate::trace::trace_event;
+use futures_util::stream::SplitSink;
 use futures_util::{SinkExt, StreamExt};
 use serde_json::json;
-use tokio::sync::mpsc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, watch};
 use tokio::time::Duration;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message as WsMessage};
+use tokio_native_tls::TlsConnector;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Error as WsError;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::{
+    client_async, tungstenite::protocol::Message as WsMessage, MaybeTlsStream, WebSocketStream,
+};
+use url::Url;
 
 const DEFAULT_HOST: &str = "wss://ws-live-data.polymarket.com";
 const DEFAULT_PING_INTERVAL: u64 = 5000;
+const DEFAULT_RECONNECT_INITIAL_DELAY: u64 = 1000;
+const DEFAULT_RECONNECT_MAX_DELAY: u64 = 30000;
+const DEFAULT_BATCH_WINDOW: u64 = 50;
+const DEFAULT_BATCH_MAX_PER_FRAME: usize = 100;
 
-enum Command {
-    Subscribe(SubscriptionMessage),
+type WsStream = WebSocketStream<DeflateStream<MaybeTlsStream<TcpStream>>>;
+type WsSink = SplitSink<WsStream, WsMessage>;
+
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Backoff settings for automatic reconnection.
+///
+/// Active subscriptions are replayed once the connection is reestablished.
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    /// Delay before the first reconnect attempt
+    pub initial_delay: Duration,
+    /// Upper bound for the exponentially growing delay
+    pub max_delay: Duration,
+    /// Number of attempts before giving up, `None` to retry forever
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(DEFAULT_RECONNECT_INITIAL_DELAY),
+            max_delay: Duration::from_millis(DEFAULT_RECONNECT_MAX_DELAY),
+            max_attempts: None,
+        }
+    }
+}
+
+/// Settings for merging subscription changes into fewer frames.
+///
+/// Subscribe and unsubscribe calls made within `window` of the first pending change
+/// are sent together, at most `max_per_frame` subscriptions per frame. Subscribing to
+/// an inactive stream and unsubscribing from it within one window cancel out, as do
+/// unsubscribing from an active stream and subscribing to it again.
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// How long to collect changes before sending them
+    pub window: Duration,
+    /// Maximum number of subscriptions in a single frame
+    pub max_per_frame: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_millis(DEFAULT_BATCH_WINDOW),
+            max_per_frame: DEFAULT_BATCH_MAX_PER_FRAME,
+        }
+    }
+}
+
+pub(crate) enum Command {
+    /// Subscriptions, with the composite filter they were expanded from, if any
+    Subscribe(SubscriptionMessage, Option<Arc<CompositeFilter>>),
     Unsubscribe(SubscriptionMessage),
+    Reauthenticate,
     Disconnect,
 }
 
@@ -19,8 +96,16 @@ enum Command {
 pub struct RealTimeDataClient {
     host: String,
     ping_interval: u64,
+    proxy: Option<ProxyConfig>,
+    compression: bool,
+    metrics: Arc<ClientMetrics>,
+    reconnect: Option<ReconnectConfig>,
+    batching: Option<BatchConfig>,
+    rate_limit: Option<RateLimitConfig>,
+    credentials: Option<Arc<dyn CredentialProvider>>,
     command_tx: Option<mpsc::UnboundedSender<Command>>,
     message_rx: Option<mpsc::UnboundedReceiver<Result<Message, String>>>,
+    manager: Option<SubscriptionManager>,
 }
 
 impl RealTimeDataClient {
@@ -29,113 +114,217 @@ impl RealTimeDataClient {
         Self {
             host: DEFAULT_HOST.to_string(),
             ping_interval: DEFAULT_PING_INTERVAL,
+            proxy: None,
+            compression: false,
+            metrics: Arc::new(ClientMetrics::default()),
+            reconnect: None,
+            batching: None,
+            rate_limit: None,
+            credentials: None,
             command_tx: None,
             message_rx: None,
+            manager: None,
+        }
+    }
+
+    /// Set the WebSocket URL to connect to
+    pub fn with_host(mut self, host: impl Into<String>) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    /// Route the connection through an HTTP CONNECT or SOCKS5 proxy
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Configure the proxy from the `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY` environment variables
+    pub fn with_proxy_from_env(mut self) -> Result<Self, String> {
+        self.proxy = ProxyConfig::from_env()?;
+        Ok(self)
+    }
+
+    /// Offer the permessage-deflate extension during the handshake
+    pub fn with_compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// Returns a snapshot of the client metrics.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Returns a handle to the client metrics that can be moved to other tasks.
+    pub fn metrics_handle(&self) -> MetricsHandle {
+        MetricsHandle {
+            metrics: self.metrics.clone(),
         }
     }
 
+    /// Configure automatic reconnection, or disable it with `None`
+    pub fn with_reconnect(mut self, reconnect: Option<ReconnectConfig>) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Batch subscription changes into fewer frames, or send each call immediately with `None`
+    pub fn with_batching(mut self, batching: Option<BatchConfig>) -> Self {
+        self.batching = batching;
+        self
+    }
+
+    /// Limit the rate of outbound subscribe, unsubscribe and ping frames, or send them unthrottled with `None`
+    ///
+    /// Fails if a budget has a zero burst or a rate that is not positive.
+    pub fn with_rate_limit(mut self, rate_limit: Option<RateLimitConfig>) -> Result<Self, String> {
+        if let Some(rate_limit) = &rate_limit {
+            rate_limit.validate()?;
+        }
+        self.rate_limit = rate_limit;
+        Ok(self)
+    }
+
+    /// Obtain credentials for authenticated subscriptions from a provider when they are sent
+    pub fn with_credential_provider(mut self, provider: Arc<dyn CredentialProvider>) -> Self {
+        self.credentials = Some(provider);
+        self
+    }
+
     /// Establishes a WebSocket connection to the server.
     pub async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let (ws_stream, _) = connect_async(&self.host).await?;
-        let (mut write, mut read) = ws_stream.split();
+        let transport = Transport {
+            connection_id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            host: self.host.clone(),
+            proxy: self.proxy.clone(),
+            compression: self.compression,
+            metrics: self.metrics.clone(),
+        };
+        let ws_stream = transport
+            .open()
+            .await
+            .map_err(|e| e as Box<dyn std::error::Error>)?;
 
-        let (command_tx, mut command_rx) = mpsc::unbounded_channel::<Command>();
+        let (command_tx, command_rx) = mpsc::unbounded_channel::<Command>();
         let (message_tx, message_rx) = mpsc::unbounded_channel::<Result<Message, String>>();
 
+        self.manager = Some(SubscriptionManager::new(
+            command_tx.clone(),
+            self.credentials.is_some(),
+        ));
         self.command_tx = Some(command_tx);
         self.message_rx = Some(message_rx);
 
-        let ping_interval = self.ping_interval;
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "rtds_connection",
+            connection_id = transport.connection_id,
+            host = %transport.host
+        );
 
-        // Spawn the WebSocket handler
-        tokio::spawn(async move {
-            let mut ping_interval_timer = tokio::time::interval(Duration::from_millis(ping_interval));
+        let connection = Connection {
+            transport,
+            ping_interval: self.ping_interval,
+            reconnect: self.reconnect.clone(),
+            batch: self.batching.clone().map(Batch::new),
+            outbox: self.rate_limit.as_ref().map(Outbox::new),
+            rotations: self.credentials.as_ref().and_then(|c| c.rotations()),
+            credentials: self.credentials.clone(),
+            sent_clob_auth: None,
+            command_rx,
+            message_tx,
+            subscriptions: Vec::new(),
+            local_filters: Vec::new(),
+            ping_sent_at: None,
+        };
 
-            loop {
-                tokio::select! {
-                    Some(msg) = read.next() => {
-                        match msg {
-                            Ok(WsMessage::Text(text)) => {
-                                if text.contains("payload") {
-                                    if let Ok(message) = serde_json::from_str::<Message>(&text) {
-                                        let _ = message_tx.send(Ok(message));
-                                    }
-                                }
-                            }
-                            Ok(WsMessage::Close(_)) => {
-                                break;
-                            }
-                            Err(e) => {
-                                eprintln!("WebSocket error: {}", e);
-                                break;
-                            }
-                            _ => {}
-                        }
-                    }
-                    Some(cmd) = command_rx.recv() => {
-                        match cmd {
-                            Command::Subscribe(msg) => {
-                                let payload = json!({
-                                    "action": "subscribe",
-                                    "subscriptions": msg.subscriptions,
-                                });
-                                if write.send(WsMessage::Text(payload.to_string().into())).await.is_err() {
-                                    eprintln!("Failed to send subscribe message");
-                                    break;
-                                }
-                            }
-                            Command::Unsubscribe(msg) => {
-                                let payload = json!({
-                                    "action": "unsubscribe",
-                                    "subscriptions": msg.subscriptions,
-                                });
-                                if write.send(WsMessage::Text(payload.to_string().into())).await.is_err() {
-                                    eprintln!("Failed to send unsubscribe message");
-                                    break;
-                                }
-                            }
-                            Command::Disconnect => {
-                                let _ = write.send(WsMessage::Close(None)).await;
-                                break;
-                            }
-                        }
-                    }
-                    _ = ping_interval_timer.tick() => {
-                        if write.send(WsMessage::Text("ping".to_string().into())).await.is_err() {
-                            eprintln!("Failed to send ping");
-                            break;
-                        }
-                    }
-                    else => break,
-                }
-            }
-        });
+        // Spawn the WebSocket handler
+        #[cfg(feature = "tracing")]
+        tokio::spawn(tracing::Instrument::instrument(connection.run(ws_stream), span));
+        #[cfg(not(feature = "tracing"))]
+        tokio::spawn(connection.run(ws_stream));
 
         Ok(())
     }
 
     /// Receives the next message from the WebSocket connection.
     pub async fn recv(&mut self) -> Option<Result<Message, String>> {
-        if let Some(ref mut rx) = self.message_rx {
-            rx.recv().await
-        } else {
-            None
+        let message = self.message_rx.as_mut()?.recv().await;
+        if message.is_some() {
+            self.metrics.record_message_dequeued();
         }
+        message
     }
 
     /// Subscribes to data streams.
-    pub async fn subscribe(&self, subscriptions: Vec<crate::model::Subscription>) -> Result<(), String> {
+    pub async fn subscribe(&self, subscriptions: Vec<Subscription>) -> Result<(), String> {
+        self.send_subscribe(subscriptions, None)
+    }
+
+    /// Subscribes to the subscriptions expanded from a composite filter, applying
+    /// the parts of the filter the server cannot to incoming messages.
+    ///
+    /// A stream subscribed with several filters delivers messages matching any of
+    /// them, and one subscribed without a filter delivers every message.
+    pub async fn subscribe_filtered(
+        &self,
+        subscriptions: Vec<Subscription>,
+        filter: &CompositeFilter,
+    ) -> Result<(), String> {
+        for subscription in &subscriptions {
+            filter.check(&subscription.topic)?;
+        }
+        self.send_subscribe(subscriptions, Some(Arc::new(filter.clone())))
+    }
+
+    fn send_subscribe(
+        &self,
+        subscriptions: Vec<Subscription>,
+        filter: Option<Arc<CompositeFilter>>,
+    ) -> Result<(), String> {
+        for subscription in &subscriptions {
+            self.validate(subscription)?;
+        }
         let msg = SubscriptionMessage { subscriptions };
         if let Some(ref tx) = self.command_tx {
-            tx.send(Command::Subscribe(msg))
+            tx.send(Command::Subscribe(msg, filter))
                 .map_err(|_| "Failed to send subscribe command".to_string())
         } else {
             Err("Socket not connected".to_string())
         }
     }
 
+    /// Re-sends `clob_user` subscriptions that use the credential provider, so that
+    /// they pick up rotated credentials. Public subscriptions are left untouched.
+    ///
+    /// Providers with [`CredentialProvider::rotations`] trigger this automatically.
+    pub async fn reauthenticate(&self) -> Result<(), String> {
+        if let Some(ref tx) = self.command_tx {
+            tx.send(Command::Reauthenticate)
+                .map_err(|_| "Failed to send reauthenticate command".to_string())
+        } else {
+            Err("Socket not connected".to_string())
+        }
+    }
+
+    /// Validates a subscription, taking the credential provider into account.
+    pub(crate) fn validate(&self, subscription: &Subscription) -> Result<(), String> {
+        subscription.validate_with(self.credentials.is_some())
+    }
+
+    /// Returns the reference-counting subscription manager of this connection.
+    ///
+    /// Every call returns a handle to the same manager, so consumers that subscribe
+    /// through it share counts for identical subscriptions.
+    pub fn subscription_manager(&self) -> Result<SubscriptionManager, String> {
+        self.manager
+            .clone()
+            .ok_or_else(|| "Socket not connected".to_string())
+    }
+
     /// Unsubscribes from data streams.
-    pub async fn unsubscribe(&self, subscriptions: Vec<crate::model::Subscription>) -> Result<(), String> {
+    pub async fn unsubscribe(&self, subscriptions: Vec<Subscription>) -> Result<(), String> {
         let msg = SubscriptionMessage { subscriptions };
         if let Some(ref tx) = self.command_tx {
             tx.send(Command::Unsubscribe(msg))
@@ -160,4 +349,692 @@ impl Default for RealTimeDataClient {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+/// Settings for opening a WebSocket, shared with the connection task for reconnects.
+struct Transport {
+    /// Local identifier of the logical connection, kept across reconnects
+    #[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+    connection_id: u64,
+    host: String,
+    proxy: Option<ProxyConfig>,
+    compression: bool,
+    metrics: Arc<ClientMetrics>,
+}
+
+impl Transport {
+    /// Opens the WebSocket, tunnelling through the proxy unless the host is excluded.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "connect",
+            skip(self),
+            fields(
+                connection_id = self.connection_id,
+                host = %self.host,
+                proxied = self.proxy.is_some(),
+                compression = self.compression
+            ),
+            err(Display)
+        )
+    )]
+    async fn open(&self) -> Result<WsStream, Box<dyn std::error::Error + Send + Sync>> {
+        let url = Url::parse(&self.host)?;
+        let host = url
+            .host_str()
+            .ok_or("WebSocket URL has no host")?
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        let port = url.port_or_known_default().ok_or("WebSocket URL has no port")?;
+
+        let stream = match self.proxy {
+            Some(ref proxy) if !proxy.bypasses(host) => proxy.connect(host, port).await?,
+            _ => TcpStream::connect((host, port)).await?,
+        };
+
+        let stream = if url.scheme() == "wss" {
+            let connector = TlsConnector::from(native_tls::TlsConnector::new()?);
+            MaybeTlsStream::NativeTls(connector.connect(host, stream).await?)
+        } else {
+            MaybeTlsStream::Plain(stream)
+        };
+
+        let mut request = self.host.as_str().into_client_request()?;
+        if self.compression {
+            request.headers_mut().insert(
+                "Sec-WebSocket-Extensions",
+                HeaderValue::from_static(deflate::EXTENSION_OFFER),
+            );
+        }
+
+        self.metrics.set_compression_negotiated(false);
+        let stream = DeflateStream::new(stream, self.compression, self.metrics.clone());
+        let (ws_stream, _) = client_async(request, stream).await?;
+        trace_event!(
+            info,
+            compression_negotiated = self.metrics.snapshot().compression_negotiated,
+            "WebSocket connected"
+        );
+        Ok(ws_stream)
+    }
+}
+
+/// How a served socket ended.
+enum Exit {
+    /// The client asked to disconnect or was dropped
+    Closed,
+    /// The socket failed or the server closed it
+    Lost,
+}
+
+/// Subscription changes collected during a batch window.
+struct Batch {
+    config: BatchConfig,
+    subscribe: Vec<Pending>,
+    unsubscribe: Vec<Pending>,
+    /// When the pending changes are due to be sent
+    flush_at: Option<tokio::time::Instant>,
+}
+
+/// A subscription waiting in a batch.
+struct Pending {
+    subscription: Subscription,
+    /// Whether sending it changes what the server streams, rather than repeating a
+    /// subscribe of an active stream or an unsubscribe of an inactive one
+    change: bool,
+}
+
+impl Batch {
+    fn new(config: BatchConfig) -> Self {
+        Self {
+            config,
+            subscribe: Vec::new(),
+            unsubscribe: Vec::new(),
+            flush_at: None,
+        }
+    }
+
+    /// Adds a change, given the streams that were active before it.
+    ///
+    /// A pending opposite change to the same stream is dropped. If it changed what
+    /// the server streams the two cancel out, otherwise the new change is queued.
+    fn push(&mut self, subscribe: bool, subscriptions: &[Subscription], active: &[Subscription]) {
+        let (add, cancel) = if subscribe {
+            (&mut self.subscribe, &mut self.unsubscribe)
+        } else {
+            (&mut self.unsubscribe, &mut self.subscribe)
+        };
+        for subscription in subscriptions {
+            let was_active = active.iter().any(|s| s.same_stream(subscription));
+            if let Some(index) = cancel
+                .iter()
+                .position(|p| p.subscription.same_stream(subscription))
+            {
+                if cancel.remove(index).change {
+                    continue;
+                }
+            }
+            if !add.iter().any(|p| p.subscription.same_stream(subscription)) {
+                add.push(Pending {
+                    subscription: subscription.clone(),
+                    change: was_active != subscribe,
+                });
+            }
+        }
+        self.flush_at = self
+            .flush_at
+            .or_else(|| Some(tokio::time::Instant::now() + self.config.window));
+    }
+
+    fn clear(&mut self) {
+        self.subscribe.clear();
+        self.unsubscribe.clear();
+        self.flush_at = None;
+    }
+}
+
+/// State of the spawned task driving the WebSocket.
+struct Connection {
+    transport: Transport,
+    ping_interval: u64,
+    reconnect: Option<ReconnectConfig>,
+    /// Pending subscription changes, if batching is enabled
+    batch: Option<Batch>,
+    /// Frames waiting for the rate limiter, if enabled
+    outbox: Option<Outbox>,
+    /// Supplies credentials missing from authenticated subscriptions
+    credentials: Option<Arc<dyn CredentialProvider>>,
+    /// Notifies credential rotations
+    rotations: Option<watch::Receiver<u64>>,
+    /// CLOB credentials the provider supplied for the last frame sent, which
+    /// subscriptions are removed with after a rotation
+    sent_clob_auth: Option<ClobApiKeyCreds>,
+    command_rx: mpsc::UnboundedReceiver<Command>,
+    message_tx: mpsc::UnboundedSender<Result<Message, String>>,
+    /// Active subscriptions, replayed after a reconnect
+    subscriptions: Vec<Subscription>,
+    /// Local parts of the composite filters active subscriptions were made with.
+    /// Streams without an entry deliver every message.
+    local_filters: Vec<(Subscription, Vec<Arc<CompositeFilter>>)>,
+    /// When the last unanswered ping was sent
+    ping_sent_at: Option<Instant>,
+}
+
+impl Connection {
+    async fn run(mut self, mut ws_stream: WsStream) {
+        loop {
+            self.transport.metrics.set_connected(true);
+            let exit = self.serve(ws_stream).await;
+            self.transport.metrics.set_connected(false);
+            if let Exit::Closed = exit {
+                trace_event!(info, "WebSocket closed by client");
+                return;
+            }
+            match self.reconnect().await {
+                Some(stream) => ws_stream = stream,
+                None => return,
+            }
+        }
+    }
+
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    async fn serve(&mut self, ws_stream: WsStream) -> Exit {
+        let (mut write, mut read) = ws_stream.split();
+        let mut ping_interval_timer =
+            tokio::time::interval(Duration::from_millis(self.ping_interval));
+        self.ping_sent_at = None;
+
+        // The replay below already reflects any changes still waiting in the batch.
+        if let Some(batch) = self.batch.as_mut() {
+            batch.clear();
+        }
+        if let Some(outbox) = self.outbox.as_mut() {
+            outbox.clear();
+            self.transport.metrics.set_outbound_queue(0);
+        }
+        // The replay uses the current credentials, so earlier rotations are handled.
+        if let Some(rotations) = self.rotations.as_mut() {
+            rotations.mark_unchanged();
+        }
+        if !self.subscriptions.is_empty() {
+            let subscriptions = self.subscriptions.clone();
+            if !self.send_batched(&mut write, "subscribe", &subscriptions).await {
+                return Exit::Lost;
+            }
+        }
+
+        loop {
+            tokio::select! {
+                msg = read.next() => {
+                    match msg {
+                        Some(Ok(WsMessage::Text(text))) => {
+                            trace_event!(trace, bytes = text.len(), "Frame received");
+                            self.transport.metrics.record_frame_received(text.len());
+                            if text.as_str() == "pong" {
+                                if let Some(sent_at) = self.ping_sent_at.take() {
+                                    self.transport.metrics.record_ping_rtt(sent_at.elapsed());
+                                }
+                            } else {
+                                self.decode(&text);
+                            }
+                        }
+                        Some(Ok(WsMessage::Close(frame))) => {
+                            trace_event!(
+                                warn,
+                                reason = ?frame.as_ref().map(|f| f.reason.as_str()),
+                                "WebSocket closed by server"
+                            );
+                            return Exit::Lost;
+                        }
+                        None => {
+                            trace_event!(warn, "WebSocket stream ended");
+                            return Exit::Lost;
+                        }
+                        Some(Err(e)) => {
+                            trace_event!(error, error = %e, "WebSocket error");
+                            return Exit::Lost;
+                        }
+                        _ => {}
+                    }
+                }
+                cmd = self.command_rx.recv() => {
+                    match cmd {
+                        Some(Command::Subscribe(msg, filter)) => {
+                            let batched = self.batch.as_mut().map(|batch| {
+                                batch.push(true, &msg.subscriptions, &self.subscriptions)
+                            });
+                            self.track_subscribe(&msg.subscriptions, filter.as_deref());
+                            if batched.is_none() && !self.send_subscriptions(&mut write, "subscribe", &msg.subscriptions).await {
+                                return Exit::Lost;
+                            }
+                        }
+                        Some(Command::Unsubscribe(msg)) => {
+                            let batched = self.batch.as_mut().map(|batch| {
+                                batch.push(false, &msg.subscriptions, &self.subscriptions)
+                            });
+                            self.track_unsubscribe(&msg.subscriptions);
+                            if batched.is_none() && !self.send_subscriptions(&mut write, "unsubscribe", &msg.subscriptions).await {
+                                return Exit::Lost;
+                            }
+                        }
+                        Some(Command::Reauthenticate) => {
+                            if !self.reauthenticate(&mut write).await {
+                                return Exit::Lost;
+                            }
+                        }
+                        Some(Command::Disconnect) | None => {
+                            let _ = write.send(WsMessage::Close(None)).await;
+                            return Exit::Closed;
+                        }
+                    }
+                }
+                _ = sleep_until(self.batch.as_ref().and_then(|b| b.flush_at)) => {
+                    if !self.flush_batch(&mut write).await {
+                        return Exit::Lost;
+                    }
+                }
+                _ = sleep_until(self.outbox.as_ref().and_then(Outbox::next_ready)) => {
+                    if let Err(e) = self.drain_outbox(&mut write).await {
+                        trace_event!(error, error = %e, "Failed to send queued frame");
+                        return Exit::Lost;
+                    }
+                }
+                _ = rotation(&mut self.rotations) => {
+                    if !self.reauthenticate(&mut write).await {
+                        return Exit::Lost;
+                    }
+                }
+                _ = ping_interval_timer.tick() => {
+                    if let Err(e) = self.send_frame(&mut write, Frame::Ping).await {
+                        trace_event!(error, error = %e, "Failed to send ping");
+                        return Exit::Lost;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Decodes a data frame and forwards it to the client.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    fn decode(&self, text: &str) {
+        if !text.contains("payload") {
+            return;
+        }
+        match serde_json::from_str::<Message>(text) {
+            Ok(message) if !self.accepts(&message) => {
+                trace_event!(
+                    trace,
+                    topic = %message.topic,
+                    message_type = %message.message_type,
+                    "Message dropped by local filter"
+                );
+            }
+            Ok(message) => {
+                trace_event!(
+                    debug,
+                    topic = %message.topic,
+                    message_type = %message.message_type,
+                    server_connection_id = %message.connection_id,
+                    bytes = text.len(),
+                    "Message decoded"
+                );
+                self.transport
+                    .metrics
+                    .record_message(&message.topic, &message.message_type);
+                let _ = self.message_tx.send(Ok(message));
+            }
+            Err(e) => {
+                trace_event!(warn, error = %e, bytes = text.len(), "Failed to decode message");
+                self.transport.metrics.record_decode_error();
+            }
+        }
+    }
+
+    /// Applies the local part of composite filters.
+    ///
+    /// A message is delivered if any subscription to its topic and type accepts it,
+    /// or if no such subscription is tracked at all. A subscription accepts a message
+    /// when the payload passes its server-side filter and then its local filters, so
+    /// a plain filtered subscription does not let through messages that only match a
+    /// composite one on the same topic.
+    fn accepts(&self, message: &Message) -> bool {
+        let mut any = false;
+        for subscription in self.subscriptions.iter().filter(|s| s.covers(message)) {
+            any = true;
+            if !server_accepts(subscription, &message.payload) {
+                continue;
+            }
+            let filters = self
+                .local_filters
+                .iter()
+                .find(|(s, _)| s.same_stream(subscription));
+            match filters {
+                None => return true,
+                Some((_, filters)) if filters.iter().any(|f| f.matches(&message.payload)) => {
+                    return true
+                }
+                Some(_) => {}
+            }
+        }
+        !any
+    }
+
+    /// Reopens the socket with exponential backoff, returning `None` when giving up
+    /// or when the client disconnects in the meantime.
+    async fn reconnect(&mut self) -> Option<WsStream> {
+        let config = self.reconnect.clone()?;
+        let mut delay = config.initial_delay;
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            if config.max_attempts.is_some_and(|max| attempt > max) {
+                trace_event!(error, attempts = attempt - 1, "Giving up reconnecting");
+                return None;
+            }
+
+            trace_event!(
+                info,
+                attempt,
+                delay_ms = delay.as_millis() as u64,
+                "Reconnecting"
+            );
+            let sleep = tokio::time::sleep(delay);
+            tokio::pin!(sleep);
+            loop {
+                tokio::select! {
+                    _ = &mut sleep => break,
+                    cmd = self.command_rx.recv() => match cmd {
+                        Some(Command::Subscribe(msg, filter)) => {
+                            self.track_subscribe(&msg.subscriptions, filter.as_deref())
+                        }
+                        Some(Command::Unsubscribe(msg)) => self.track_unsubscribe(&msg.subscriptions),
+                        // The replay after reconnecting uses the current credentials.
+                        Some(Command::Reauthenticate) => {}
+                        Some(Command::Disconnect) | None => return None,
+                    },
+                }
+            }
+
+            // `open` reports its own failure through the `connect` span.
+            if let Ok(stream) = self.transport.open().await {
+                trace_event!(
+                    info,
+                    attempt,
+                    subscriptions = self.subscriptions.len(),
+                    "Reconnected"
+                );
+                self.transport.metrics.record_reconnect();
+                return Some(stream);
+            }
+            delay = (delay * 2).min(config.max_delay);
+        }
+    }
+
+    fn track_subscribe(&mut self, subscriptions: &[Subscription], filter: Option<&CompositeFilter>) {
+        for subscription in subscriptions {
+            let tracked = self.subscriptions.iter().any(|s| s.same_stream(subscription));
+            let entry = self
+                .local_filters
+                .iter()
+                .position(|(s, _)| s.same_stream(subscription));
+            match (filter.and_then(|f| f.local_part(subscription)), entry) {
+                // Without a local part the stream delivers everything.
+                (None, Some(index)) => {
+                    self.local_filters.swap_remove(index);
+                }
+                (None, None) => {}
+                (Some(local), Some(index)) => self.local_filters[index].1.push(local),
+                // A tracked stream without an entry already delivers everything.
+                (Some(_), None) if tracked => {}
+                (Some(local), None) => self.local_filters.push((subscription.clone(), vec![local])),
+            }
+            if !tracked {
+                self.subscriptions.push(subscription.clone());
+            }
+        }
+        self.transport
+            .metrics
+            .set_subscriptions(self.subscriptions.len());
+    }
+
+    fn track_unsubscribe(&mut self, subscriptions: &[Subscription]) {
+        self.subscriptions
+            .retain(|s| !subscriptions.iter().any(|u| u.same_stream(s)));
+        self.local_filters
+            .retain(|(s, _)| !subscriptions.iter().any(|u| u.same_stream(s)));
+        self.transport
+            .metrics
+            .set_subscriptions(self.subscriptions.len());
+    }
+
+    /// Re-sends the `clob_user` subscriptions whose credentials come from the provider.
+    ///
+    /// Subscriptions are first added again with the new credentials, then removed
+    /// with the ones they were sent with, so the feed has no gap.
+    async fn reauthenticate(&mut self, write: &mut WsSink) -> bool {
+        let Some(ref provider) = self.credentials else {
+            return true;
+        };
+        let previous = self.sent_clob_auth.clone();
+        if previous.is_none() || previous == provider.clob_auth() {
+            return true;
+        }
+        let subscriptions: Vec<Subscription> = self
+            .subscriptions
+            .iter()
+            .filter(|s| s.topic == Topic::ClobUser && s.clob_auth.is_none())
+            .cloned()
+            .collect();
+        if subscriptions.is_empty() {
+            return true;
+        }
+        trace_event!(
+            info,
+            subscriptions = subscriptions.len(),
+            "Re-authenticating clob_user subscriptions"
+        );
+        let stale: Vec<Subscription> = subscriptions
+            .iter()
+            .cloned()
+            .map(|mut subscription| {
+                subscription.clob_auth = previous.clone();
+                subscription
+            })
+            .collect();
+        self.send_batched(write, "subscribe", &subscriptions).await
+            && self.send_batched(write, "unsubscribe", &stale).await
+    }
+
+    /// Fills in credentials the subscriptions lack from the credential provider.
+    fn authenticate(&mut self, subscriptions: &[Subscription]) -> Vec<Subscription> {
+        let Some(ref provider) = self.credentials else {
+            return subscriptions.to_vec();
+        };
+        let mut clob_auth = None;
+        let mut gamma_auth = None;
+        let authenticated = subscriptions
+            .iter()
+            .cloned()
+            .map(|mut subscription| {
+                match subscription.topic {
+                    Topic::ClobUser if subscription.clob_auth.is_none() => {
+                        subscription.clob_auth = clob_auth
+                            .get_or_insert_with(|| provider.clob_auth())
+                            .clone();
+                        if subscription.clob_auth.is_none() {
+                            trace_event!(warn, "Credential provider returned no CLOB credentials");
+                        }
+                    }
+                    Topic::Comments if subscription.gamma_auth.is_none() => {
+                        subscription.gamma_auth = gamma_auth
+                            .get_or_insert_with(|| provider.gamma_auth())
+                            .clone();
+                    }
+                    _ => {}
+                }
+                subscription
+            })
+            .collect();
+        if let Some(Some(creds)) = clob_auth {
+            self.sent_clob_auth = Some(creds);
+        }
+        authenticated
+    }
+
+    /// Sends the changes collected in the batch window, unsubscribes first.
+    async fn flush_batch(&mut self, write: &mut WsSink) -> bool {
+        let Some(batch) = self.batch.as_mut() else {
+            return true;
+        };
+        let unsubscribe: Vec<Subscription> = std::mem::take(&mut batch.unsubscribe)
+            .into_iter()
+            .map(|p| p.subscription)
+            .collect();
+        let subscribe: Vec<Subscription> = std::mem::take(&mut batch.subscribe)
+            .into_iter()
+            .map(|p| p.subscription)
+            .collect();
+        batch.flush_at = None;
+        trace_event!(
+            debug,
+            subscribe = subscribe.len(),
+            unsubscribe = unsubscribe.len(),
+            "Flushing subscription batch"
+        );
+
+        (unsubscribe.is_empty() || self.send_batched(write, "unsubscribe", &unsubscribe).await)
+            && (subscribe.is_empty() || self.send_batched(write, "subscribe", &subscribe).await)
+    }
+
+    /// Sends subscriptions split into frames of at most the batch size, if batching
+    /// is enabled, or as a single frame otherwise.
+    async fn send_batched(
+        &mut self,
+        write: &mut WsSink,
+        action: &'static str,
+        subscriptions: &[Subscription],
+    ) -> bool {
+        let max_per_frame = self
+            .batch
+            .as_ref()
+            .map_or(subscriptions.len(), |b| b.config.max_per_frame)
+            .max(1);
+        for chunk in subscriptions.chunks(max_per_frame) {
+            if !self.send_subscriptions(write, action, chunk).await {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Sends a subscribe or unsubscribe frame, returning false if the socket failed.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    async fn send_subscriptions(
+        &mut self,
+        write: &mut WsSink,
+        action: &'static str,
+        subscriptions: &[Subscription],
+    ) -> bool {
+        for subscription in subscriptions {
+            trace_event!(
+                debug,
+                action,
+                topic = %subscription.topic,
+                message_type = %subscription.subscription_type,
+                filtered = subscription.filters.is_some(),
+                "Sending subscription"
+            );
+        }
+
+        let frame = Frame::Subscriptions {
+            action,
+            subscriptions: subscriptions.to_vec(),
+        };
+        match self.send_frame(write, frame).await {
+            Ok(()) => true,
+            Err(e) => {
+                trace_event!(error, action, error = %e, "Failed to send frame");
+                false
+            }
+        }
+    }
+
+    /// Sends a frame right away, or through the rate limiter if one is configured.
+    async fn send_frame(&mut self, write: &mut WsSink, frame: Frame) -> Result<(), WsError> {
+        match self.outbox.as_mut() {
+            Some(outbox) => {
+                outbox.push(frame);
+                self.drain_outbox(write).await
+            }
+            None => self.write_frame(write, frame).await,
+        }
+    }
+
+    /// Sends every queued frame the rate limiter currently allows.
+    async fn drain_outbox(&mut self, write: &mut WsSink) -> Result<(), WsError> {
+        while let Some((frame, waited)) = self.outbox.as_mut().and_then(Outbox::pop_ready) {
+            if let Some(waited) = waited {
+                trace_event!(debug, waited_ms = waited.as_millis() as u64, "Rate limited frame sent");
+                self.transport.metrics.record_rate_limit_delay(waited);
+            }
+            self.write_frame(write, frame).await?;
+        }
+        if let Some(outbox) = self.outbox.as_mut() {
+            outbox.mark_delayed();
+            self.transport.metrics.set_outbound_queue(outbox.len());
+        }
+        Ok(())
+    }
+
+    /// Renders a frame with the current credentials and sends it.
+    async fn write_frame(&mut self, write: &mut WsSink, frame: Frame) -> Result<(), WsError> {
+        match frame {
+            Frame::Ping => {
+                self.send_text(write, "ping".to_string()).await?;
+                self.ping_sent_at.get_or_insert_with(Instant::now);
+            }
+            Frame::Subscriptions {
+                action,
+                subscriptions,
+            } => {
+                let payload = json!({
+                    "action": action,
+                    "subscriptions": self.authenticate(&subscriptions),
+                })
+                .to_string();
+                self.send_text(write, payload).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a text frame and records it in the metrics.
+    async fn send_text(&self, write: &mut WsSink, text: String) -> Result<(), WsError> {
+        let bytes = text.len();
+        write.send(WsMessage::Text(text.into())).await?;
+        trace_event!(trace, bytes, "Frame sent");
+        self.transport.metrics.record_frame_sent(bytes);
+        Ok(())
+    }
+}
+
+/// Waits for a credential rotation, or forever if there is no rotation source.
+async fn rotation(rotations: &mut Option<watch::Receiver<u64>>) {
+    let closed = match rotations.as_mut() {
+        Some(rx) => rx.changed().await.is_err(),
+        None => true,
+    };
+    if closed {
+        *rotations = None;
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Sleeps until `deadline`, or forever if there is none.
+pub(crate) async fn sleep_until(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,380 @@
+//! Comment thread reconstruction from the `comments` topic.
+//!
+//! A [`CommentStore`] links comments to their parents to build a thread per event or
+//! series, applies removals, and keeps reaction counts per comment.
+
+use crate::model::{Message, MessageType, Topic};
+use crate::types::{Comment, Reaction};
+use std::collections::{HashMap, HashSet};
+
+/// Default number of reactions kept for comments the store has not seen.
+pub const DEFAULT_ORPHAN_REACTION_LIMIT: usize = 10_000;
+
+/// Reply depth below which [`CommentStore::subtree`] stops descending.
+const MAX_DEPTH: usize = 256;
+
+/// A comment with its reactions and replies.
+#[derive(Debug, Clone)]
+pub struct CommentNode {
+    /// The comment
+    pub comment: Comment,
+    /// Whether the comment was removed; removed comments are only kept while they
+    /// have replies
+    pub removed: bool,
+    /// Reaction counts by reaction type
+    pub reactions: HashMap<String, usize>,
+    /// Replies, oldest first
+    pub replies: Vec<CommentNode>,
+}
+
+impl CommentNode {
+    /// Total number of reactions of every type.
+    pub fn reaction_count(&self) -> usize {
+        self.reactions.values().sum()
+    }
+}
+
+struct StoredComment {
+    comment: Comment,
+    removed: bool,
+}
+
+/// Comment threads per event or series, built from `comments` messages.
+///
+/// Replies whose parent has not been seen are shown as top-level comments until the
+/// parent arrives. Reactions are counted even for comments that were posted before
+/// the store started listening, up to a limit on reactions to unseen comments; see
+/// [`CommentStore::with_orphan_reaction_limit`] and
+/// [`CommentStore::prune_orphan_reactions`].
+///
+/// Parent links that form a cycle are cut where the cycle closes, and threads are
+/// built at most 256 replies deep.
+pub struct CommentStore {
+    comments: HashMap<String, StoredComment>,
+    /// Comment IDs per (parent entity type, parent entity ID), in arrival order
+    entities: HashMap<(String, i64), Vec<String>>,
+    /// Reply IDs per parent comment ID, in arrival order
+    replies: HashMap<String, Vec<String>>,
+    /// Reaction counts per comment ID and reaction type
+    reactions: HashMap<String, HashMap<String, usize>>,
+    /// Comment ID and type of each counted reaction, by reaction ID
+    reaction_index: HashMap<String, (String, String)>,
+    /// Number of counted reactions whose comment is not stored
+    orphan_reactions: usize,
+    orphan_reaction_limit: usize,
+}
+
+impl Default for CommentStore {
+    fn default() -> Self {
+        Self {
+            comments: HashMap::new(),
+            entities: HashMap::new(),
+            replies: HashMap::new(),
+            reactions: HashMap::new(),
+            reaction_index: HashMap::new(),
+            orphan_reactions: 0,
+            orphan_reaction_limit: DEFAULT_ORPHAN_REACTION_LIMIT,
+        }
+    }
+}
+
+impl CommentStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how many reactions to comments the store has not seen are counted.
+    /// Further reactions to unseen comments are ignored until some are pruned or
+    /// their comments arrive.
+    pub fn with_orphan_reaction_limit(mut self, limit: usize) -> Self {
+        self.orphan_reaction_limit = limit;
+        self
+    }
+
+    /// Applies a `comments` message, ignoring other topics. Returns whether the store
+    /// changed.
+    pub fn apply(&mut self, message: &Message) -> Result<bool, String> {
+        if message.topic != Topic::Comments {
+            return Ok(false);
+        }
+        Ok(match message.message_type {
+            MessageType::CommentCreated => self.add_comment(message.decode()?),
+            MessageType::CommentRemoved => self.remove_comment(&message.decode::<Comment>()?.id),
+            MessageType::ReactionCreated => self.add_reaction(&message.decode()?),
+            MessageType::ReactionRemoved => self.remove_reaction(&message.decode()?),
+            _ => false,
+        })
+    }
+
+    /// Adds a comment, returning `false` if it was already stored.
+    pub fn add_comment(&mut self, comment: Comment) -> bool {
+        if self.comments.contains_key(&comment.id) {
+            return false;
+        }
+        self.entities
+            .entry((comment.parent_entity_type.clone(), comment.parent_entity_id))
+            .or_default()
+            .push(comment.id.clone());
+        if let Some(parent) = &comment.parent_comment_id {
+            self.replies
+                .entry(parent.clone())
+                .or_default()
+                .push(comment.id.clone());
+        }
+        let adopted: usize = self
+            .reactions
+            .get(&comment.id)
+            .map_or(0, |c| c.values().sum());
+        self.orphan_reactions -= adopted;
+        self.comments.insert(
+            comment.id.clone(),
+            StoredComment {
+                comment,
+                removed: false,
+            },
+        );
+        true
+    }
+
+    /// Removes a comment. A comment with replies is kept as a removed placeholder
+    /// so that its thread stays intact.
+    pub fn remove_comment(&mut self, id: &str) -> bool {
+        let has_replies = self.replies.get(id).is_some_and(|r| !r.is_empty());
+        if has_replies {
+            return match self.comments.get_mut(id) {
+                Some(stored) if !stored.removed => {
+                    stored.removed = true;
+                    true
+                }
+                _ => false,
+            };
+        }
+
+        let Some(mut parent) = self.drop_comment(id) else {
+            return false;
+        };
+        // A removed parent only stayed for its replies; drop it with its last one.
+        while let Some(id) = parent {
+            let childless = self.replies.get(&id).is_none_or(|r| r.is_empty());
+            if !childless || !self.comments.get(&id).is_some_and(|p| p.removed) {
+                break;
+            }
+            parent = self.drop_comment(&id).flatten();
+        }
+        true
+    }
+
+    /// Deletes a stored comment and its reactions, returning its parent comment ID,
+    /// or `None` if it was not stored.
+    fn drop_comment(&mut self, id: &str) -> Option<Option<String>> {
+        let stored = self.comments.remove(id)?;
+        let comment = stored.comment;
+        let entity = (comment.parent_entity_type.clone(), comment.parent_entity_id);
+        if let Some(ids) = self.entities.get_mut(&entity) {
+            ids.retain(|other| other != id);
+            if ids.is_empty() {
+                self.entities.remove(&entity);
+            }
+        }
+        if let Some(parent) = &comment.parent_comment_id {
+            if let Some(siblings) = self.replies.get_mut(parent) {
+                siblings.retain(|other| other != id);
+                if siblings.is_empty() {
+                    self.replies.remove(parent);
+                }
+            }
+        }
+        self.reactions.remove(id);
+        self.reaction_index
+            .retain(|_, (comment_id, _)| comment_id != id);
+        Some(comment.parent_comment_id)
+    }
+
+    /// Counts a reaction, returning `false` if it was already counted.
+    pub fn add_reaction(&mut self, reaction: &Reaction) -> bool {
+        if self.reaction_index.contains_key(&reaction.id) {
+            return false;
+        }
+        let comment_id = reaction.comment_id.to_string();
+        if !self.comments.contains_key(&comment_id) {
+            if self.orphan_reactions >= self.orphan_reaction_limit {
+                return false;
+            }
+            self.orphan_reactions += 1;
+        }
+        *self
+            .reactions
+            .entry(comment_id.clone())
+            .or_default()
+            .entry(reaction.reaction_type.clone())
+            .or_default() += 1;
+        self.reaction_index.insert(
+            reaction.id.clone(),
+            (comment_id, reaction.reaction_type.clone()),
+        );
+        true
+    }
+
+    /// Uncounts a reaction, returning `false` if it was not counted.
+    pub fn remove_reaction(&mut self, reaction: &Reaction) -> bool {
+        let Some((comment_id, reaction_type)) = self.reaction_index.remove(&reaction.id) else {
+            return false;
+        };
+        if !self.comments.contains_key(&comment_id) {
+            self.orphan_reactions -= 1;
+        }
+        if let Some(counts) = self.reactions.get_mut(&comment_id) {
+            if let Some(count) = counts.get_mut(&reaction_type) {
+                *count -= 1;
+                if *count == 0 {
+                    counts.remove(&reaction_type);
+                }
+            }
+            if counts.is_empty() {
+                self.reactions.remove(&comment_id);
+            }
+        }
+        true
+    }
+
+    /// Drops the reactions counted for comments the store has not seen, returning
+    /// how many were dropped.
+    pub fn prune_orphan_reactions(&mut self) -> usize {
+        let comments = &self.comments;
+        self.reactions.retain(|id, _| comments.contains_key(id));
+        self.reaction_index
+            .retain(|_, (id, _)| comments.contains_key(id));
+        std::mem::take(&mut self.orphan_reactions)
+    }
+
+    /// Returns the number of reactions counted for comments the store has not seen.
+    pub fn orphan_reactions(&self) -> usize {
+        self.orphan_reactions
+    }
+
+    /// Returns a stored comment, including removed placeholders.
+    pub fn comment(&self, id: &str) -> Option<&Comment> {
+        self.comments.get(id).map(|stored| &stored.comment)
+    }
+
+    /// Returns the reaction counts of a comment by reaction type.
+    pub fn reactions(&self, comment_id: &str) -> HashMap<String, usize> {
+        self.reactions.get(comment_id).cloned().unwrap_or_default()
+    }
+
+    /// Returns a comment and its replies. A reply that leads back to a comment
+    /// already in the tree is left out, as are replies more than 256 levels deep.
+    pub fn subtree(&self, id: &str) -> Option<CommentNode> {
+        self.build_subtree(id, &mut HashSet::new(), 0)
+    }
+
+    fn build_subtree<'a>(
+        &'a self,
+        id: &'a str,
+        visited: &mut HashSet<&'a str>,
+        depth: usize,
+    ) -> Option<CommentNode> {
+        let stored = self.comments.get(id)?;
+        if !visited.insert(id) {
+            return None;
+        }
+        let mut replies: Vec<CommentNode> = Vec::new();
+        if depth < MAX_DEPTH {
+            for reply in self.replies.get(id).into_iter().flatten() {
+                replies.extend(self.build_subtree(reply, visited, depth + 1));
+            }
+        }
+        sort_oldest_first(&mut replies);
+        Some(CommentNode {
+            comment: stored.comment.clone(),
+            removed: stored.removed,
+            reactions: self.reactions(id),
+            replies,
+        })
+    }
+
+    /// Returns whether a comment starts a thread: its parent has not been seen, or
+    /// its parent links loop back and it has the smallest ID in the loop.
+    fn is_root(&self, comment: &Comment) -> bool {
+        // Position of each comment on the way up, to find where a loop closes.
+        let mut chain: HashMap<&str, usize> = HashMap::from([(comment.id.as_str(), 0)]);
+        let mut current = comment;
+        while let Some(parent) = current.parent_comment_id.as_deref() {
+            let Some(stored) = self.comments.get(parent) else {
+                break;
+            };
+            if let Some(&start) = chain.get(parent) {
+                return start == 0 && chain.keys().all(|id| *id >= comment.id.as_str());
+            }
+            chain.insert(parent, chain.len());
+            current = &stored.comment;
+        }
+        chain.len() == 1
+    }
+
+    /// Returns the threads of an event or series (`entity_type` is `Event` or
+    /// `Series`), oldest first.
+    pub fn thread(&self, entity_type: &str, entity_id: i64) -> Vec<CommentNode> {
+        let mut roots: Vec<CommentNode> = self
+            .entity_comments(entity_type, entity_id)
+            .filter(|stored| self.is_root(&stored.comment))
+            .filter_map(|stored| self.subtree(&stored.comment.id))
+            .collect();
+        sort_oldest_first(&mut roots);
+        roots
+    }
+
+    /// Returns up to `limit` comments of an event or series with the most
+    /// reactions, counting only `reaction_type` if given. Removed comments are left
+    /// out.
+    pub fn top_comments(
+        &self,
+        entity_type: &str,
+        entity_id: i64,
+        reaction_type: Option<&str>,
+        limit: usize,
+    ) -> Vec<(&Comment, usize)> {
+        let mut ranked: Vec<(&Comment, usize)> = self
+            .entity_comments(entity_type, entity_id)
+            .filter(|stored| !stored.removed)
+            .map(|stored| {
+                let counts = self.reactions.get(&stored.comment.id);
+                let count = match reaction_type {
+                    Some(kind) => counts.and_then(|c| c.get(kind)).copied().unwrap_or(0),
+                    None => counts.map_or(0, |c| c.values().sum()),
+                };
+                (&stored.comment, count)
+            })
+            .collect();
+        ranked.sort_by(|(a, a_count), (b, b_count)| {
+            b_count
+                .cmp(a_count)
+                .then_with(|| a.created_at.cmp(&b.created_at))
+        });
+        ranked.truncate(limit);
+        ranked
+    }
+
+    fn entity_comments<'a>(
+        &'a self,
+        entity_type: &str,
+        entity_id: i64,
+    ) -> impl Iterator<Item = &'a StoredComment> {
+        self.entities
+            .get(&(entity_type.to_string(), entity_id))
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.comments.get(id))
+    }
+}
+
+/// Sorts by creation time; timestamps are ISO 8601 so they order as strings.
+fn sort_oldest_first(nodes: &mut [CommentNode]) {
+    nodes.sort_by(|a, b| {
+        a.comment
+            .created_at
+            .cmp(&b.comment.created_at)
+            .then_with(|| a.comment.id.cmp(&b.comment.id))
+    });
+}
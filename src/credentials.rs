@@ -0,0 +1,216 @@
+use crate::model::{ClobApiKeyCreds, GammaAuth};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::path::Path;
+use std::sync::{PoisonError, RwLock};
+use tokio::sync::watch;
+use zeroize::Zeroize;
+
+const ENV_API_KEY: &str = "CLOB_API_KEY";
+const ENV_API_SECRET: &str = "CLOB_API_SECRET";
+const ENV_PASSPHRASE: &str = "CLOB_PASSPHRASE";
+
+/// A sensitive string that is redacted in `Debug` and `Display` output and wiped
+/// from memory when dropped.
+///
+/// The value is only revealed through [`Secret::expose`] and when serialized, so that
+/// it still reaches the server in subscription frames.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Wraps a sensitive value.
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// Returns the wrapped value.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(Secret)
+    }
+}
+
+impl ClobApiKeyCreds {
+    /// Creates credentials from the API key, secret and passphrase.
+    pub fn new(
+        key: impl Into<String>,
+        secret: impl Into<Secret>,
+        passphrase: impl Into<Secret>,
+    ) -> Self {
+        Self {
+            key: key.into(),
+            secret: secret.into(),
+            passphrase: passphrase.into(),
+        }
+    }
+
+    /// Reads credentials from the `CLOB_API_KEY`, `CLOB_API_SECRET` and
+    /// `CLOB_PASSPHRASE` environment variables.
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| format!("Environment variable {} is not set", name))
+        };
+        Ok(Self::new(
+            var(ENV_API_KEY)?,
+            var(ENV_API_SECRET)?,
+            var(ENV_PASSPHRASE)?,
+        ))
+    }
+
+    /// Reads credentials from a `.json` or `.toml` file with `key`, `secret` and
+    /// `passphrase` fields.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = Secret::new(
+            std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?,
+        );
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(contents.expose())
+                .map_err(|e| format!("Failed to parse credentials: {}", e)),
+            // The full TOML error quotes the offending line, which may hold a secret.
+            Some("toml") => toml::from_str(contents.expose())
+                .map_err(|e| format!("Failed to parse credentials: {}", e.message())),
+            _ => Err(format!(
+                "Unsupported credentials file {}: expected .json or .toml",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// Supplies credentials to the client whenever it sends or replays authenticated
+/// subscriptions, so that they do not have to be stored in each [`Subscription`].
+///
+/// Subscriptions that carry their own `clob_auth` or `gamma_auth` keep it; the
+/// provider only fills in what is missing.
+///
+/// [`Subscription`]: crate::Subscription
+pub trait CredentialProvider: Send + Sync {
+    /// Current CLOB API credentials for `clob_user` subscriptions.
+    fn clob_auth(&self) -> Option<ClobApiKeyCreds>;
+
+    /// Current Gamma authentication for `comments` subscriptions.
+    fn gamma_auth(&self) -> Option<GammaAuth> {
+        None
+    }
+
+    /// A receiver that changes whenever the credentials rotate.
+    ///
+    /// On each change, the client re-authenticates its `clob_user` subscriptions.
+    fn rotations(&self) -> Option<watch::Receiver<u64>> {
+        None
+    }
+}
+
+impl CredentialProvider for ClobApiKeyCreds {
+    fn clob_auth(&self) -> Option<ClobApiKeyCreds> {
+        Some(self.clone())
+    }
+}
+
+/// A credential provider whose credentials can be replaced at runtime.
+///
+/// Calling [`RotatingCredentials::rotate`] notifies every client using it.
+pub struct RotatingCredentials {
+    clob_auth: RwLock<Option<ClobApiKeyCreds>>,
+    gamma_auth: RwLock<Option<GammaAuth>>,
+    rotations: watch::Sender<u64>,
+}
+
+impl RotatingCredentials {
+    /// Creates a provider with initial CLOB credentials.
+    pub fn new(clob_auth: Option<ClobApiKeyCreds>) -> Self {
+        Self {
+            clob_auth: RwLock::new(clob_auth),
+            gamma_auth: RwLock::new(None),
+            rotations: watch::channel(0).0,
+        }
+    }
+
+    /// Set the Gamma authentication
+    pub fn with_gamma_auth(self, auth: GammaAuth) -> Self {
+        *self
+            .gamma_auth
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(auth);
+        self
+    }
+
+    /// Replaces the CLOB credentials and re-authenticates every client using them.
+    pub fn rotate(&self, clob_auth: ClobApiKeyCreds) {
+        *self
+            .clob_auth
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(clob_auth);
+        self.rotations.send_modify(|generation| *generation += 1);
+    }
+}
+
+impl CredentialProvider for RotatingCredentials {
+    fn clob_auth(&self) -> Option<ClobApiKeyCreds> {
+        self.clob_auth
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn gamma_auth(&self) -> Option<GammaAuth> {
+        self.gamma_auth
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn rotations(&self) -> Option<watch::Receiver<u64>> {
+        Some(self.rotations.subscribe())
+    }
+}
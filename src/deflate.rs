@@ -0,0 +1,337 @@
+//! Client side of the permessage-deflate extension (RFC 7692).
+//!
+//! `tungstenite` rejects frames with the RSV1 bit set, so compressed messages are
+//! inflated by a stream adapter that sits between the transport and the WebSocket
+//! codec. The adapter watches the handshake response for the negotiated extension
+//! and, when accepted, rewrites every compressed message into a plain frame.
+//! Outgoing messages are left uncompressed, which the extension permits.
+
+use crate::metrics::ClientMetrics;
+use flate2::{Decompress, FlushDecompress, Status};
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Value of the `Sec-WebSocket-Extensions` header offered during the handshake.
+pub(crate) const EXTENSION_OFFER: &str = "permessage-deflate; client_max_window_bits";
+
+/// Upper bound for a single inflated message.
+const MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+/// Trailer removed by the sender from every compressed message.
+const DEFLATE_TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+enum Mode {
+    /// Waiting for the end of the HTTP handshake response.
+    Handshake,
+    /// Extension was not negotiated, bytes are forwarded untouched.
+    Passthrough,
+    /// Extension was negotiated, frames are parsed and inflated.
+    Inflate,
+}
+
+/// Stream adapter inflating permessage-deflate frames before they reach the codec.
+pub(crate) struct DeflateStream<S> {
+    inner: S,
+    mode: Mode,
+    /// Raw bytes read from the transport and not yet processed.
+    input: Vec<u8>,
+    /// Processed bytes ready to be handed to the reader.
+    output: Vec<u8>,
+    output_pos: usize,
+    decompress: Decompress,
+    no_context_takeover: bool,
+    /// Opcode and collected payload of the compressed message being assembled.
+    message: Option<(u8, Vec<u8>)>,
+    metrics: Arc<ClientMetrics>,
+}
+
+struct FrameHeader {
+    fin: bool,
+    rsv1: bool,
+    opcode: u8,
+    header_len: usize,
+    payload_len: usize,
+    mask: Option<[u8; 4]>,
+}
+
+impl<S> DeflateStream<S> {
+    /// Wraps `inner`; `offered` tells whether the extension was requested in the handshake.
+    pub(crate) fn new(inner: S, offered: bool, metrics: Arc<ClientMetrics>) -> Self {
+        Self {
+            inner,
+            mode: if offered { Mode::Handshake } else { Mode::Passthrough },
+            input: Vec::new(),
+            output: Vec::new(),
+            output_pos: 0,
+            decompress: Decompress::new(false),
+            no_context_takeover: false,
+            message: None,
+            metrics,
+        }
+    }
+
+    /// Moves data from `input` to `output` according to the current mode.
+    fn process(&mut self) -> io::Result<()> {
+        loop {
+            match self.mode {
+                Mode::Handshake => {
+                    let end = match find(&self.input, b"\r\n\r\n") {
+                        Some(pos) => pos + 4,
+                        None => return Ok(()),
+                    };
+                    let head: Vec<u8> = self.input.drain(..end).collect();
+                    self.negotiate(&head);
+                    self.output.extend_from_slice(&head);
+                }
+                Mode::Passthrough => {
+                    self.output.append(&mut self.input);
+                    return Ok(());
+                }
+                Mode::Inflate => {
+                    while let Some(header) = parse_header(&self.input)? {
+                        let frame_len = header.header_len + header.payload_len;
+                        if self.input.len() < frame_len {
+                            break;
+                        }
+                        let frame: Vec<u8> = self.input.drain(..frame_len).collect();
+                        self.process_frame(header, frame)?;
+                    }
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Inspects the handshake response for the accepted extension parameters.
+    fn negotiate(&mut self, head: &[u8]) {
+        let head = String::from_utf8_lossy(head);
+        let extension = head.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("sec-websocket-extensions")
+                .then(|| value.trim().to_ascii_lowercase())
+        });
+
+        match extension {
+            Some(value) if value.starts_with("permessage-deflate") => {
+                self.no_context_takeover = value
+                    .split(';')
+                    .any(|param| param.trim() == "server_no_context_takeover");
+                self.metrics.set_compression_negotiated(true);
+                self.mode = Mode::Inflate;
+            }
+            _ => self.mode = Mode::Passthrough,
+        }
+    }
+
+    fn process_frame(&mut self, header: FrameHeader, frame: Vec<u8>) -> io::Result<()> {
+        let is_control = header.opcode & 0x08 != 0;
+        let starts_compressed = header.rsv1 && !is_control && header.opcode != 0;
+        let continues_compressed = header.opcode == 0 && self.message.is_some();
+
+        if !starts_compressed && !continues_compressed {
+            self.output.extend_from_slice(&frame);
+            return Ok(());
+        }
+
+        let mut payload = frame[header.header_len..].to_vec();
+        if let Some(mask) = header.mask {
+            payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= mask[i % 4]);
+        }
+
+        let (opcode, mut data) = self.message.take().unwrap_or((header.opcode, Vec::new()));
+        data.extend_from_slice(&payload);
+        if data.len() > MAX_MESSAGE_SIZE {
+            return Err(invalid_data("Compressed message too large"));
+        }
+
+        if !header.fin {
+            self.message = Some((opcode, data));
+            return Ok(());
+        }
+
+        let inflated = self.inflate(&data)?;
+        self.metrics.record_compressed_message(data.len(), inflated.len());
+        write_frame(&mut self.output, opcode, &inflated);
+        Ok(())
+    }
+
+    fn inflate(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        if self.no_context_takeover {
+            self.decompress.reset(false);
+        }
+
+        let mut input = data.to_vec();
+        input.extend_from_slice(&DEFLATE_TRAILER);
+
+        let mut out = Vec::with_capacity(input.len() * 4);
+        let mut consumed = 0;
+        loop {
+            if out.capacity() - out.len() < 4096 {
+                out.reserve(out.capacity().max(4096));
+            }
+            let before_in = self.decompress.total_in();
+            let before_out = out.len();
+            let status = self
+                .decompress
+                .decompress_vec(&input[consumed..], &mut out, FlushDecompress::Sync)
+                .map_err(|e| invalid_data(&format!("Failed to inflate message: {}", e)))?;
+            let read = (self.decompress.total_in() - before_in) as usize;
+            consumed += read;
+
+            if out.len() > MAX_MESSAGE_SIZE {
+                return Err(invalid_data("Inflated message too large"));
+            }
+            // All input consumed with output space to spare means the flush completed.
+            if matches!(status, Status::StreamEnd)
+                || (consumed >= input.len() && out.len() < out.capacity())
+            {
+                return Ok(out);
+            }
+            if read == 0 && out.len() == before_out {
+                if consumed >= input.len() {
+                    return Ok(out);
+                }
+                return Err(invalid_data("Failed to inflate message: no progress"));
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for DeflateStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.output_pos < this.output.len() {
+                let n = (this.output.len() - this.output_pos).min(buf.remaining());
+                buf.put_slice(&this.output[this.output_pos..this.output_pos + n]);
+                this.output_pos += n;
+                if this.output_pos == this.output.len() {
+                    this.output.clear();
+                    this.output_pos = 0;
+                }
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut chunk = [0u8; 8192];
+            let mut chunk_buf = ReadBuf::new(&mut chunk);
+            match Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf) {
+                Poll::Ready(Ok(())) => {
+                    let filled = chunk_buf.filled();
+                    if filled.is_empty() {
+                        // EOF; hand over whatever is left so the codec can report it.
+                        this.output.append(&mut this.input);
+                        if this.output.is_empty() {
+                            return Poll::Ready(Ok(()));
+                        }
+                        continue;
+                    }
+                    this.input.extend_from_slice(filled);
+                    this.process()?;
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for DeflateStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Parses a frame header, returning `None` until enough bytes are buffered.
+fn parse_header(buf: &[u8]) -> io::Result<Option<FrameHeader>> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    let fin = buf[0] & 0x80 != 0;
+    let rsv1 = buf[0] & 0x40 != 0;
+    let opcode = buf[0] & 0x0f;
+    let masked = buf[1] & 0x80 != 0;
+
+    let (payload_len, mut header_len) = match buf[1] & 0x7f {
+        126 => {
+            if buf.len() < 4 {
+                return Ok(None);
+            }
+            (u16::from_be_bytes([buf[2], buf[3]]) as usize, 4)
+        }
+        127 => {
+            if buf.len() < 10 {
+                return Ok(None);
+            }
+            let mut len = [0u8; 8];
+            len.copy_from_slice(&buf[2..10]);
+            let len = u64::from_be_bytes(len);
+            if len > MAX_MESSAGE_SIZE as u64 {
+                return Err(invalid_data("Frame too large"));
+            }
+            (len as usize, 10)
+        }
+        len => (len as usize, 2),
+    };
+
+    let mask = if masked {
+        if buf.len() < header_len + 4 {
+            return Ok(None);
+        }
+        let mut key = [0u8; 4];
+        key.copy_from_slice(&buf[header_len..header_len + 4]);
+        header_len += 4;
+        Some(key)
+    } else {
+        None
+    };
+
+    Ok(Some(FrameHeader {
+        fin,
+        rsv1,
+        opcode,
+        header_len,
+        payload_len,
+        mask,
+    }))
+}
+
+/// Writes a final, unmasked frame with the RSV bits cleared.
+fn write_frame(out: &mut Vec<u8>, opcode: u8, payload: &[u8]) {
+    out.push(0x80 | opcode);
+    match payload.len() {
+        len if len < 126 => out.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            out.push(126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(127);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(payload);
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
@@ -0,0 +1,331 @@
+//! Exchange vs Chainlink price divergence monitoring.
+//!
+//! A [`DivergenceMonitor`] pairs `crypto_prices` ticks with the nearest-in-time
+//! `crypto_prices_chainlink` ticks for the same asset, reports the spread between
+//! them, and raises events when the gap passes a threshold and when it closes again.
+//! Exchange markets quoted in different currencies, such as `btcusdt` and `btcusdc`,
+//! are tracked separately against the same Chainlink `btc/usd` feed.
+
+use crate::builders::{ChainlinkPriceSubscription, CryptoPriceSubscription};
+use crate::client::RealTimeDataClient;
+use crate::model::{Message, MessageType, Subscription, Topic};
+use crate::types::PriceUpdate;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+const DEFAULT_THRESHOLD: f64 = 0.002;
+const DEFAULT_MAX_SKEW: Duration = Duration::from_secs(2);
+
+/// Quote currencies recognized when normalizing symbols, longest first.
+const QUOTE_CURRENCIES: [&str; 3] = ["usdt", "usdc", "usd"];
+
+/// Normalizes a symbol to lowercase `<base>/<quote>`, so that `BTCUSDT` and `btcusdt`
+/// become `btc/usdt`, and `BTC/USD` becomes `btc/usd`. Symbols without a known quote
+/// currency, such as a bare base asset, are only lowercased.
+pub fn normalize_symbol(symbol: &str) -> String {
+    let symbol: String = symbol
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    QUOTE_CURRENCIES
+        .iter()
+        .find_map(|quote| {
+            let base = symbol.strip_suffix(quote).filter(|base| !base.is_empty())?;
+            Some(format!("{}/{}", base, quote))
+        })
+        .unwrap_or(symbol)
+}
+
+/// Returns the base asset of a normalized symbol.
+fn base_asset(symbol: &str) -> &str {
+    symbol.split('/').next().unwrap_or(symbol)
+}
+
+/// Settings for a [`DivergenceMonitor`].
+#[derive(Debug, Clone)]
+pub struct DivergenceConfig {
+    /// Relative spread, as a fraction of the Chainlink price, at which the sources
+    /// are considered diverged (0.002 is 0.2%)
+    pub threshold: f64,
+    /// Largest time difference between two ticks that are compared
+    pub max_skew: Duration,
+}
+
+impl Default for DivergenceConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            max_skew: DEFAULT_MAX_SKEW,
+        }
+    }
+}
+
+/// An exchange tick compared with a Chainlink tick.
+#[derive(Debug, Clone)]
+pub struct SpreadSample {
+    /// Normalized exchange market, such as `btc/usdt`
+    pub symbol: String,
+    /// Price from `crypto_prices`
+    pub exchange_price: f64,
+    /// Timestamp of the exchange tick in UNIX milliseconds
+    pub exchange_timestamp: i64,
+    /// Price from `crypto_prices_chainlink`
+    pub chainlink_price: f64,
+    /// Timestamp of the Chainlink tick in UNIX milliseconds
+    pub chainlink_timestamp: i64,
+    /// Exchange price minus Chainlink price
+    pub spread: f64,
+    /// Spread as a fraction of the Chainlink price
+    pub relative_spread: f64,
+}
+
+/// Output of a [`DivergenceMonitor`].
+#[derive(Debug, Clone)]
+pub enum DivergenceEvent {
+    /// A new pair of aligned ticks
+    Spread(SpreadSample),
+    /// The relative spread reached the threshold
+    DivergenceStarted(SpreadSample),
+    /// The relative spread fell back below the threshold
+    DivergenceEnded(SpreadSample),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Tick {
+    timestamp: i64,
+    value: f64,
+}
+
+#[derive(Default)]
+struct Market {
+    ticks: VecDeque<Tick>,
+    diverged: bool,
+}
+
+/// Compares `crypto_prices` with `crypto_prices_chainlink` per asset.
+///
+/// Each new tick is paired with the other source's tick nearest in time, if one lies
+/// within `max_skew`. A Chainlink tick is compared with every exchange market of its
+/// asset.
+#[derive(Default)]
+pub struct DivergenceMonitor {
+    config: DivergenceConfig,
+    /// Monitored assets (`btc`) and exchange markets (`btc/usdc`); everything if empty
+    symbols: HashSet<String>,
+    /// Exchange ticks by normalized symbol
+    markets: HashMap<String, Market>,
+    /// Chainlink ticks by base asset
+    chainlink: HashMap<String, VecDeque<Tick>>,
+}
+
+impl DivergenceMonitor {
+    /// Creates a monitor for every asset.
+    pub fn new(config: DivergenceConfig) -> Self {
+        Self {
+            config,
+            symbols: HashSet::new(),
+            markets: HashMap::new(),
+            chainlink: HashMap::new(),
+        }
+    }
+
+    /// Only monitor an asset, given by its base symbol such as `btc`, or a single
+    /// exchange market such as `btcusdc`
+    pub fn with_symbol(mut self, symbol: &str) -> Self {
+        self.symbols.insert(normalize_symbol(symbol));
+        self
+    }
+
+    /// Returns the subscriptions the monitor needs: every symbol on both topics, or
+    /// the exchange market (`<base>usdt` for a bare asset) on `crypto_prices` and
+    /// `<base>/usd` on `crypto_prices_chainlink` for each monitored symbol.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        if self.symbols.is_empty() {
+            return vec![
+                CryptoPriceSubscription::update().build(),
+                ChainlinkPriceSubscription::update().build(),
+            ];
+        }
+        let mut symbols: Vec<&String> = self.symbols.iter().collect();
+        symbols.sort();
+        let mut bases = HashSet::new();
+        let mut subscriptions = Vec::new();
+        for symbol in symbols {
+            let base = base_asset(symbol);
+            let market = match symbol.split_once('/') {
+                Some((base, quote)) => format!("{}{}", base, quote),
+                None => format!("{}usdt", base),
+            };
+            subscriptions.push(CryptoPriceSubscription::update().symbol(market).build());
+            if bases.insert(base) {
+                subscriptions.push(
+                    ChainlinkPriceSubscription::update()
+                        .symbol(format!("{}/usd", base))
+                        .build(),
+                );
+            }
+        }
+        subscriptions
+    }
+
+    /// Subscribes the client to both price topics.
+    pub async fn subscribe(&self, client: &RealTimeDataClient) -> Result<(), String> {
+        client.subscribe(self.subscriptions()).await
+    }
+
+    /// Applies a price message, ignoring anything other than live `crypto_prices`
+    /// and `crypto_prices_chainlink` updates.
+    pub fn apply(&mut self, message: &Message) -> Result<Vec<DivergenceEvent>, String> {
+        let chainlink = match message.topic {
+            Topic::CryptoPrices => false,
+            Topic::CryptoPricesChainlink => true,
+            _ => return Ok(Vec::new()),
+        };
+        if message.message_type != MessageType::Update {
+            return Ok(Vec::new());
+        }
+        Ok(match message.price_update()? {
+            PriceUpdate::CryptoPrice(price) => {
+                self.record(chainlink, &price.symbol, price.timestamp, price.value)
+            }
+            _ => Vec::new(),
+        })
+    }
+
+    /// Adds a tick from either source, returning the resulting events.
+    pub fn record(
+        &mut self,
+        chainlink: bool,
+        symbol: &str,
+        timestamp: i64,
+        value: f64,
+    ) -> Vec<DivergenceEvent> {
+        let symbol = normalize_symbol(symbol);
+        let base = base_asset(&symbol).to_string();
+        let max_skew = self.config.max_skew.as_millis() as i64;
+        let threshold = self.config.threshold;
+        let tick = Tick { timestamp, value };
+
+        if !chainlink {
+            if !self.monitors(&symbol) {
+                return Vec::new();
+            }
+            let market = self.markets.entry(symbol.clone()).or_default();
+            push_tick(&mut market.ticks, tick, max_skew);
+            let matched = self
+                .chainlink
+                .get(&base)
+                .and_then(|ticks| nearest(ticks, timestamp, max_skew));
+            return match matched {
+                Some(matched) => compare(market, symbol, tick, matched, threshold),
+                None => Vec::new(),
+            };
+        }
+
+        if !self.symbols.is_empty() && !self.symbols.iter().any(|s| base_asset(s) == base) {
+            return Vec::new();
+        }
+        push_tick(
+            self.chainlink.entry(base.clone()).or_default(),
+            tick,
+            max_skew,
+        );
+        let mut symbols: Vec<String> = self
+            .markets
+            .keys()
+            .filter(|symbol| base_asset(symbol) == base)
+            .cloned()
+            .collect();
+        symbols.sort();
+        let mut events = Vec::new();
+        for symbol in symbols {
+            let Some(market) = self.markets.get_mut(&symbol) else {
+                continue;
+            };
+            if let Some(matched) = nearest(&market.ticks, timestamp, max_skew) {
+                events.extend(compare(market, symbol, matched, tick, threshold));
+            }
+        }
+        events
+    }
+
+    /// Returns whether an exchange market such as `btcusdt` is currently diverged.
+    /// A bare asset such as `btc` checks every market of that asset.
+    pub fn is_diverged(&self, symbol: &str) -> bool {
+        let symbol = normalize_symbol(symbol);
+        if symbol.contains('/') {
+            return self
+                .markets
+                .get(&symbol)
+                .is_some_and(|market| market.diverged);
+        }
+        self.markets
+            .iter()
+            .any(|(market, state)| base_asset(market) == symbol && state.diverged)
+    }
+
+    fn monitors(&self, symbol: &str) -> bool {
+        self.symbols.is_empty()
+            || self.symbols.contains(symbol)
+            || self.symbols.contains(base_asset(symbol))
+    }
+}
+
+/// Inserts a tick in timestamp order and drops ticks too old to be matched.
+fn push_tick(ticks: &mut VecDeque<Tick>, tick: Tick, max_skew: i64) {
+    let position = ticks.partition_point(|t| t.timestamp <= tick.timestamp);
+    ticks.insert(position, tick);
+    // Older ticks can no longer be the nearest match for new ones.
+    let newest = ticks.back().map_or(tick.timestamp, |t| t.timestamp);
+    while ticks
+        .front()
+        .is_some_and(|t| t.timestamp < newest - 2 * max_skew)
+    {
+        ticks.pop_front();
+    }
+}
+
+/// Returns the tick nearest to `timestamp`, if one lies within `max_skew`.
+fn nearest(ticks: &VecDeque<Tick>, timestamp: i64, max_skew: i64) -> Option<Tick> {
+    ticks
+        .iter()
+        .filter(|t| (t.timestamp - timestamp).abs() <= max_skew)
+        .min_by_key(|t| (t.timestamp - timestamp).abs())
+        .copied()
+}
+
+/// Compares an exchange tick with a Chainlink tick, updating the market's state.
+fn compare(
+    market: &mut Market,
+    symbol: String,
+    exchange: Tick,
+    chainlink: Tick,
+    threshold: f64,
+) -> Vec<DivergenceEvent> {
+    if chainlink.value == 0.0 {
+        return Vec::new();
+    }
+
+    let spread = exchange.value - chainlink.value;
+    let sample = SpreadSample {
+        symbol,
+        exchange_price: exchange.value,
+        exchange_timestamp: exchange.timestamp,
+        chainlink_price: chainlink.value,
+        chainlink_timestamp: chainlink.timestamp,
+        spread,
+        relative_spread: spread / chainlink.value,
+    };
+    let diverged = sample.relative_spread.abs() >= threshold;
+    let mut events = vec![DivergenceEvent::Spread(sample.clone())];
+    if diverged != market.diverged {
+        market.diverged = diverged;
+        events.push(if diverged {
+            DivergenceEvent::DivergenceStarted(sample)
+        } else {
+            DivergenceEvent::DivergenceEnded(sample)
+        });
+    }
+    events
+}
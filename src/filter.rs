@@ -0,0 +1,374 @@
+//! Composite subscription filters.
+//!
+//! The server accepts at most one filter value per subscription (a single slug,
+//! symbol or parent entity; `clob_market` takes a token list). A [`CompositeFilter`]
+//! can hold several values per field and several fields at once: it is expanded into
+//! one subscription per server-side value, and whatever the server cannot filter on
+//! is checked by the client against each message payload before it is delivered,
+//! for subscriptions made with
+//! [`RealTimeDataClient::subscribe_filtered`](crate::RealTimeDataClient::subscribe_filtered).
+
+use crate::model::{MessageType, Subscription, SubscriptionFilter, Topic};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Payload field that a [`CompositeFilter`] clause matches on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterField {
+    /// Event slug of an activity message
+    EventSlug,
+    /// Market slug of an activity message
+    MarketSlug,
+    /// Parent entity (ID and type) of a comment
+    ParentEntity,
+    /// Symbol of a price update
+    Symbol,
+    /// CLOB token (asset) ID
+    TokenId,
+}
+
+impl FilterField {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FilterField::EventSlug => "event_slug",
+            FilterField::MarketSlug => "market_slug",
+            FilterField::ParentEntity => "parent_entity",
+            FilterField::Symbol => "symbol",
+            FilterField::TokenId => "token_id",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Clause {
+    EventSlugs(Vec<String>),
+    MarketSlugs(Vec<String>),
+    ParentEntities(Vec<(u64, String)>),
+    Symbols(Vec<String>),
+    TokenIds(Vec<String>),
+}
+
+impl Clause {
+    fn field(&self) -> FilterField {
+        match self {
+            Clause::EventSlugs(_) => FilterField::EventSlug,
+            Clause::MarketSlugs(_) => FilterField::MarketSlug,
+            Clause::ParentEntities(_) => FilterField::ParentEntity,
+            Clause::Symbols(_) => FilterField::Symbol,
+            Clause::TokenIds(_) => FilterField::TokenId,
+        }
+    }
+
+    /// Returns whether the payload satisfies the clause, or `None` if the payload
+    /// does not carry the field.
+    fn matches(&self, payload: &Value) -> Option<bool> {
+        let string = |name: &str| payload.get(name).and_then(Value::as_str);
+        match self {
+            Clause::EventSlugs(slugs) => {
+                string("eventSlug").map(|slug| slugs.iter().any(|s| s == slug))
+            }
+            Clause::MarketSlugs(slugs) => {
+                string("slug").map(|slug| slugs.iter().any(|s| s == slug))
+            }
+            Clause::ParentEntities(entities) => {
+                let id = payload.get("parentEntityID").and_then(Value::as_u64)?;
+                let entity_type = string("parentEntityType")?;
+                Some(
+                    entities
+                        .iter()
+                        .any(|(i, t)| *i == id && t.eq_ignore_ascii_case(entity_type)),
+                )
+            }
+            Clause::Symbols(symbols) => string("symbol")
+                .map(|symbol| symbols.iter().any(|s| s.eq_ignore_ascii_case(symbol))),
+            Clause::TokenIds(ids) => {
+                let assets = asset_ids(payload);
+                if assets.is_empty() {
+                    None
+                } else {
+                    Some(assets.iter().any(|asset| ids.iter().any(|id| id == asset)))
+                }
+            }
+        }
+    }
+}
+
+impl From<SubscriptionFilter> for Clause {
+    fn from(filter: SubscriptionFilter) -> Self {
+        match filter {
+            SubscriptionFilter::EventSlug { event_slug } => Clause::EventSlugs(vec![event_slug]),
+            SubscriptionFilter::MarketSlug { market_slug } => {
+                Clause::MarketSlugs(vec![market_slug])
+            }
+            SubscriptionFilter::ParentEntity {
+                parent_entity_id,
+                parent_entity_type,
+            } => Clause::ParentEntities(vec![(parent_entity_id, parent_entity_type)]),
+            SubscriptionFilter::Symbol { symbol } => Clause::Symbols(vec![symbol]),
+            SubscriptionFilter::TokenIds(ids) => Clause::TokenIds(ids),
+        }
+    }
+}
+
+/// Returns whether a payload satisfies the server-side filter of a subscription.
+///
+/// Subscriptions without filters, with filters that cannot be parsed, or whose
+/// filter field the payload does not carry accept the payload: the server has
+/// already applied the filter to what it sent on that subscription.
+pub(crate) fn server_accepts(subscription: &Subscription, payload: &Value) -> bool {
+    let filter = match subscription.filters.as_deref() {
+        Some(filters) => serde_json::from_str::<SubscriptionFilter>(filters),
+        None => return true,
+    };
+    match filter {
+        Ok(filter) => Clause::from(filter).matches(payload).unwrap_or(true),
+        Err(_) => true,
+    }
+}
+
+/// Collects the token IDs a CLOB payload refers to, whatever its message type.
+fn asset_ids(payload: &Value) -> Vec<&str> {
+    let mut ids = Vec::new();
+    for key in ["asset_id", "asset_ids"] {
+        match payload.get(key) {
+            Some(Value::String(id)) => ids.push(id.as_str()),
+            Some(Value::Array(values)) => ids.extend(values.iter().filter_map(Value::as_str)),
+            _ => {}
+        }
+    }
+    if let Some(Value::Array(changes)) = payload.get("pc") {
+        ids.extend(
+            changes
+                .iter()
+                .filter_map(|change| change.get("a").and_then(Value::as_str)),
+        );
+    }
+    ids
+}
+
+/// A filter with several values per field, combined across fields with AND.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompositeFilter {
+    clauses: Vec<Clause>,
+    server_field: Option<FilterField>,
+}
+
+impl CompositeFilter {
+    /// Creates an empty filter that matches every message.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match any of the given event slugs
+    pub fn event_slugs<I, S>(self, slugs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.with_clause(Clause::EventSlugs(strings(slugs)))
+    }
+
+    /// Match any of the given market slugs
+    pub fn market_slugs<I, S>(self, slugs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.with_clause(Clause::MarketSlugs(strings(slugs)))
+    }
+
+    /// Match a parent entity, in addition to any already added
+    pub fn parent_entity(self, id: u64, entity_type: impl Into<String>) -> Self {
+        self.with_clause(Clause::ParentEntities(vec![(id, entity_type.into())]))
+    }
+
+    /// Match any of the given symbols
+    pub fn symbols<I, S>(self, symbols: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.with_clause(Clause::Symbols(strings(symbols)))
+    }
+
+    /// Match any of the given token IDs
+    pub fn token_ids<I, S>(self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.with_clause(Clause::TokenIds(strings(ids)))
+    }
+
+    /// Adds a clause, merging its values into an existing clause on the same field.
+    fn with_clause(mut self, clause: Clause) -> Self {
+        let existing = self
+            .clauses
+            .iter_mut()
+            .find(|c| c.field() == clause.field());
+        match (existing, clause) {
+            (Some(Clause::EventSlugs(a)), Clause::EventSlugs(b))
+            | (Some(Clause::MarketSlugs(a)), Clause::MarketSlugs(b))
+            | (Some(Clause::Symbols(a)), Clause::Symbols(b))
+            | (Some(Clause::TokenIds(a)), Clause::TokenIds(b)) => a.extend(b),
+            (Some(Clause::ParentEntities(a)), Clause::ParentEntities(b)) => a.extend(b),
+            (_, clause) => self.clauses.push(clause),
+        }
+        self
+    }
+
+    /// Returns the fields this filter matches on.
+    pub fn fields(&self) -> Vec<FilterField> {
+        self.clauses.iter().map(Clause::field).collect()
+    }
+
+    /// Expands the filter into the subscriptions to send for `topic` and `message_type`.
+    ///
+    /// The field the server can filter on is sent with the subscriptions, one per value
+    /// where the server accepts only one. Remaining fields are applied by the client to
+    /// incoming messages when the subscriptions are passed to
+    /// [`RealTimeDataClient::subscribe_filtered`](crate::RealTimeDataClient::subscribe_filtered).
+    /// Fails if a field does not appear in the topic's messages or a list is empty.
+    pub fn subscriptions(
+        &self,
+        topic: Topic,
+        message_type: MessageType,
+    ) -> Result<Vec<Subscription>, String> {
+        self.check(&topic)?;
+
+        let server_filters = match self.server_clause(&topic, &message_type) {
+            Some(Clause::EventSlugs(slugs)) => slugs
+                .iter()
+                .map(|slug| SubscriptionFilter::event_slug(slug.clone()))
+                .collect(),
+            Some(Clause::MarketSlugs(slugs)) => slugs
+                .iter()
+                .map(|slug| SubscriptionFilter::market_slug(slug.clone()))
+                .collect(),
+            Some(Clause::ParentEntities(entities)) => entities
+                .iter()
+                .map(|(id, entity_type)| {
+                    SubscriptionFilter::parent_entity(*id, entity_type.clone())
+                })
+                .collect(),
+            Some(Clause::Symbols(symbols)) => symbols
+                .iter()
+                .map(|symbol| SubscriptionFilter::symbol(symbol.clone()))
+                .collect(),
+            Some(Clause::TokenIds(ids)) => vec![SubscriptionFilter::token_ids(ids.clone())],
+            None => Vec::new(),
+        };
+
+        let subscription = |filter: Option<SubscriptionFilter>| {
+            let subscription = Subscription::new(topic.clone(), message_type.clone());
+            match filter {
+                Some(filter) => subscription
+                    .with_filter(filter)
+                    .map_err(|e| format!("Failed to serialize filter: {}", e)),
+                None => Ok(subscription),
+            }
+        };
+
+        if server_filters.is_empty() {
+            Ok(vec![subscription(None)?])
+        } else {
+            server_filters
+                .into_iter()
+                .map(|filter| subscription(Some(filter)))
+                .collect()
+        }
+    }
+
+    /// Fails if a field does not appear in the topic's messages or a list is empty.
+    pub(crate) fn check(&self, topic: &Topic) -> Result<(), String> {
+        for clause in &self.clauses {
+            let field = clause.field();
+            if !payload_fields(topic).contains(&field) {
+                return Err(format!(
+                    "Topic '{}' messages cannot be filtered by {}",
+                    topic,
+                    field.as_str()
+                ));
+            }
+            let empty = match clause {
+                Clause::EventSlugs(v)
+                | Clause::MarketSlugs(v)
+                | Clause::Symbols(v)
+                | Clause::TokenIds(v) => v.is_empty(),
+                Clause::ParentEntities(v) => v.is_empty(),
+            };
+            if empty {
+                return Err(format!("Filter on {} has no values", field.as_str()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the clause the server filters on for a topic and type, if any.
+    fn server_clause(&self, topic: &Topic, message_type: &MessageType) -> Option<&Clause> {
+        server_fields(topic, message_type)
+            .iter()
+            .find_map(|field| self.clauses.iter().find(|c| c.field() == *field))
+    }
+
+    /// Returns the part of the filter the client applies to messages of a
+    /// subscription, or `None` if the server applies all of it.
+    pub(crate) fn local_part(&self, subscription: &Subscription) -> Option<Arc<CompositeFilter>> {
+        let server_field = self
+            .server_clause(&subscription.topic, &subscription.subscription_type)
+            .map(Clause::field);
+        (self.clauses.len() > usize::from(server_field.is_some())).then(|| {
+            Arc::new(CompositeFilter {
+                clauses: self.clauses.clone(),
+                server_field,
+            })
+        })
+    }
+
+    /// Returns whether a message payload satisfies every clause.
+    ///
+    /// A clause on a field the payload does not carry only passes if the server
+    /// already applied it.
+    pub fn matches(&self, payload: &Value) -> bool {
+        self.clauses.iter().all(|clause| {
+            clause
+                .matches(payload)
+                .unwrap_or(self.server_field == Some(clause.field()))
+        })
+    }
+}
+
+/// Fields carried by the payloads of a topic.
+fn payload_fields(topic: &Topic) -> &'static [FilterField] {
+    match topic {
+        Topic::Activity => &[FilterField::EventSlug, FilterField::MarketSlug],
+        Topic::Comments => &[FilterField::ParentEntity],
+        Topic::CryptoPrices | Topic::CryptoPricesChainlink | Topic::EquityPrices => {
+            &[FilterField::Symbol]
+        }
+        Topic::ClobUser | Topic::ClobMarket => &[FilterField::TokenId],
+        Topic::Rfq => &[],
+    }
+}
+
+/// Fields the server can filter on for a topic and type, in order of preference.
+fn server_fields(topic: &Topic, message_type: &MessageType) -> &'static [FilterField] {
+    match (topic, message_type) {
+        (Topic::Activity, _) => &[FilterField::MarketSlug, FilterField::EventSlug],
+        (Topic::Comments, _) => &[FilterField::ParentEntity],
+        (Topic::CryptoPrices | Topic::CryptoPricesChainlink | Topic::EquityPrices, _) => {
+            &[FilterField::Symbol]
+        }
+        (Topic::ClobMarket, MessageType::MarketCreated | MessageType::MarketResolved) => &[],
+        (Topic::ClobMarket, _) => &[FilterField::TokenId],
+        (Topic::ClobUser | Topic::Rfq, _) => &[],
+    }
+}
+
+fn strings<I, S>(values: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    values.into_iter().map(Into::into).collect()
+}
@@ -0,0 +1,159 @@
+//! Per-symbol price history.
+//!
+//! A [`PriceHistory`] is seeded from the historical payloads sent for price topics
+//! and then extended with live updates. Points are keyed by timestamp, so overlap
+//! between the backfill and the live feed is deduplicated.
+
+use crate::model::{Message, MessageType, Topic};
+use crate::types::{PriceDataPoint, PriceUpdate};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Price points per symbol, built from `crypto_prices`, `crypto_prices_chainlink` and
+/// `equity_prices` updates.
+#[derive(Debug, Default)]
+pub struct PriceHistory {
+    /// Points by symbol, keyed by timestamp in UNIX milliseconds
+    series: HashMap<String, BTreeMap<i64, f64>>,
+    retention: Option<Duration>,
+}
+
+impl PriceHistory {
+    /// Creates an empty history that keeps every point.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only keep points within `retention` of each symbol's latest point
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Applies a price update, historical or live, returning the number of new
+    /// points. Other messages are ignored.
+    pub fn apply(&mut self, message: &Message) -> Result<usize, String> {
+        if !matches!(
+            message.topic,
+            Topic::CryptoPrices | Topic::CryptoPricesChainlink | Topic::EquityPrices
+        ) || message.message_type != MessageType::Update
+        {
+            return Ok(0);
+        }
+        Ok(match message.price_update()? {
+            PriceUpdate::CryptoPrice(price) => {
+                usize::from(self.insert(&price.symbol, price.timestamp, price.value))
+            }
+            PriceUpdate::EquityPrice(price) => {
+                usize::from(self.insert(&price.symbol, price.timestamp, price.value))
+            }
+            PriceUpdate::CryptoPriceHistorical(history) => {
+                self.seed(&history.symbol, &history.data)
+            }
+            PriceUpdate::EquityPriceHistorical(history) => {
+                self.seed(&history.symbol, &history.data)
+            }
+        })
+    }
+
+    /// Adds historical points, returning the number of new ones.
+    pub fn seed(&mut self, symbol: &str, points: &[PriceDataPoint]) -> usize {
+        let series = self.series.entry(symbol.to_string()).or_default();
+        let added = points
+            .iter()
+            .filter(|point| series.insert(point.timestamp, point.value).is_none())
+            .count();
+        self.prune(symbol);
+        added
+    }
+
+    /// Adds a point, returning whether its timestamp was new. A point at an existing
+    /// timestamp replaces its value.
+    pub fn insert(&mut self, symbol: &str, timestamp: i64, value: f64) -> bool {
+        let added = self
+            .series
+            .entry(symbol.to_string())
+            .or_default()
+            .insert(timestamp, value)
+            .is_none();
+        self.prune(symbol);
+        added
+    }
+
+    fn prune(&mut self, symbol: &str) {
+        let (Some(retention), Some(series)) = (self.retention, self.series.get_mut(symbol)) else {
+            return;
+        };
+        if let Some((&latest, _)) = series.last_key_value() {
+            let cutoff = latest - retention.as_millis() as i64;
+            *series = series.split_off(&cutoff);
+        }
+    }
+
+    /// Returns the symbols with history.
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.series.keys().map(String::as_str)
+    }
+
+    /// Returns the number of points stored for a symbol.
+    pub fn len(&self, symbol: &str) -> usize {
+        self.series.get(symbol).map_or(0, BTreeMap::len)
+    }
+
+    /// Returns the latest point of a symbol.
+    pub fn latest(&self, symbol: &str) -> Option<PriceDataPoint> {
+        self.series
+            .get(symbol)?
+            .last_key_value()
+            .map(|(&timestamp, &value)| PriceDataPoint { timestamp, value })
+    }
+
+    /// Returns the last value at or before `timestamp`.
+    pub fn value_at(&self, symbol: &str, timestamp: i64) -> Option<f64> {
+        self.series
+            .get(symbol)?
+            .range(..=timestamp)
+            .next_back()
+            .map(|(_, &value)| value)
+    }
+
+    /// Returns the points with `from <= timestamp <= to`, oldest first.
+    pub fn range(&self, symbol: &str, from: i64, to: i64) -> Vec<PriceDataPoint> {
+        let Some(series) = self.series.get(symbol).filter(|_| from <= to) else {
+            return Vec::new();
+        };
+        series
+            .range(from..=to)
+            .map(|(&timestamp, &value)| PriceDataPoint { timestamp, value })
+            .collect()
+    }
+
+    /// Resamples `from..=to` to one point per `interval`, aligned to multiples of the
+    /// interval since the epoch.
+    ///
+    /// Each point holds the last value at or before the end of its interval, so
+    /// intervals without ticks carry the previous value forward. Intervals before the
+    /// first known value are left out.
+    pub fn resample(
+        &self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+        interval: Duration,
+    ) -> Vec<PriceDataPoint> {
+        let step = interval.as_millis().max(1) as i64;
+        let mut points = Vec::new();
+        let mut start = from - from.rem_euclid(step);
+        while start <= to {
+            let end = (start + step - 1).min(to);
+            if let Some(value) = self.value_at(symbol, end) {
+                points.push(PriceDataPoint {
+                    timestamp: start,
+                    value,
+                });
+            }
+            start += step;
+        }
+        points
+    }
+}
@@ -1,10 +1,58 @@
+pub mod builders;
+pub mod candles;
 pub mod client;
+pub mod comments;
+pub mod credentials;
+mod deflate;
+pub mod divergence;
+pub mod filter;
+pub mod history;
+pub mod manager;
+pub mod metrics;
 pub mod model;
+pub mod orders;
+pub mod positions;
+pub mod proxy;
+pub mod ratelimit;
+pub mod redundant;
+pub mod rfq;
+pub mod rules;
+pub mod settlement;
+pub mod sharded;
+pub mod tape;
+mod trace;
 pub mod types;
+mod util;
 
-pub use client::RealTimeDataClient;
+pub use builders::{
+    ActivitySubscription, ChainlinkPriceSubscription, ClobBookSubscription, ClobMarketSubscription,
+    ClobUserSubscription, CommentsSubscription, CryptoPriceSubscription, EquityPriceSubscription,
+    PriceSubscription, RfqSubscription,
+};
+pub use candles::{Candle, CandleAggregator, CandleConfig};
+pub use client::{BatchConfig, RealTimeDataClient, ReconnectConfig};
+pub use comments::{CommentNode, CommentStore, DEFAULT_ORPHAN_REACTION_LIMIT};
+pub use credentials::{CredentialProvider, RotatingCredentials, Secret};
+pub use divergence::{DivergenceConfig, DivergenceEvent, DivergenceMonitor, SpreadSample};
+pub use filter::{CompositeFilter, FilterField};
+pub use history::PriceHistory;
+pub use manager::{SubscriptionHandle, SubscriptionManager};
+pub use metrics::{MetricsHandle, MetricsSnapshot};
 pub use model::{
     ClobApiKeyCreds, ConnectionStatus, GammaAuth, Message, MessageType, Subscription,
     SubscriptionFilter, SubscriptionMessage, Topic,
 };
+pub use orders::{OrderEvent, OrderPhase, OrderState, OrderTracker};
+pub use positions::{Fill, Position, PositionLedger};
+pub use proxy::{ProxyConfig, ProxyKind};
+pub use ratelimit::{RateLimit, RateLimitConfig};
+pub use redundant::{dedup_key, LegHealth, RedundantClient};
+pub use rfq::{RfqAction, RfqBook, RfqCloseReason, RfqEntry, RfqEvent, RfqState};
+pub use rules::{Condition, MessageRouter, Rule, RuleSet, RulesHandle};
+pub use settlement::{
+    SettlementConfig, SettlementEvent, SettlementStatus, SettlementTracker, TradeSettlement,
+    Transition,
+};
+pub use sharded::{ShardHealth, ShardKey, ShardedClient};
+pub use tape::{MarketTape, TapeConfig, TradeTape, WindowStats};
 pub use types::*;
\ No newline at end of file
@@ -0,0 +1,170 @@
+use crate::client::Command;
+use crate::model::{Subscription, SubscriptionMessage};
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::mpsc;
+
+struct Entry {
+    subscription: Subscription,
+    holders: usize,
+}
+
+struct Shared {
+    command_tx: mpsc::UnboundedSender<Command>,
+    /// Whether a credential provider supplies `clob_user` credentials
+    provided_auth: bool,
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl Shared {
+    fn release(&self, subscription: &Subscription) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(index) = entries
+            .iter()
+            .position(|entry| entry.subscription.same_stream(subscription))
+        else {
+            return;
+        };
+        entries[index].holders -= 1;
+        if entries[index].holders == 0 {
+            let entry = entries.swap_remove(index);
+            // The connection may already be gone, in which case there is nothing to undo.
+            let _ = self
+                .command_tx
+                .send(Command::Unsubscribe(SubscriptionMessage {
+                    subscriptions: vec![entry.subscription],
+                }));
+        }
+    }
+}
+
+/// Reference-counts subscriptions shared by several consumers of one client.
+///
+/// Identical subscriptions (same topic, type and filters) are sent to the server
+/// once, and only unsubscribed when the last [`SubscriptionHandle`] for them is
+/// dropped. Subscriptions made directly on the client are not counted.
+#[derive(Clone)]
+pub struct SubscriptionManager {
+    shared: Arc<Shared>,
+}
+
+impl SubscriptionManager {
+    pub(crate) fn new(command_tx: mpsc::UnboundedSender<Command>, provided_auth: bool) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                command_tx,
+                provided_auth,
+                entries: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Subscribes to a data stream, sending the subscription only if no other
+    /// handle holds it yet.
+    pub fn subscribe(&self, subscription: Subscription) -> Result<SubscriptionHandle, String> {
+        subscription.validate_with(self.shared.provided_auth)?;
+
+        let mut entries = self
+            .shared
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match entries
+            .iter_mut()
+            .find(|entry| entry.subscription.same_stream(&subscription))
+        {
+            Some(entry) => entry.holders += 1,
+            None => {
+                self.shared
+                    .command_tx
+                    .send(Command::Subscribe(
+                        SubscriptionMessage {
+                            subscriptions: vec![subscription.clone()],
+                        },
+                        None,
+                    ))
+                    .map_err(|_| "Failed to send subscribe command".to_string())?;
+                entries.push(Entry {
+                    subscription: subscription.clone(),
+                    holders: 1,
+                });
+            }
+        }
+
+        Ok(SubscriptionHandle {
+            shared: self.shared.clone(),
+            subscription,
+        })
+    }
+
+    /// Returns the number of handles holding an identical subscription.
+    pub fn holders(&self, subscription: &Subscription) -> usize {
+        self.shared
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .find(|entry| entry.subscription.same_stream(subscription))
+            .map_or(0, |entry| entry.holders)
+    }
+
+    /// Returns the distinct subscriptions currently held.
+    pub fn active(&self) -> Vec<Subscription> {
+        self.shared
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|entry| entry.subscription.clone())
+            .collect()
+    }
+}
+
+/// Keeps a managed subscription alive; the wire `unsubscribe` is sent when the last
+/// handle for it is dropped.
+pub struct SubscriptionHandle {
+    shared: Arc<Shared>,
+    subscription: Subscription,
+}
+
+impl SubscriptionHandle {
+    /// Returns the held subscription.
+    pub fn subscription(&self) -> &Subscription {
+        &self.subscription
+    }
+}
+
+impl Clone for SubscriptionHandle {
+    fn clone(&self) -> Self {
+        let mut entries = self
+            .shared
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = entries
+            .iter_mut()
+            .find(|entry| entry.subscription.same_stream(&self.subscription))
+        {
+            entry.holders += 1;
+        }
+        Self {
+            shared: self.shared.clone(),
+            subscription: self.subscription.clone(),
+        }
+    }
+}
+
+impl Drop for SubscriptionHandle {
+    fn drop(&mut self) {
+        self.shared.release(&self.subscription);
+    }
+}
+
+impl std::fmt::Debug for SubscriptionHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubscriptionHandle")
+            .field("topic", &self.subscription.topic)
+            .field("type", &self.subscription.subscription_type)
+            .field("filters", &self.subscription.filters)
+            .finish()
+    }
+}
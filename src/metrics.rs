@@ -0,0 +1,393 @@
+use crate::model::{MessageType, Topic};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Counters shared between the client and its connection task.
+#[derive(Debug, Default)]
+pub(crate) struct ClientMetrics {
+    connected: AtomicBool,
+    messages_received: Mutex<HashMap<(&'static str, &'static str), u64>>,
+    frames_received: AtomicU64,
+    frames_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    decode_errors: AtomicU64,
+    reconnects: AtomicU64,
+    subscriptions: AtomicU64,
+    messages_enqueued: AtomicU64,
+    messages_dequeued: AtomicU64,
+    ping_rtt_micros: AtomicU64,
+    last_message_at_millis: AtomicU64,
+    compression_negotiated: AtomicBool,
+    compressed_bytes: AtomicU64,
+    decompressed_bytes: AtomicU64,
+    rate_limited_frames: AtomicU64,
+    rate_limit_delay_micros: AtomicU64,
+    outbound_queue: AtomicU64,
+}
+
+impl ClientMetrics {
+    pub(crate) fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_frame_received(&self, bytes: usize) {
+        self.frames_received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_frame_sent(&self, bytes: usize) {
+        self.frames_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Records a decoded message that was handed to the client channel.
+    pub(crate) fn record_message(&self, topic: &Topic, message_type: &MessageType) {
+        if let Ok(mut messages) = self.messages_received.lock() {
+            *messages
+                .entry((topic.as_str(), message_type.as_str()))
+                .or_default() += 1;
+        }
+        self.messages_enqueued.fetch_add(1, Ordering::Relaxed);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.last_message_at_millis.store(now, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_message_dequeued(&self) {
+        self.messages_dequeued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_decode_error(&self) {
+        self.decode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_subscriptions(&self, count: usize) {
+        self.subscriptions.store(count as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_ping_rtt(&self, rtt: Duration) {
+        self.ping_rtt_micros
+            .store(rtt.as_micros().max(1) as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_compression_negotiated(&self, negotiated: bool) {
+        self.compression_negotiated
+            .store(negotiated, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_compressed_message(&self, compressed: usize, decompressed: usize) {
+        self.compressed_bytes
+            .fetch_add(compressed as u64, Ordering::Relaxed);
+        self.decompressed_bytes
+            .fetch_add(decompressed as u64, Ordering::Relaxed);
+    }
+
+    /// Records a frame that waited `delay` for the outbound rate limiter.
+    pub(crate) fn record_rate_limit_delay(&self, delay: Duration) {
+        self.rate_limited_frames.fetch_add(1, Ordering::Relaxed);
+        self.rate_limit_delay_micros
+            .fetch_add(delay.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_outbound_queue(&self, depth: usize) {
+        self.outbound_queue.store(depth as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> MetricsSnapshot {
+        let messages_received = self
+            .messages_received
+            .lock()
+            .map(|messages| {
+                messages
+                    .iter()
+                    .map(|(&(topic, message_type), &count)| {
+                        ((topic.to_string(), message_type.to_string()), count)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let ping_rtt = match self.ping_rtt_micros.load(Ordering::Relaxed) {
+            0 => None,
+            micros => Some(Duration::from_micros(micros)),
+        };
+        let last_message_at = match self.last_message_at_millis.load(Ordering::Relaxed) {
+            0 => None,
+            millis => Some(UNIX_EPOCH + Duration::from_millis(millis)),
+        };
+
+        MetricsSnapshot {
+            connected: self.connected.load(Ordering::Relaxed),
+            messages_received,
+            frames_received: self.frames_received.load(Ordering::Relaxed),
+            frames_sent: self.frames_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            decode_errors: self.decode_errors.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            subscriptions: self.subscriptions.load(Ordering::Relaxed),
+            channel_depth: self
+                .messages_enqueued
+                .load(Ordering::Relaxed)
+                .saturating_sub(self.messages_dequeued.load(Ordering::Relaxed)),
+            ping_rtt,
+            last_message_at,
+            compression_negotiated: self.compression_negotiated.load(Ordering::Relaxed),
+            compressed_bytes: self.compressed_bytes.load(Ordering::Relaxed),
+            decompressed_bytes: self.decompressed_bytes.load(Ordering::Relaxed),
+            rate_limited_frames: self.rate_limited_frames.load(Ordering::Relaxed),
+            rate_limit_delay: Duration::from_micros(
+                self.rate_limit_delay_micros.load(Ordering::Relaxed),
+            ),
+            outbound_queue: self.outbound_queue.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// How long the Prometheus listener waits for a request before closing the connection.
+#[cfg(feature = "prometheus")]
+const PROMETHEUS_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Cloneable handle to a client's metrics, usable without borrowing the client.
+#[derive(Debug, Clone)]
+pub struct MetricsHandle {
+    pub(crate) metrics: Arc<ClientMetrics>,
+}
+
+impl MetricsHandle {
+    /// Returns a snapshot of the client metrics.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Serves the metrics in Prometheus text format on `addr` (e.g. `127.0.0.1:9100`).
+    ///
+    /// The server answers `GET /metrics` until the returned task is aborted.
+    /// Connections that do not send a complete request within 5 seconds are closed.
+    #[cfg(feature = "prometheus")]
+    pub async fn serve_prometheus(
+        self,
+        addr: impl tokio::net::ToSocketAddrs,
+    ) -> std::io::Result<tokio::task::JoinHandle<()>> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        Ok(tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let handle = self.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    let read = async {
+                        while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+                            match stream.read(&mut buf).await {
+                                Ok(0) | Err(_) => return false,
+                                Ok(n) => request.extend_from_slice(&buf[..n]),
+                            }
+                        }
+                        true
+                    };
+                    if !matches!(tokio::time::timeout(PROMETHEUS_REQUEST_TIMEOUT, read).await, Ok(true)) {
+                        return;
+                    }
+
+                    let request = String::from_utf8_lossy(&request);
+                    let path = request.split_whitespace().nth(1).unwrap_or_default();
+                    let response = if path == "/metrics" || path == "/" {
+                        let body = handle.snapshot().to_prometheus();
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            body.len(),
+                            body
+                        )
+                    } else {
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_string()
+                    };
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        }))
+    }
+}
+
+/// Point-in-time view of the client metrics.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Whether the WebSocket is currently connected
+    pub connected: bool,
+    /// Decoded messages received, by `(topic, type)`
+    pub messages_received: BTreeMap<(String, String), u64>,
+    /// WebSocket frames received, including pongs and undecodable frames
+    pub frames_received: u64,
+    /// WebSocket frames sent
+    pub frames_sent: u64,
+    /// Bytes received in frame payloads (after decompression)
+    pub bytes_received: u64,
+    /// Bytes sent in frame payloads
+    pub bytes_sent: u64,
+    /// Frames that looked like messages but failed to decode
+    pub decode_errors: u64,
+    /// Successful reconnections
+    pub reconnects: u64,
+    /// Active subscriptions
+    pub subscriptions: u64,
+    /// Messages waiting in the client channel
+    pub channel_depth: u64,
+    /// Round trip time of the last answered ping
+    pub ping_rtt: Option<Duration>,
+    /// When the last message was received
+    pub last_message_at: Option<SystemTime>,
+    /// Whether the server accepted the permessage-deflate extension
+    pub compression_negotiated: bool,
+    /// Payload bytes of compressed messages as received on the wire
+    pub compressed_bytes: u64,
+    /// Size of the same messages after inflation
+    pub decompressed_bytes: u64,
+    /// Outbound frames held back by the rate limiter
+    pub rate_limited_frames: u64,
+    /// Total time outbound frames spent waiting for the rate limiter
+    pub rate_limit_delay: Duration,
+    /// Outbound frames currently queued by the rate limiter
+    pub outbound_queue: u64,
+}
+
+impl MetricsSnapshot {
+    /// Ratio of inflated to compressed bytes, if any compressed message was received.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        if self.compressed_bytes == 0 {
+            None
+        } else {
+            Some(self.decompressed_bytes as f64 / self.compressed_bytes as f64)
+        }
+    }
+
+    /// Renders the snapshot in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: f64| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            let _ = writeln!(out, "{} {}", name, value);
+        };
+
+        metric(
+            "rtds_connected",
+            "gauge",
+            "Whether the WebSocket is connected.",
+            self.connected as u8 as f64,
+        );
+        metric(
+            "rtds_frames_received_total",
+            "counter",
+            "WebSocket frames received.",
+            self.frames_received as f64,
+        );
+        metric(
+            "rtds_frames_sent_total",
+            "counter",
+            "WebSocket frames sent.",
+            self.frames_sent as f64,
+        );
+        metric(
+            "rtds_bytes_received_total",
+            "counter",
+            "Bytes received in frame payloads.",
+            self.bytes_received as f64,
+        );
+        metric(
+            "rtds_bytes_sent_total",
+            "counter",
+            "Bytes sent in frame payloads.",
+            self.bytes_sent as f64,
+        );
+        metric(
+            "rtds_decode_errors_total",
+            "counter",
+            "Frames that failed to decode.",
+            self.decode_errors as f64,
+        );
+        metric(
+            "rtds_reconnects_total",
+            "counter",
+            "Successful reconnections.",
+            self.reconnects as f64,
+        );
+        metric(
+            "rtds_subscriptions",
+            "gauge",
+            "Active subscriptions.",
+            self.subscriptions as f64,
+        );
+        metric(
+            "rtds_channel_depth",
+            "gauge",
+            "Messages waiting in the client channel.",
+            self.channel_depth as f64,
+        );
+        metric(
+            "rtds_rate_limited_frames_total",
+            "counter",
+            "Outbound frames held back by the rate limiter.",
+            self.rate_limited_frames as f64,
+        );
+        metric(
+            "rtds_rate_limit_delay_seconds_total",
+            "counter",
+            "Time outbound frames spent waiting for the rate limiter.",
+            self.rate_limit_delay.as_secs_f64(),
+        );
+        metric(
+            "rtds_outbound_queue_depth",
+            "gauge",
+            "Outbound frames queued by the rate limiter.",
+            self.outbound_queue as f64,
+        );
+        if let Some(rtt) = self.ping_rtt {
+            metric(
+                "rtds_ping_rtt_seconds",
+                "gauge",
+                "Round trip time of the last answered ping.",
+                rtt.as_secs_f64(),
+            );
+        }
+        if let Some(at) = self.last_message_at {
+            metric(
+                "rtds_last_message_timestamp_seconds",
+                "gauge",
+                "Unix time of the last received message.",
+                at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64(),
+            );
+        }
+        if let Some(ratio) = self.compression_ratio() {
+            metric(
+                "rtds_compression_ratio",
+                "gauge",
+                "Ratio of inflated to compressed bytes.",
+                ratio,
+            );
+        }
+
+        let _ = writeln!(out, "# HELP rtds_messages_received_total Decoded messages received.");
+        let _ = writeln!(out, "# TYPE rtds_messages_received_total counter");
+        for ((topic, message_type), count) in &self.messages_received {
+            let _ = writeln!(
+                out,
+                "rtds_messages_received_total{{topic=\"{}\",type=\"{}\"}} {}",
+                topic, message_type, count
+            );
+        }
+        out
+    }
+}
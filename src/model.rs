@@ -1,17 +1,20 @@
+use crate::credentials::Secret;
+use crate::types::PriceUpdate;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// API key credentials for CLOB authentication.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ClobApiKeyCreds {
     /// API key used for authentication
     pub key: String,
 
     /// API secret associated with the key
-    pub secret: String,
+    pub secret: Secret,
 
     /// Passphrase required for authentication
-    pub passphrase: String,
+    pub passphrase: Secret,
 }
 
 /// Authentication details for Gamma authentication.
@@ -54,6 +57,41 @@ impl Topic {
             Topic::ClobMarket => "clob_market",
         }
     }
+
+    /// Message types the server publishes on this topic, excluding the `*` wildcard.
+    pub fn message_types(&self) -> &'static [MessageType] {
+        match self {
+            Topic::Activity => &[MessageType::Trades, MessageType::OrdersMatched],
+            Topic::Comments => &[
+                MessageType::CommentCreated,
+                MessageType::CommentRemoved,
+                MessageType::ReactionCreated,
+                MessageType::ReactionRemoved,
+            ],
+            Topic::Rfq => &[
+                MessageType::RequestCreated,
+                MessageType::RequestEdited,
+                MessageType::RequestCanceled,
+                MessageType::RequestExpired,
+                MessageType::QuoteCreated,
+                MessageType::QuoteEdited,
+                MessageType::QuoteCanceled,
+                MessageType::QuoteExpired,
+            ],
+            Topic::CryptoPrices | Topic::CryptoPricesChainlink | Topic::EquityPrices => {
+                &[MessageType::Update]
+            }
+            Topic::ClobUser => &[MessageType::Order, MessageType::Trade],
+            Topic::ClobMarket => &[
+                MessageType::PriceChange,
+                MessageType::AggOrderbook,
+                MessageType::LastTradePrice,
+                MessageType::TickSizeChange,
+                MessageType::MarketCreated,
+                MessageType::MarketResolved,
+            ],
+        }
+    }
 }
 
 impl fmt::Display for Topic {
@@ -325,6 +363,147 @@ impl Subscription {
         self.gamma_auth = Some(auth);
         self
     }
+
+    /// Checks that the topic, type, filters and credentials form a combination the
+    /// server accepts, returning a description of the first problem found.
+    ///
+    /// The server ignores invalid subscriptions without reporting an error, so
+    /// [`RealTimeDataClient::subscribe`](crate::RealTimeDataClient::subscribe) runs
+    /// this check before anything is sent.
+    pub fn validate(&self) -> Result<(), String> {
+        self.validate_with(false)
+    }
+
+    /// Like [`Subscription::validate`], but allows `clob_user` without credentials
+    /// when a credential provider will supply them at send time.
+    pub(crate) fn validate_with(&self, provided_auth: bool) -> Result<(), String> {
+        let topic = &self.topic;
+        let message_type = &self.subscription_type;
+
+        if *message_type != MessageType::All && !topic.message_types().contains(message_type) {
+            return Err(format!(
+                "Message type '{}' is not published on topic '{}'",
+                message_type, topic
+            ));
+        }
+
+        match (topic, &self.clob_auth) {
+            (Topic::ClobUser, None) if !provided_auth => {
+                return Err("Topic 'clob_user' requires clob_auth credentials".to_string())
+            }
+            (Topic::ClobUser, _) => {}
+            (_, Some(_)) => {
+                return Err(format!(
+                    "Topic '{}' does not accept clob_auth credentials",
+                    topic
+                ))
+            }
+            (_, None) => {}
+        }
+
+        let filters = match self.filters {
+            Some(ref filters) => filters,
+            None if *message_type == MessageType::PriceChange => {
+                return Err("Message type 'price_change' requires a token ID filter".to_string())
+            }
+            None => return Ok(()),
+        };
+        let filter: serde_json::Value = serde_json::from_str(filters)
+            .map_err(|e| format!("Filters for topic '{}' are not valid JSON: {}", topic, e))?;
+
+        validate_filter(topic, message_type, &filter)
+            .map_err(|e| format!("Invalid filters for topic '{}': {}", topic, e))
+    }
+
+    /// Returns true if the message belongs to this subscription's topic and type.
+    pub(crate) fn covers(&self, message: &Message) -> bool {
+        self.topic == message.topic
+            && (self.subscription_type == MessageType::All
+                || self.subscription_type == message.message_type)
+    }
+
+    /// Returns true if both subscriptions target the same topic, type and filters.
+    pub(crate) fn same_stream(&self, other: &Subscription) -> bool {
+        self.topic == other.topic
+            && self.subscription_type == other.subscription_type
+            && self.filters == other.filters
+    }
+}
+
+/// Checks a parsed filter against the shapes listed in the topic table.
+fn validate_filter(
+    topic: &Topic,
+    message_type: &MessageType,
+    filter: &serde_json::Value,
+) -> Result<(), String> {
+    use serde_json::Value;
+
+    let object = |expected: &str| {
+        filter
+            .as_object()
+            .ok_or_else(|| format!("expected an object like {}", expected))
+    };
+
+    match topic {
+        Topic::Activity => {
+            let fields = object(r#"{"event_slug":"..."} or {"market_slug":"..."}"#)?;
+            let mut keys = fields.keys();
+            match (keys.next().map(String::as_str), keys.next()) {
+                (Some(key @ ("event_slug" | "market_slug")), None) => match fields[key] {
+                    Value::String(_) => Ok(()),
+                    _ => Err(format!("'{}' must be a string", key)),
+                },
+                (Some(_), Some(_)) => {
+                    Err("expected exactly one of 'event_slug' or 'market_slug'".to_string())
+                }
+                (Some(key), None) => Err(format!("unknown field '{}'", key)),
+                (None, _) => Err("expected one of 'event_slug' or 'market_slug'".to_string()),
+            }
+        }
+        Topic::Comments => {
+            let fields = object(r#"{"parentEntityID":1,"parentEntityType":"Event"}"#)?;
+            if let Some(key) = fields
+                .keys()
+                .find(|key| *key != "parentEntityID" && *key != "parentEntityType")
+            {
+                return Err(format!("unknown field '{}'", key));
+            }
+            if !fields.get("parentEntityID").is_some_and(Value::is_u64) {
+                return Err("'parentEntityID' must be a number".to_string());
+            }
+            if !fields.get("parentEntityType").is_some_and(Value::is_string) {
+                return Err("'parentEntityType' must be a string".to_string());
+            }
+            Ok(())
+        }
+        Topic::CryptoPrices | Topic::CryptoPricesChainlink | Topic::EquityPrices => {
+            let fields = object(r#"{"symbol":"..."}"#)?;
+            if let Some(key) = fields.keys().find(|key| *key != "symbol") {
+                return Err(format!("unknown field '{}'", key));
+            }
+            match fields.get("symbol") {
+                Some(Value::String(_)) => Ok(()),
+                _ => Err("'symbol' must be a string".to_string()),
+            }
+        }
+        Topic::ClobMarket
+            if matches!(
+                message_type,
+                MessageType::MarketCreated | MessageType::MarketResolved
+            ) =>
+        {
+            Err(format!(
+                "message type '{}' does not accept filters",
+                message_type
+            ))
+        }
+        Topic::ClobMarket => match filter.as_array() {
+            Some(ids) if ids.is_empty() => Err("token ID list is empty".to_string()),
+            Some(ids) if ids.iter().all(Value::is_string) => Ok(()),
+            _ => Err(r#"expected a list of token IDs like ["100","200"]"#.to_string()),
+        },
+        Topic::Rfq | Topic::ClobUser => Err("this topic does not accept filters".to_string()),
+    }
 }
 
 /// Message structure for subscription requests.
@@ -354,6 +533,24 @@ pub struct Message {
     pub connection_id: String,
 }
 
+impl Message {
+    /// Deserializes the payload into a typed struct such as [`crate::ClobOrder`].
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, String> {
+        T::deserialize(&self.payload).map_err(|e| {
+            format!(
+                "Failed to decode {}/{} payload: {}",
+                self.topic, self.message_type, e
+            )
+        })
+    }
+
+    /// Decodes the payload of a price topic `update`, choosing the crypto or equity
+    /// variant from the message's topic.
+    pub fn price_update(&self) -> Result<PriceUpdate, String> {
+        PriceUpdate::from_payload(&self.topic, &self.payload)
+    }
+}
+
 /// Represents websocket connection status
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConnectionStatus {
@@ -0,0 +1,360 @@
+//! Order lifecycle tracking for the authenticated `clob_user` feed.
+//!
+//! `order` updates (placements, updates and cancellations) and `trade` fills arrive
+//! as independent messages. An [`OrderTracker`] folds both into the live state of each
+//! order and reports the transitions as [`OrderEvent`]s.
+
+use crate::model::{Message, MessageType, Topic};
+use crate::types::{ClobOrder, ClobTrade};
+use crate::util::{parse_number, unix_now};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Sizes closer than this are considered equal.
+const SIZE_EPSILON: f64 = 1e-9;
+/// Fills held back for orders that are not tracked yet. Trades also name other
+/// users' maker orders, which never arrive, so the oldest are dropped past this.
+const MAX_PENDING_FILLS: usize = 1024;
+
+/// Where an order is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderPhase {
+    /// Resting on the book with nothing matched
+    Open,
+    /// Resting on the book with part of its size matched
+    PartiallyFilled,
+    /// Fully matched
+    Filled,
+    /// Canceled before it was fully matched
+    Canceled,
+    /// Reached its expiration before it was fully matched
+    Expired,
+}
+
+impl OrderPhase {
+    /// Returns whether the order can no longer change.
+    pub fn is_closed(self) -> bool {
+        matches!(self, Self::Filled | Self::Canceled | Self::Expired)
+    }
+}
+
+/// Live state of a single order.
+#[derive(Debug, Clone)]
+pub struct OrderState {
+    /// Unique order hash identifier
+    pub id: String,
+    /// ERC1155 token ID of the conditional token
+    pub asset_id: String,
+    /// Condition ID or market identifier
+    pub market: String,
+    /// Order outcome: YES / NO
+    pub outcome: String,
+    /// Side of the order: BUY or SELL
+    pub side: String,
+    /// Type of order: GTC, GTD, FOK, FAK
+    pub order_type: String,
+    /// Order price
+    pub price: f64,
+    /// Size of the order at placement
+    pub original_size: f64,
+    /// Size matched so far, from order updates or trade fills, whichever is ahead
+    pub size_matched: f64,
+    /// Status as last reported by the server
+    pub status: String,
+    /// Lifecycle phase
+    pub phase: OrderPhase,
+    /// Expiration as a UNIX timestamp in seconds, if the order has one
+    pub expiration: Option<i64>,
+    /// IDs of the trades that matched this order, in arrival order
+    pub trade_ids: Vec<String>,
+}
+
+impl OrderState {
+    /// Size still open on the book.
+    pub fn remaining(&self) -> f64 {
+        (self.original_size - self.size_matched).max(0.0)
+    }
+}
+
+/// A lifecycle transition of an order.
+#[derive(Debug, Clone)]
+pub enum OrderEvent {
+    /// The order was seen for the first time
+    Opened(OrderState),
+    /// More of the order was matched, but some size remains
+    PartiallyFilled {
+        order: OrderState,
+        /// The trade that matched it, if the fill came from a trade message
+        trade_id: Option<String>,
+    },
+    /// The order was fully matched
+    Filled {
+        order: OrderState,
+        /// The trade that completed it, if the fill came from a trade message
+        trade_id: Option<String>,
+    },
+    /// The order was canceled
+    Canceled(OrderState),
+    /// The order expired
+    Expired(OrderState),
+}
+
+impl OrderEvent {
+    /// Returns the order state after the transition.
+    pub fn order(&self) -> &OrderState {
+        match self {
+            Self::Opened(order)
+            | Self::PartiallyFilled { order, .. }
+            | Self::Filled { order, .. }
+            | Self::Canceled(order)
+            | Self::Expired(order) => order,
+        }
+    }
+}
+
+struct TrackedOrder {
+    state: OrderState,
+    /// `size_matched` from the latest order update
+    reported: f64,
+    /// Sum of the fills seen in trade messages
+    traded: f64,
+}
+
+impl TrackedOrder {
+    /// Recomputes the matched size and returns the fill event if it grew.
+    fn refresh_fill(&mut self, trade_id: Option<&str>) -> Option<OrderEvent> {
+        let matched = self.reported.max(self.traded).min(self.state.original_size);
+        if matched <= self.state.size_matched + SIZE_EPSILON {
+            return None;
+        }
+        self.state.size_matched = matched;
+        let trade_id = trade_id.map(str::to_string);
+        if self.state.remaining() <= SIZE_EPSILON {
+            self.state.phase = OrderPhase::Filled;
+            Some(OrderEvent::Filled {
+                order: self.state.clone(),
+                trade_id,
+            })
+        } else {
+            self.state.phase = OrderPhase::PartiallyFilled;
+            Some(OrderEvent::PartiallyFilled {
+                order: self.state.clone(),
+                trade_id,
+            })
+        }
+    }
+}
+
+/// A trade fill for an order that was not tracked when the trade arrived.
+struct PendingFill {
+    trade_id: String,
+    order_id: String,
+    amount: f64,
+}
+
+/// Tracks the live state of the user's orders from `clob_user` messages.
+///
+/// Fills are counted once per trade and order, so repeated trade messages for the
+/// same match (for example as its status moves from MATCHED to MINED) do not inflate
+/// the matched size. A trade can arrive before the order it matched; its fill is held
+/// back and applied when the order arrives. Settlement failures are not rolled back
+/// here.
+#[derive(Default)]
+pub struct OrderTracker {
+    orders: HashMap<String, TrackedOrder>,
+    /// Fills already counted, as (trade ID, order ID)
+    fills: HashSet<(String, String)>,
+    /// Fills for orders not tracked yet, oldest first
+    pending: VecDeque<PendingFill>,
+}
+
+impl OrderTracker {
+    /// Creates an empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies a message, ignoring anything other than `clob_user` orders and trades.
+    pub fn apply(&mut self, message: &Message) -> Result<Vec<OrderEvent>, String> {
+        match (&message.topic, &message.message_type) {
+            (Topic::ClobUser, MessageType::Order) => self.apply_order(&message.decode()?),
+            (Topic::ClobUser, MessageType::Trade) => self.apply_trade(&message.decode()?),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Applies an order placement, update or cancellation.
+    pub fn apply_order(&mut self, order: &ClobOrder) -> Result<Vec<OrderEvent>, String> {
+        let original_size = parse_number("original_size", &order.original_size)?;
+        let size_matched = parse_number("size_matched", &order.size_matched)?;
+        let price = parse_number("price", &order.price)?;
+        let expiration = order
+            .expiration
+            .parse::<i64>()
+            .ok()
+            .filter(|expiration| *expiration > 0);
+
+        let mut events = Vec::new();
+        let tracked = self.orders.entry(order.id.clone()).or_insert_with(|| {
+            let state = OrderState {
+                id: order.id.clone(),
+                asset_id: order.asset_id.clone(),
+                market: order.market.clone(),
+                outcome: order.outcome.clone(),
+                side: order.side.clone(),
+                order_type: order.order_type.clone(),
+                price,
+                original_size,
+                size_matched: 0.0,
+                status: order.status.clone(),
+                phase: OrderPhase::Open,
+                expiration,
+                trade_ids: Vec::new(),
+            };
+            events.push(OrderEvent::Opened(state.clone()));
+            TrackedOrder {
+                state,
+                reported: 0.0,
+                traded: 0.0,
+            }
+        });
+
+        // A new order picks up the fills of trades that arrived before it.
+        let mut trade_id = None;
+        if !events.is_empty() && self.pending.iter().any(|fill| fill.order_id == order.id) {
+            let (buffered, pending): (VecDeque<_>, VecDeque<_>) = std::mem::take(&mut self.pending)
+                .into_iter()
+                .partition(|fill| fill.order_id == order.id);
+            self.pending = pending;
+            for fill in buffered {
+                if self.fills.insert((fill.trade_id.clone(), fill.order_id)) {
+                    tracked.state.trade_ids.push(fill.trade_id.clone());
+                    tracked.traded += fill.amount;
+                    trade_id = Some(fill.trade_id);
+                }
+            }
+        }
+
+        tracked.state.status = order.status.clone();
+        tracked.state.original_size = original_size;
+        tracked.reported = tracked.reported.max(size_matched);
+        if tracked.state.phase.is_closed() {
+            return Ok(events);
+        }
+        events.extend(tracked.refresh_fill(trade_id.as_deref()));
+
+        let canceled = order.update_type.eq_ignore_ascii_case("CANCELLATION")
+            || order.status.eq_ignore_ascii_case("CANCELED");
+        if canceled && !tracked.state.phase.is_closed() {
+            if tracked.state.expiration.is_some_and(|at| at <= unix_now()) {
+                tracked.state.phase = OrderPhase::Expired;
+                events.push(OrderEvent::Expired(tracked.state.clone()));
+            } else {
+                tracked.state.phase = OrderPhase::Canceled;
+                events.push(OrderEvent::Canceled(tracked.state.clone()));
+            }
+        }
+        Ok(events)
+    }
+
+    /// Applies a trade, crediting the taker order and every maker order, or holding
+    /// the fill back if the order is not tracked yet.
+    pub fn apply_trade(&mut self, trade: &ClobTrade) -> Result<Vec<OrderEvent>, String> {
+        let mut fills = vec![(
+            trade.taker_order_id.as_str(),
+            parse_number("size", &trade.size)?,
+        )];
+        for maker in &trade.maker_orders {
+            fills.push((
+                maker.order_id.as_str(),
+                parse_number("matched_amount", &maker.matched_amount)?,
+            ));
+        }
+
+        let mut events = Vec::new();
+        for (order_id, amount) in fills {
+            let Some(tracked) = self.orders.get_mut(order_id) else {
+                self.hold_fill(&trade.id, order_id, amount);
+                continue;
+            };
+            if !self.fills.insert((trade.id.clone(), order_id.to_string())) {
+                continue;
+            }
+            tracked.state.trade_ids.push(trade.id.clone());
+            tracked.traded += amount;
+            if !tracked.state.phase.is_closed() {
+                events.extend(tracked.refresh_fill(Some(&trade.id)));
+            }
+        }
+        Ok(events)
+    }
+
+    /// Holds back a fill until its order arrives, once per trade and order.
+    fn hold_fill(&mut self, trade_id: &str, order_id: &str, amount: f64) {
+        let held = self
+            .pending
+            .iter()
+            .any(|fill| fill.trade_id == trade_id && fill.order_id == order_id);
+        if held {
+            return;
+        }
+        if self.pending.len() == MAX_PENDING_FILLS {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(PendingFill {
+            trade_id: trade_id.to_string(),
+            order_id: order_id.to_string(),
+            amount,
+        });
+    }
+
+    /// Marks open orders whose expiration has passed as expired.
+    ///
+    /// The server does not always announce expirations, so call this periodically.
+    pub fn expire_due(&mut self) -> Vec<OrderEvent> {
+        let now = unix_now();
+        let mut events = Vec::new();
+        for tracked in self.orders.values_mut() {
+            let state = &mut tracked.state;
+            if !state.phase.is_closed() && state.expiration.is_some_and(|at| at <= now) {
+                state.phase = OrderPhase::Expired;
+                events.push(OrderEvent::Expired(state.clone()));
+            }
+        }
+        events
+    }
+
+    /// Returns the state of an order.
+    pub fn order(&self, id: &str) -> Option<&OrderState> {
+        self.orders.get(id).map(|tracked| &tracked.state)
+    }
+
+    /// Returns every tracked order.
+    pub fn orders(&self) -> impl Iterator<Item = &OrderState> {
+        self.orders.values().map(|tracked| &tracked.state)
+    }
+
+    /// Returns the orders that are still on the book.
+    pub fn open_orders(&self) -> impl Iterator<Item = &OrderState> {
+        self.orders().filter(|order| !order.phase.is_closed())
+    }
+
+    /// Returns the tracked orders matched by a trade.
+    pub fn orders_for_trade<'a>(
+        &'a self,
+        trade_id: &'a str,
+    ) -> impl Iterator<Item = &'a OrderState> {
+        self.orders()
+            .filter(move |order| order.trade_ids.iter().any(|id| id == trade_id))
+    }
+
+    /// Forgets filled, canceled and expired orders, returning how many were removed.
+    pub fn prune_closed(&mut self) -> usize {
+        let before = self.orders.len();
+        self.orders
+            .retain(|_, tracked| !tracked.state.phase.is_closed());
+        let orders = &self.orders;
+        self.fills
+            .retain(|(_, order_id)| orders.contains_key(order_id));
+        before - self.orders.len()
+    }
+}
@@ -0,0 +1,430 @@
+//! Position and PnL tracking from authenticated trade fills.
+//!
+//! A [`PositionLedger`] applies the user's `clob_user` trade fills, as taker or as
+//! maker, to a position per asset, and marks the positions to market from
+//! `clob_market` book and last trade price updates.
+
+use crate::model::{Message, MessageType, Topic};
+use crate::types::{AggOrderbook, ClobTrade, LastTradePrice, OrderLevel, PriceChanges};
+use crate::util::parse_number;
+use std::collections::{HashMap, HashSet};
+
+/// Sizes closer to zero than this are treated as flat.
+const SIZE_EPSILON: f64 = 1e-9;
+
+/// A fill of one of the user's orders.
+#[derive(Debug, Clone)]
+pub struct Fill {
+    /// ID of the trade
+    pub trade_id: String,
+    /// ID of the user's order that was filled
+    pub order_id: String,
+    /// ERC1155 token ID of the conditional token
+    pub asset_id: String,
+    /// Condition ID or market identifier
+    pub market: String,
+    /// Outcome of the token: YES / NO
+    pub outcome: String,
+    /// Side of the user's order: BUY or SELL
+    pub side: String,
+    /// Filled size
+    pub size: f64,
+    /// Fill price
+    pub price: f64,
+    /// Fee charged on the fill
+    pub fee: f64,
+}
+
+impl Fill {
+    /// Size signed by side: positive for buys, negative for sells.
+    pub fn signed_size(&self) -> f64 {
+        if self.side.eq_ignore_ascii_case("SELL") {
+            -self.size
+        } else {
+            self.size
+        }
+    }
+}
+
+/// Position in a single asset.
+#[derive(Debug, Clone)]
+pub struct Position {
+    /// ERC1155 token ID of the conditional token
+    pub asset_id: String,
+    /// Condition ID or market identifier
+    pub market: String,
+    /// Outcome of the token: YES / NO
+    pub outcome: String,
+    /// Net size: positive when long, negative when short
+    pub size: f64,
+    /// Average entry price of the open size
+    pub average_price: f64,
+    /// PnL locked in by reducing or closing the position, before fees
+    pub realized_pnl: f64,
+    /// Fees paid on every fill
+    pub fees: f64,
+    /// Latest mark price, if one is known
+    pub mark_price: Option<f64>,
+}
+
+impl Position {
+    fn new(fill: &Fill) -> Self {
+        Self {
+            asset_id: fill.asset_id.clone(),
+            market: fill.market.clone(),
+            outcome: fill.outcome.clone(),
+            size: 0.0,
+            average_price: 0.0,
+            realized_pnl: 0.0,
+            fees: 0.0,
+            mark_price: None,
+        }
+    }
+
+    fn apply(&mut self, fill: &Fill) {
+        if fill.size.abs() <= SIZE_EPSILON {
+            return;
+        }
+        let signed = fill.signed_size();
+        self.fees += fill.fee;
+        if self.size.abs() <= SIZE_EPSILON || self.size.signum() == signed.signum() {
+            let size = self.size.abs() + signed.abs();
+            self.average_price =
+                (self.average_price * self.size.abs() + fill.price * signed.abs()) / size;
+            self.size += signed;
+            return;
+        }
+
+        let closed = signed.abs().min(self.size.abs());
+        self.realized_pnl += closed * (fill.price - self.average_price) * self.size.signum();
+        let flipped = signed.abs() > self.size.abs() + SIZE_EPSILON;
+        self.size += signed;
+        if flipped {
+            self.average_price = fill.price;
+        } else if self.size.abs() <= SIZE_EPSILON {
+            self.size = 0.0;
+            self.average_price = 0.0;
+        }
+    }
+
+    /// Rebuilds the position from a settled state and the fills after it, keeping
+    /// the mark price.
+    fn replay<'a>(&mut self, settled: &Position, fills: impl IntoIterator<Item = &'a Fill>) {
+        let mark_price = self.mark_price;
+        *self = settled.clone();
+        self.mark_price = mark_price;
+        for fill in fills {
+            self.apply(fill);
+        }
+    }
+
+    /// PnL of the open size at the mark price, if one is known.
+    pub fn unrealized_pnl(&self) -> Option<f64> {
+        self.mark_price
+            .map(|mark| (mark - self.average_price) * self.size)
+    }
+
+    /// Realized plus unrealized PnL, net of fees. Unmarked positions count no
+    /// unrealized PnL.
+    pub fn total_pnl(&self) -> f64 {
+        self.realized_pnl + self.unrealized_pnl().unwrap_or(0.0) - self.fees
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Mark {
+    mid: Option<f64>,
+    last_trade: Option<f64>,
+}
+
+impl Mark {
+    /// The book mid-price, or the last trade price when the book is unknown.
+    fn price(&self) -> Option<f64> {
+        self.mid.or(self.last_trade)
+    }
+}
+
+/// Fills of an asset that can still be backed out.
+#[derive(Debug)]
+struct History {
+    /// Position before the first of `fills`
+    settled: Position,
+    /// Fills in order, with whether their trade is CONFIRMED
+    fills: Vec<(Fill, bool)>,
+}
+
+/// Tracks positions and PnL from the user's trade fills.
+///
+/// Fills are attributed to the user when the trade's taker `owner`, or a maker
+/// order's `owner`, matches the API key owner the ledger was created with. Each
+/// fill is applied once, even if its trade is re-sent as it settles. A trade
+/// reported as FAILED is never applied, and if its fills were applied while it was
+/// MATCHED, they are backed out by rebuilding the affected positions without them.
+/// Fills are kept for that only until their trade is CONFIRMED.
+///
+/// Fees are `fee_rate_bps / 10000 * min(price, 1 - price) * size`, the CLOB fee
+/// formula for binary outcome tokens.
+pub struct PositionLedger {
+    owner: String,
+    positions: HashMap<String, Position>,
+    marks: HashMap<String, Mark>,
+    /// Fills already applied or failed, as (trade ID, order ID)
+    applied: HashSet<(String, String)>,
+    /// Fills applied to each asset whose trade is not final, so failed trades can
+    /// be backed out
+    history: HashMap<String, History>,
+}
+
+impl PositionLedger {
+    /// Creates an empty ledger for the API key owner `owner` (the `owner` UUID on
+    /// trade messages).
+    pub fn new(owner: impl Into<String>) -> Self {
+        Self {
+            owner: owner.into(),
+            positions: HashMap::new(),
+            marks: HashMap::new(),
+            applied: HashSet::new(),
+            history: HashMap::new(),
+        }
+    }
+
+    /// Applies a message: `clob_user` trades update positions, and `clob_market`
+    /// price changes, order books and last trade prices update marks. Returns the
+    /// fills that were applied.
+    pub fn apply(&mut self, message: &Message) -> Result<Vec<Fill>, String> {
+        match (&message.topic, &message.message_type) {
+            (Topic::ClobUser, MessageType::Trade) => return self.apply_trade(&message.decode()?),
+            (Topic::ClobMarket, MessageType::PriceChange) => {
+                self.apply_price_changes(&message.decode()?)?
+            }
+            (Topic::ClobMarket, MessageType::AggOrderbook) => {
+                self.apply_orderbook(&message.decode()?)?
+            }
+            (Topic::ClobMarket, MessageType::LastTradePrice) => {
+                self.apply_last_trade_price(&message.decode()?)?
+            }
+            _ => {}
+        }
+        Ok(Vec::new())
+    }
+
+    /// Applies the user's fills in a trade, returning those not applied before. A
+    /// FAILED trade returns no fills and reverses any applied earlier, and a
+    /// CONFIRMED trade can no longer be reversed.
+    pub fn apply_trade(&mut self, trade: &ClobTrade) -> Result<Vec<Fill>, String> {
+        let mut fills = Vec::new();
+        if trade.owner == self.owner {
+            let size = parse_number("size", &trade.size)?;
+            let price = parse_number("price", &trade.price)?;
+            fills.push(Fill {
+                trade_id: trade.id.clone(),
+                order_id: trade.taker_order_id.clone(),
+                asset_id: trade.asset_id.clone(),
+                market: trade.market.clone(),
+                outcome: trade.outcome.clone(),
+                side: trade.side.clone(),
+                size,
+                price,
+                fee: fee(&trade.fee_rate_bps, price, size)?,
+            });
+        }
+        for maker in trade.maker_orders.iter().filter(|m| m.owner == self.owner) {
+            let size = parse_number("matched_amount", &maker.matched_amount)?;
+            let price = parse_number("price", &maker.price)?;
+            fills.push(Fill {
+                trade_id: trade.id.clone(),
+                order_id: maker.order_id.clone(),
+                asset_id: maker.asset_id.clone(),
+                market: trade.market.clone(),
+                outcome: maker.outcome.clone(),
+                side: maker.side.clone(),
+                size,
+                price,
+                fee: fee(&maker.fee_rate_bps, price, size)?,
+            });
+        }
+
+        let failed = trade.status.eq_ignore_ascii_case("FAILED");
+        fills.retain(|fill| {
+            self.applied
+                .insert((fill.trade_id.clone(), fill.order_id.clone()))
+        });
+        if failed {
+            self.reverse_trade(&trade.id);
+            return Ok(Vec::new());
+        }
+        for fill in &fills {
+            self.record_fill(fill);
+        }
+        if trade.status.eq_ignore_ascii_case("CONFIRMED") {
+            self.settle_trade(&trade.id);
+        }
+        Ok(fills)
+    }
+
+    /// Applies a fill, for example one replayed from the REST trade history.
+    /// Returns false if the fill was already applied. The fill can be backed out
+    /// until its trade arrives as CONFIRMED or FAILED.
+    pub fn apply_fill(&mut self, fill: &Fill) -> bool {
+        if !self
+            .applied
+            .insert((fill.trade_id.clone(), fill.order_id.clone()))
+        {
+            return false;
+        }
+        self.record_fill(fill);
+        true
+    }
+
+    fn record_fill(&mut self, fill: &Fill) {
+        let mark = self.marks.get(&fill.asset_id).and_then(Mark::price);
+        let position = self
+            .positions
+            .entry(fill.asset_id.clone())
+            .or_insert_with(|| Position::new(fill));
+        self.history
+            .entry(fill.asset_id.clone())
+            .or_insert_with(|| History {
+                settled: position.clone(),
+                fills: Vec::new(),
+            })
+            .fills
+            .push((fill.clone(), false));
+        position.apply(fill);
+        position.mark_price = position.mark_price.or(mark);
+    }
+
+    /// Removes the fills of a trade and rebuilds the positions they touched.
+    fn reverse_trade(&mut self, trade_id: &str) {
+        for (asset_id, history) in &mut self.history {
+            let before = history.fills.len();
+            history.fills.retain(|(fill, _)| fill.trade_id != trade_id);
+            if history.fills.len() == before {
+                continue;
+            }
+            if let Some(position) = self.positions.get_mut(asset_id) {
+                position.replay(&history.settled, history.fills.iter().map(|(fill, _)| fill));
+            }
+        }
+        self.history.retain(|_, history| !history.fills.is_empty());
+    }
+
+    /// Marks the fills of a confirmed trade, and folds the confirmed fills at the
+    /// start of each history into its settled position.
+    fn settle_trade(&mut self, trade_id: &str) {
+        for history in self.history.values_mut() {
+            for (fill, confirmed) in &mut history.fills {
+                *confirmed |= fill.trade_id == trade_id;
+            }
+            let settled = history
+                .fills
+                .iter()
+                .take_while(|(_, confirmed)| *confirmed)
+                .count();
+            for (fill, _) in history.fills.drain(..settled) {
+                history.settled.apply(&fill);
+            }
+        }
+        self.history.retain(|_, history| !history.fills.is_empty());
+    }
+
+    /// Marks assets at the mid of the best bid and ask in a `price_change` message.
+    pub fn apply_price_changes(&mut self, changes: &PriceChanges) -> Result<(), String> {
+        for change in &changes.price_change {
+            let bid = parse_number("best_bid", &change.best_bid)?;
+            let ask = parse_number("best_ask", &change.best_ask)?;
+            self.set_mid(&change.asset_id, mid(Some(bid), Some(ask)));
+        }
+        Ok(())
+    }
+
+    /// Marks an asset at the mid of an aggregated order book.
+    pub fn apply_orderbook(&mut self, book: &AggOrderbook) -> Result<(), String> {
+        let best = |levels: &[OrderLevel], pick: fn(f64, f64) -> f64| {
+            levels.iter().try_fold(None, |best: Option<f64>, level| {
+                let price = parse_number("price", &level.price)?;
+                Ok::<_, String>(Some(best.map_or(price, |best| pick(best, price))))
+            })
+        };
+        let bid = best(&book.bids, f64::max)?;
+        let ask = best(&book.asks, f64::min)?;
+        self.set_mid(&book.asset_id, mid(bid, ask));
+        Ok(())
+    }
+
+    /// Records the last trade price of an asset, used as the mark when no book
+    /// mid-price is known.
+    pub fn apply_last_trade_price(&mut self, last: &LastTradePrice) -> Result<(), String> {
+        let price = parse_number("price", &last.price)?;
+        self.marks
+            .entry(last.asset_id.clone())
+            .or_default()
+            .last_trade = Some(price);
+        self.refresh_mark(&last.asset_id);
+        Ok(())
+    }
+
+    /// Sets the mark price of an asset, until the next book update replaces it.
+    pub fn set_mark(&mut self, asset_id: &str, price: f64) {
+        self.set_mid(asset_id, Some(price));
+    }
+
+    fn set_mid(&mut self, asset_id: &str, mid: Option<f64>) {
+        self.marks.entry(asset_id.to_string()).or_default().mid = mid;
+        self.refresh_mark(asset_id);
+    }
+
+    fn refresh_mark(&mut self, asset_id: &str) {
+        if let Some(position) = self.positions.get_mut(asset_id) {
+            position.mark_price = self.marks.get(asset_id).and_then(Mark::price);
+        }
+    }
+
+    /// Returns the position in an asset.
+    pub fn position(&self, asset_id: &str) -> Option<&Position> {
+        self.positions.get(asset_id)
+    }
+
+    /// Returns every position, including flat ones with realized PnL.
+    pub fn positions(&self) -> impl Iterator<Item = &Position> {
+        self.positions.values()
+    }
+
+    /// Returns the asset IDs with a position, for subscribing to their
+    /// `clob_market` updates.
+    pub fn asset_ids(&self) -> Vec<String> {
+        self.positions.keys().cloned().collect()
+    }
+
+    /// Realized PnL across all positions, before fees.
+    pub fn realized_pnl(&self) -> f64 {
+        self.positions().map(|p| p.realized_pnl).sum()
+    }
+
+    /// Unrealized PnL across all marked positions.
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.positions().filter_map(Position::unrealized_pnl).sum()
+    }
+
+    /// Fees paid across all positions.
+    pub fn fees(&self) -> f64 {
+        self.positions().map(|p| p.fees).sum()
+    }
+
+    /// Realized plus unrealized PnL, net of fees.
+    pub fn total_pnl(&self) -> f64 {
+        self.positions().map(Position::total_pnl).sum()
+    }
+}
+
+fn mid(bid: Option<f64>, ask: Option<f64>) -> Option<f64> {
+    match (bid, ask) {
+        (Some(bid), Some(ask)) if bid > 0.0 && ask > 0.0 => Some((bid + ask) / 2.0),
+        _ => None,
+    }
+}
+
+fn fee(rate_bps: &str, price: f64, size: f64) -> Result<f64, String> {
+    let rate = parse_number("fee_rate_bps", rate_bps)? / 10_000.0;
+    Ok(rate * price.min(1.0 - price).max(0.0) * size)
+}
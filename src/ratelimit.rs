@@ -0,0 +1,206 @@
+use crate::model::Subscription;
+use std::collections::VecDeque;
+use tokio::time::{Duration, Instant};
+
+const DEFAULT_CONTROL_BURST: u32 = 10;
+const DEFAULT_CONTROL_PER_SECOND: f64 = 5.0;
+const DEFAULT_PING_BURST: u32 = 1;
+const DEFAULT_PING_PER_SECOND: f64 = 1.0;
+/// Stand-in for "never" when the next token is too far away to represent.
+const FAR_FUTURE: Duration = Duration::from_secs(86400 * 365 * 30);
+
+/// Token bucket budget: up to `burst` frames at once, refilled at `per_second`.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    /// Frames that can be sent back to back
+    pub burst: u32,
+    /// Sustained frames per second
+    pub per_second: f64,
+}
+
+impl RateLimit {
+    fn validate(&self, name: &str) -> Result<(), String> {
+        if self.burst == 0 {
+            return Err(format!("{} rate limit burst must be at least 1", name));
+        }
+        if !(self.per_second.is_finite() && self.per_second > 0.0) {
+            return Err(format!(
+                "{} rate limit must refill at a positive rate, got {}",
+                name, self.per_second
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Budgets for outbound frames.
+///
+/// Subscribe and unsubscribe frames share the control budget; pings have their own so
+/// that a large resubscribe cannot starve the keepalive. Frames over budget are queued
+/// and sent in order as tokens become available.
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Budget for subscribe and unsubscribe frames
+    pub control: RateLimit,
+    /// Budget for ping frames
+    pub ping: RateLimit,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            control: RateLimit {
+                burst: DEFAULT_CONTROL_BURST,
+                per_second: DEFAULT_CONTROL_PER_SECOND,
+            },
+            ping: RateLimit {
+                burst: DEFAULT_PING_BURST,
+                per_second: DEFAULT_PING_PER_SECOND,
+            },
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Checks that every budget allows at least one frame and refills over time.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        self.control.validate("control")?;
+        self.ping.validate("ping")
+    }
+}
+
+struct TokenBucket {
+    capacity: f64,
+    per_second: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: &RateLimit) -> Self {
+        let capacity = f64::from(limit.burst);
+        Self {
+            capacity,
+            per_second: limit.per_second,
+            tokens: capacity,
+            updated: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.updated = now;
+    }
+
+    fn try_take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// When the next token will be available.
+    fn ready_at(&self) -> Instant {
+        let missing = (1.0 - self.tokens).max(0.0);
+        Duration::try_from_secs_f64(missing / self.per_second)
+            .ok()
+            .and_then(|wait| self.updated.checked_add(wait))
+            .unwrap_or_else(|| self.updated + FAR_FUTURE)
+    }
+}
+
+/// Outbound frame, rendered to text only when it is sent so that it carries the
+/// credentials current at that point rather than when it was queued.
+pub(crate) enum Frame {
+    Ping,
+    Subscriptions {
+        action: &'static str,
+        subscriptions: Vec<Subscription>,
+    },
+}
+
+struct Queued {
+    frame: Frame,
+    enqueued_at: Instant,
+    /// Whether the frame had to wait for a token
+    delayed: bool,
+}
+
+/// Outbound frames waiting for their budget.
+pub(crate) struct Outbox {
+    control: TokenBucket,
+    ping: TokenBucket,
+    queue: VecDeque<Queued>,
+    /// A pending ping; further pings are dropped while one waits
+    ping_queued: Option<Queued>,
+}
+
+impl Outbox {
+    pub(crate) fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            control: TokenBucket::new(&config.control),
+            ping: TokenBucket::new(&config.ping),
+            queue: VecDeque::new(),
+            ping_queued: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, frame: Frame) {
+        let ping = matches!(frame, Frame::Ping);
+        let queued = Queued {
+            frame,
+            enqueued_at: Instant::now(),
+            delayed: false,
+        };
+        if ping {
+            self.ping_queued.get_or_insert(queued);
+        } else {
+            self.queue.push_back(queued);
+        }
+    }
+
+    /// Takes the next frame whose budget allows sending it now, along with how long
+    /// it waited if it was held back.
+    pub(crate) fn pop_ready(&mut self) -> Option<(Frame, Option<Duration>)> {
+        let now = Instant::now();
+        let queued = if self.ping_queued.is_some() && self.ping.try_take(now) {
+            self.ping_queued.take()?
+        } else if !self.queue.is_empty() && self.control.try_take(now) {
+            self.queue.pop_front()?
+        } else {
+            return None;
+        };
+        let waited = queued
+            .delayed
+            .then(|| now.saturating_duration_since(queued.enqueued_at));
+        Some((queued.frame, waited))
+    }
+
+    /// Marks every frame still queued as held back by the limiter.
+    pub(crate) fn mark_delayed(&mut self) {
+        for queued in self.queue.iter_mut().chain(self.ping_queued.as_mut()) {
+            queued.delayed = true;
+        }
+    }
+
+    /// When the next queued frame can be sent, if any is queued.
+    pub(crate) fn next_ready(&self) -> Option<Instant> {
+        let control = (!self.queue.is_empty()).then(|| self.control.ready_at());
+        let ping = self.ping_queued.as_ref().map(|_| self.ping.ready_at());
+        control.into_iter().chain(ping).min()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.queue.len() + usize::from(self.ping_queued.is_some())
+    }
+
+    /// Drops queued frames; budgets are kept so reconnect storms stay limited.
+    pub(crate) fn clear(&mut self) {
+        self.queue.clear();
+        self.ping_queued = None;
+    }
+}
@@ -0,0 +1,430 @@
+use crate::client::{RealTimeDataClient, ReconnectConfig};
+use crate::model::{Message, MessageType, Subscription, Topic};
+use crate::trace::trace_event;
+use futures_util::future::select_all;
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+const DEFAULT_DEDUP_CAPACITY: usize = 10_000;
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A client handed back by a background retry, with the outcome of its connect.
+type RetryResult = (usize, RealTimeDataClient, Result<(), String>);
+
+/// Health information for a single redundant connection.
+#[derive(Debug, Clone)]
+pub struct LegHealth {
+    /// Index of the connection
+    pub index: usize,
+    /// Whether the connection is still alive
+    pub connected: bool,
+    /// Number of messages received on this connection
+    pub messages: u64,
+    /// Number of messages this connection delivered before any other
+    pub first_deliveries: u64,
+}
+
+struct Leg {
+    /// The leg's client, or `None` while a background retry owns it
+    client: Option<RealTimeDataClient>,
+    connected: bool,
+    /// Backoff state of a leg that is down, if it is still retried
+    retry: Option<Retry>,
+    messages: u64,
+    first_deliveries: u64,
+}
+
+struct Retry {
+    attempt: u32,
+    delay: Duration,
+}
+
+/// Bounded set of recently seen message keys.
+struct Deduplicator {
+    capacity: usize,
+    seen: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl Deduplicator {
+    /// Records `key`, returning false if it was already seen.
+    fn insert(&mut self, key: String) -> bool {
+        if self.seen.contains(&key) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(key.clone());
+        self.order.push_back(key);
+        true
+    }
+}
+
+/// A client that keeps several parallel connections to the same subscriptions and
+/// merges them into a single deduplicated stream.
+///
+/// Whichever connection delivers a message first wins; later copies are dropped.
+/// While one connection reconnects, the others keep the stream gap free.
+///
+/// Connections that fail to connect, or whose client gives up reconnecting, are
+/// retried with exponential backoff in background tasks, so the live connections
+/// keep delivering meanwhile, and resubscribed once they are back.
+pub struct RedundantClient {
+    legs: Vec<Leg>,
+    dedup: Deduplicator,
+    cursor: usize,
+    reconnect: Option<ReconnectConfig>,
+    connect_timeout: Duration,
+    retry_tx: mpsc::UnboundedSender<RetryResult>,
+    retry_rx: mpsc::UnboundedReceiver<RetryResult>,
+    /// Active subscriptions, sent to connections that come back
+    subscriptions: Mutex<Vec<Subscription>>,
+    /// Set once the client disconnects, so that ended connections are not retried
+    closed: AtomicBool,
+}
+
+impl RedundantClient {
+    /// Creates a redundant client with `connections` default clients that reconnect
+    /// automatically.
+    pub fn new(connections: usize) -> Self {
+        Self::from_clients(
+            (0..connections.max(1))
+                .map(|_| RealTimeDataClient::new().with_reconnect(Some(ReconnectConfig::default())))
+                .collect(),
+        )
+    }
+
+    /// Creates a redundant client from preconfigured clients, one per connection.
+    pub fn from_clients(clients: Vec<RealTimeDataClient>) -> Self {
+        let legs = clients
+            .into_iter()
+            .map(|client| Leg {
+                client: Some(client),
+                connected: false,
+                retry: None,
+                messages: 0,
+                first_deliveries: 0,
+            })
+            .collect();
+        let (retry_tx, retry_rx) = mpsc::unbounded_channel();
+        Self {
+            legs,
+            dedup: Deduplicator {
+                capacity: DEFAULT_DEDUP_CAPACITY,
+                seen: HashSet::new(),
+                order: VecDeque::new(),
+            },
+            cursor: 0,
+            reconnect: Some(ReconnectConfig::default()),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            retry_tx,
+            retry_rx,
+            subscriptions: Mutex::new(Vec::new()),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    /// Set how many recent message keys are remembered for deduplication
+    pub fn with_dedup_capacity(mut self, capacity: usize) -> Self {
+        self.dedup.capacity = capacity.max(1);
+        self
+    }
+
+    /// Configure the backoff for retrying connections that are down, or never retry
+    /// them with `None`
+    pub fn with_reconnect(mut self, reconnect: Option<ReconnectConfig>) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Set how long a connection attempt may take before it counts as failed
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Connects every redundant connection.
+    ///
+    /// Succeeds as long as at least one connection could be established. The others
+    /// are retried in the background.
+    pub async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.closed.store(false, Ordering::Relaxed);
+        let mut last_error = None;
+        for index in 0..self.legs.len() {
+            let leg = &mut self.legs[index];
+            let Some(client) = leg.client.as_mut().filter(|_| !leg.connected) else {
+                continue;
+            };
+            match connect_within(client, self.connect_timeout).await {
+                Ok(()) => {
+                    leg.connected = true;
+                    leg.retry = None;
+                }
+                Err(e) => {
+                    trace_event!(warn, connection = index, error = %e, "Redundant connection failed");
+                    last_error = Some(e);
+                    self.schedule_retry(index);
+                }
+            }
+        }
+        match last_error {
+            Some(e) if !self.legs.iter().any(|leg| leg.connected) => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Subscribes every connection to the data streams.
+    pub async fn subscribe(&self, subscriptions: Vec<Subscription>) -> Result<(), String> {
+        {
+            let mut active = self.subscriptions.lock().unwrap();
+            for subscription in &subscriptions {
+                if !active.iter().any(|s| s.same_stream(subscription)) {
+                    active.push(subscription.clone());
+                }
+            }
+        }
+        self.broadcast(|client| client.subscribe(subscriptions.clone()))
+            .await
+    }
+
+    /// Unsubscribes every connection from the data streams.
+    pub async fn unsubscribe(&self, subscriptions: Vec<Subscription>) -> Result<(), String> {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .retain(|s| !subscriptions.iter().any(|u| u.same_stream(s)));
+        self.broadcast(|client| client.unsubscribe(subscriptions.clone()))
+            .await
+    }
+
+    /// Receives the next message not yet delivered by another connection. Connections
+    /// that come back from a retry meanwhile are resubscribed and rejoin the stream.
+    ///
+    /// Returns `None` once every connection has ended and none is retried anymore.
+    pub async fn recv(&mut self) -> Option<Result<Message, String>> {
+        loop {
+            let retrying = self.legs.iter().any(|leg| leg.client.is_none());
+            if !retrying && !self.legs.iter().any(|leg| leg.connected) {
+                return None;
+            }
+
+            // Rotate the polling order so ties are not always won by the same connection.
+            self.cursor = (self.cursor + 1) % self.legs.len();
+            let cursor = self.cursor;
+            let (head, tail) = self.legs.split_at_mut(cursor);
+            let futures: Vec<_> = tail
+                .iter_mut()
+                .zip(cursor..)
+                .chain(head.iter_mut().zip(0..))
+                .filter(|(leg, _)| leg.connected)
+                .filter_map(|(leg, index)| Some((leg.client.as_mut()?, index)))
+                .map(|(client, index)| Box::pin(async move { (index, client.recv().await) }))
+                .collect();
+            let received = async {
+                if futures.is_empty() {
+                    std::future::pending().await
+                } else {
+                    select_all(futures).await.0
+                }
+            };
+
+            let (index, result) = tokio::select! {
+                received = received => received,
+                Some((index, client, result)) = self.retry_rx.recv() => {
+                    self.restore(index, client, result).await;
+                    continue;
+                }
+            };
+            let leg = &mut self.legs[index];
+            match result {
+                Some(Ok(message)) => {
+                    leg.messages += 1;
+                    if self.dedup.insert(dedup_key(&message)) {
+                        leg.first_deliveries += 1;
+                        return Some(Ok(message));
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    trace_event!(warn, connection = index, "Redundant connection ended");
+                    leg.connected = false;
+                    if !self.closed.load(Ordering::Relaxed) {
+                        self.schedule_retry(index);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Hands a leg that is down to a background task that waits out its backoff and
+    /// reconnects it, or stops retrying it once the attempts are exhausted.
+    fn schedule_retry(&mut self, index: usize) {
+        let Some(ref config) = self.reconnect else {
+            return;
+        };
+        let leg = &mut self.legs[index];
+        let (attempt, delay) = match leg.retry.take() {
+            Some(retry) => (retry.attempt + 1, (retry.delay * 2).min(config.max_delay)),
+            None => (1, config.initial_delay),
+        };
+        if config.max_attempts.is_some_and(|max| attempt > max) {
+            trace_event!(
+                error,
+                connection = index,
+                attempts = attempt - 1,
+                "Giving up on redundant connection"
+            );
+            return;
+        }
+        let Some(mut client) = leg.client.take() else {
+            return;
+        };
+        leg.retry = Some(Retry { attempt, delay });
+
+        let timeout = self.connect_timeout;
+        let retry_tx = self.retry_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let result = connect_within(&mut client, timeout).await;
+            let _ = retry_tx.send((index, client, result));
+        });
+    }
+
+    /// Takes a leg back from its retry, resubscribing it to the active subscriptions
+    /// if it connected.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    async fn restore(
+        &mut self,
+        index: usize,
+        client: RealTimeDataClient,
+        result: Result<(), String>,
+    ) {
+        if self.closed.load(Ordering::Relaxed) {
+            if result.is_ok() {
+                let _ = client.disconnect().await;
+            }
+            self.legs[index].client = Some(client);
+            return;
+        }
+
+        let subscriptions = self.subscriptions.lock().unwrap().clone();
+        let result = match result {
+            Ok(()) if subscriptions.is_empty() => Ok(()),
+            Ok(()) => client.subscribe(subscriptions).await,
+            Err(e) => Err(e),
+        };
+        let leg = &mut self.legs[index];
+        leg.client = Some(client);
+        match result {
+            Ok(()) => {
+                trace_event!(info, connection = index, "Redundant connection restored");
+                leg.connected = true;
+                leg.retry = None;
+            }
+            Err(e) => {
+                trace_event!(warn, connection = index, error = %e, "Redundant connection retry failed");
+                self.schedule_retry(index);
+            }
+        }
+    }
+
+    /// Closes every connection.
+    pub async fn disconnect(&self) -> Result<(), String> {
+        self.closed.store(true, Ordering::Relaxed);
+        self.broadcast(|client| client.disconnect()).await
+    }
+
+    /// Returns the health of every connection.
+    pub fn health(&self) -> Vec<LegHealth> {
+        self.legs
+            .iter()
+            .enumerate()
+            .map(|(index, leg)| LegHealth {
+                index,
+                connected: leg.connected,
+                messages: leg.messages,
+                first_deliveries: leg.first_deliveries,
+            })
+            .collect()
+    }
+
+    /// Runs `f` on every live connection, succeeding if at least one succeeded.
+    async fn broadcast<'a, F, Fut>(&'a self, f: F) -> Result<(), String>
+    where
+        F: Fn(&'a RealTimeDataClient) -> Fut,
+        Fut: std::future::Future<Output = Result<(), String>>,
+    {
+        let mut result = Err("No connected clients".to_string());
+        let live = self
+            .legs
+            .iter()
+            .filter(|leg| leg.connected)
+            .filter_map(|leg| leg.client.as_ref());
+        for client in live {
+            match f(client).await {
+                Ok(()) => result = Ok(()),
+                Err(e) if result.is_err() => result = Err(e),
+                Err(_) => {}
+            }
+        }
+        result
+    }
+}
+
+/// Connects a client, failing if it takes longer than `timeout`.
+async fn connect_within(client: &mut RealTimeDataClient, timeout: Duration) -> Result<(), String> {
+    match tokio::time::timeout(timeout, client.connect()).await {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err(format!("Timed out connecting after {:?}", timeout)),
+    }
+}
+
+/// Returns the content key used to recognise the same message arriving on
+/// different connections.
+///
+/// Trades are keyed by transaction hash plus asset, wallet, side, size, price and
+/// outcome, since one transaction can settle several fills. CLOB orders and trades by `id` and their current state,
+/// and price updates by `(symbol, timestamp)`. Other messages fall back to their
+/// timestamp and full payload.
+pub fn dedup_key(message: &Message) -> String {
+    let payload = &message.payload;
+    let field = |name: &str| match payload.get(name) {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    };
+
+    let key = match (&message.topic, &message.message_type) {
+        (Topic::Activity, _) if payload.get("transactionHash").is_some() => format!(
+            "{}|{}|{}|{}|{}|{}|{}",
+            field("transactionHash"),
+            field("asset"),
+            field("proxyWallet"),
+            field("side"),
+            field("size"),
+            field("price"),
+            field("outcome")
+        ),
+        (Topic::ClobUser, MessageType::Trade) => format!("{}|{}", field("id"), field("status")),
+        (Topic::ClobUser, MessageType::Order) => format!(
+            "{}|{}|{}",
+            field("id"),
+            field("type"),
+            field("size_matched")
+        ),
+        (Topic::CryptoPrices | Topic::CryptoPricesChainlink | Topic::EquityPrices, _)
+            if payload.get("symbol").is_some() && payload.get("timestamp").is_some() =>
+        {
+            format!("{}|{}", field("symbol"), field("timestamp"))
+        }
+        _ => format!("{}|{}", message.timestamp, payload),
+    };
+
+    format!("{}|{}|{}", message.topic, message.message_type, key)
+}
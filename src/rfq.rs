@@ -0,0 +1,398 @@
+//! Request-for-quote state machine.
+//!
+//! An [`RfqBook`] keeps the open RFQ requests and the quotes attached to each of
+//! them, rejects events that do not follow a valid lifecycle, and expires entries by
+//! their `expiry` timestamp even when no expiry event arrives. Late or replayed
+//! events for entries that already closed are ignored.
+
+use crate::model::{Message, MessageType, Topic};
+use crate::types::{RfqQuote, RfqRequest};
+use crate::util::unix_seconds;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+/// A lifecycle step of a request or quote, as given by the message type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RfqAction {
+    /// `request_created` or `quote_created`
+    Created,
+    /// `request_edited` or `quote_edited`
+    Edited,
+    /// `request_canceled` or `quote_canceled`
+    Canceled,
+    /// `request_expired` or `quote_expired`
+    Expired,
+}
+
+impl RfqAction {
+    /// Returns the action of an `rfq` message type, and whether it applies to a quote
+    /// rather than a request.
+    pub fn from_message_type(message_type: &MessageType) -> Option<(Self, bool)> {
+        match message_type {
+            MessageType::RequestCreated => Some((Self::Created, false)),
+            MessageType::RequestEdited => Some((Self::Edited, false)),
+            MessageType::RequestCanceled => Some((Self::Canceled, false)),
+            MessageType::RequestExpired => Some((Self::Expired, false)),
+            MessageType::QuoteCreated => Some((Self::Created, true)),
+            MessageType::QuoteEdited => Some((Self::Edited, true)),
+            MessageType::QuoteCanceled => Some((Self::Canceled, true)),
+            MessageType::QuoteExpired => Some((Self::Expired, true)),
+            _ => None,
+        }
+    }
+}
+
+/// State of a request or quote, parsed from its free-form `state` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RfqState {
+    /// Open, or a state this crate does not know
+    Open,
+    /// Canceled by its owner
+    Canceled,
+    /// Past its expiry
+    Expired,
+    /// Filled or otherwise completed
+    Filled,
+}
+
+impl RfqState {
+    /// Parses a state such as `STATE_CANCELED`, ignoring case and the `STATE_`
+    /// prefix. Unknown states are treated as open.
+    pub fn parse(state: &str) -> Self {
+        let state = state.to_ascii_uppercase();
+        match state.strip_prefix("STATE_").unwrap_or(&state) {
+            "CANCELED" | "CANCELLED" => Self::Canceled,
+            "EXPIRED" => Self::Expired,
+            "FILLED" | "EXECUTED" | "MATCHED" | "COMPLETED" | "ACCEPTED" => Self::Filled,
+            _ => Self::Open,
+        }
+    }
+
+    /// Returns why an entry in this state leaves the book, or `None` if it is open.
+    pub fn close_reason(&self) -> Option<RfqCloseReason> {
+        match self {
+            Self::Open => None,
+            Self::Canceled => Some(RfqCloseReason::Canceled),
+            Self::Expired => Some(RfqCloseReason::Expired),
+            Self::Filled => Some(RfqCloseReason::Filled),
+        }
+    }
+}
+
+/// Why a request or quote left the book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RfqCloseReason {
+    /// Canceled by its owner
+    Canceled,
+    /// Reached its expiry
+    Expired,
+    /// Filled or completed, as given by its `state`
+    Filled,
+    /// Its request closed
+    RequestClosed,
+}
+
+/// How long after its expiry a closed request or quote is remembered, so that late
+/// or replayed events for it are ignored rather than rejected.
+const CLOSED_RETENTION_SECS: i64 = 3600;
+
+/// A change to the book.
+#[derive(Debug, Clone)]
+pub enum RfqEvent {
+    /// A request was opened
+    RequestOpened(RfqRequest),
+    /// An open request was edited
+    RequestEdited(RfqRequest),
+    /// A request was closed, along with its quotes
+    RequestClosed {
+        request: RfqRequest,
+        reason: RfqCloseReason,
+    },
+    /// A quote was attached to an open request
+    QuoteOpened(RfqQuote),
+    /// An open quote was edited
+    QuoteEdited(RfqQuote),
+    /// A quote was closed
+    QuoteClosed {
+        quote: RfqQuote,
+        reason: RfqCloseReason,
+    },
+}
+
+/// An open request and its open quotes.
+#[derive(Debug, Clone)]
+pub struct RfqEntry {
+    /// Latest version of the request
+    pub request: RfqRequest,
+    /// Open quotes, in arrival order
+    pub quotes: Vec<RfqQuote>,
+}
+
+impl RfqEntry {
+    /// Returns the open quotes from best to worst for the requester: lowest price
+    /// first for a BUY request, highest first for a SELL request. Quotes without a
+    /// token size are left out.
+    pub fn ranked_quotes(&self) -> Vec<&RfqQuote> {
+        let buying = !self.request.side.eq_ignore_ascii_case("SELL");
+        let mut quotes: Vec<&RfqQuote> = self
+            .quotes
+            .iter()
+            .filter(|quote| !quote_price(quote).is_nan())
+            .collect();
+        quotes.sort_by(|a, b| {
+            let order = quote_price(a)
+                .partial_cmp(&quote_price(b))
+                .unwrap_or(Ordering::Equal);
+            if buying {
+                order
+            } else {
+                order.reverse()
+            }
+        });
+        quotes
+    }
+
+    /// Returns the best open quote for the requester.
+    pub fn best_quote(&self) -> Option<&RfqQuote> {
+        self.ranked_quotes().into_iter().next()
+    }
+}
+
+/// Price per token implied by a quote's sizes.
+///
+/// A BUY side spends collateral for tokens (`size_in` is collateral, `size_out` is
+/// tokens), and a SELL side spends tokens for collateral.
+pub fn quote_price(quote: &RfqQuote) -> f64 {
+    let (collateral, tokens) = if quote.side.eq_ignore_ascii_case("SELL") {
+        (quote.size_out, quote.size_in)
+    } else {
+        (quote.size_in, quote.size_out)
+    };
+    if tokens > 0.0 {
+        collateral / tokens
+    } else {
+        f64::NAN
+    }
+}
+
+/// Open RFQ requests and their quotes, built from the `rfq` topic.
+///
+/// Events that do not follow a valid lifecycle are rejected with an error and leave
+/// the book unchanged: a request or quote must be created before it is edited,
+/// canceled or expired, and quotes can only attach to open requests. A `state` that
+/// says an entry is canceled, expired or filled closes it.
+///
+/// Events for entries that already closed, such as a `request_expired` after
+/// [`RfqBook::expire_due`] closed the request or a `*_created` event replayed after a
+/// reconnect, are ignored and return no events. Closed entries are remembered until
+/// an hour past their expiry.
+#[derive(Debug, Default)]
+pub struct RfqBook {
+    requests: HashMap<String, RfqEntry>,
+    /// Expiry of closed requests, by request ID
+    closed_requests: HashMap<String, i64>,
+    /// Expiry of closed quotes, by quote ID
+    closed_quotes: HashMap<String, i64>,
+}
+
+impl RfqBook {
+    /// Creates an empty book.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies an `rfq` message, ignoring other topics.
+    pub fn apply(&mut self, message: &Message) -> Result<Vec<RfqEvent>, String> {
+        if message.topic != Topic::Rfq {
+            return Ok(Vec::new());
+        }
+        match RfqAction::from_message_type(&message.message_type) {
+            Some((action, false)) => self.apply_request(action, message.decode()?),
+            Some((action, true)) => self.apply_quote(action, message.decode()?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Applies a request event.
+    pub fn apply_request(
+        &mut self,
+        action: RfqAction,
+        request: RfqRequest,
+    ) -> Result<Vec<RfqEvent>, String> {
+        if self.closed_requests.contains_key(&request.request_id) {
+            return Ok(Vec::new());
+        }
+        let open = self.requests.contains_key(&request.request_id);
+        let closing = match action {
+            RfqAction::Canceled => Some(RfqCloseReason::Canceled),
+            RfqAction::Expired => Some(RfqCloseReason::Expired),
+            RfqAction::Created | RfqAction::Edited => {
+                RfqState::parse(&request.state).close_reason()
+            }
+        };
+        match (action, closing) {
+            // A replayed create changes nothing.
+            (RfqAction::Created, _) if open => Ok(Vec::new()),
+            // A request created in a closed state never opens.
+            (RfqAction::Created, Some(_)) => {
+                self.closed_requests
+                    .insert(request.request_id, request.expiry);
+                Ok(Vec::new())
+            }
+            (RfqAction::Created, None) => {
+                self.requests.insert(
+                    request.request_id.clone(),
+                    RfqEntry {
+                        request: request.clone(),
+                        quotes: Vec::new(),
+                    },
+                );
+                Ok(vec![RfqEvent::RequestOpened(request)])
+            }
+            _ if !open => Err(format!(
+                "Request {} is not open and cannot be {:?}",
+                request.request_id, action
+            )),
+            (_, closing) => {
+                if let Some(entry) = self.requests.get_mut(&request.request_id) {
+                    entry.request = request.clone();
+                }
+                Ok(match closing {
+                    None => vec![RfqEvent::RequestEdited(request)],
+                    Some(reason) => self.close_request(&request.request_id, reason),
+                })
+            }
+        }
+    }
+
+    /// Applies a quote event.
+    pub fn apply_quote(
+        &mut self,
+        action: RfqAction,
+        quote: RfqQuote,
+    ) -> Result<Vec<RfqEvent>, String> {
+        if self.closed_quotes.contains_key(&quote.quote_id)
+            || self.closed_requests.contains_key(&quote.request_id)
+        {
+            return Ok(Vec::new());
+        }
+        let Some(entry) = self.requests.get_mut(&quote.request_id) else {
+            return Err(format!(
+                "Quote {} refers to request {}, which is not open",
+                quote.quote_id, quote.request_id
+            ));
+        };
+        let position = entry
+            .quotes
+            .iter()
+            .position(|q| q.quote_id == quote.quote_id);
+        let closing = match action {
+            RfqAction::Canceled => Some(RfqCloseReason::Canceled),
+            RfqAction::Expired => Some(RfqCloseReason::Expired),
+            RfqAction::Created | RfqAction::Edited => RfqState::parse(&quote.state).close_reason(),
+        };
+        match (action, position, closing) {
+            // A replayed create changes nothing.
+            (RfqAction::Created, Some(_), _) => Ok(Vec::new()),
+            // A quote created in a closed state never opens.
+            (RfqAction::Created, None, Some(_)) => {
+                self.closed_quotes.insert(quote.quote_id, quote.expiry);
+                Ok(Vec::new())
+            }
+            (RfqAction::Created, None, None) => {
+                entry.quotes.push(quote.clone());
+                Ok(vec![RfqEvent::QuoteOpened(quote)])
+            }
+            (_, None, _) => Err(format!(
+                "Quote {} is not open and cannot be {:?}",
+                quote.quote_id, action
+            )),
+            (_, Some(index), None) => {
+                entry.quotes[index] = quote.clone();
+                Ok(vec![RfqEvent::QuoteEdited(quote)])
+            }
+            (_, Some(index), Some(reason)) => {
+                entry.quotes.remove(index);
+                self.closed_quotes
+                    .insert(quote.quote_id.clone(), quote.expiry);
+                Ok(vec![RfqEvent::QuoteClosed { quote, reason }])
+            }
+        }
+    }
+
+    /// Closes requests and quotes whose `expiry` (UNIX seconds) is at or before
+    /// `now`.
+    pub fn expire_due(&mut self, now: SystemTime) -> Vec<RfqEvent> {
+        let now = unix_seconds(now);
+
+        let expired: Vec<String> = self
+            .requests
+            .values()
+            .filter(|entry| entry.request.expiry <= now)
+            .map(|entry| entry.request.request_id.clone())
+            .collect();
+        let mut events = Vec::new();
+        for request_id in expired {
+            events.extend(self.close_request(&request_id, RfqCloseReason::Expired));
+        }
+
+        for entry in self.requests.values_mut() {
+            let (expired, open): (Vec<RfqQuote>, Vec<RfqQuote>) = entry
+                .quotes
+                .drain(..)
+                .partition(|quote| quote.expiry <= now);
+            entry.quotes = open;
+            for quote in expired {
+                self.closed_quotes
+                    .insert(quote.quote_id.clone(), quote.expiry);
+                events.push(RfqEvent::QuoteClosed {
+                    quote,
+                    reason: RfqCloseReason::Expired,
+                });
+            }
+        }
+
+        let forgotten = now.saturating_sub(CLOSED_RETENTION_SECS);
+        self.closed_requests.retain(|_, expiry| *expiry > forgotten);
+        self.closed_quotes.retain(|_, expiry| *expiry > forgotten);
+        events
+    }
+
+    fn close_request(&mut self, request_id: &str, reason: RfqCloseReason) -> Vec<RfqEvent> {
+        let Some(entry) = self.requests.remove(request_id) else {
+            return Vec::new();
+        };
+        self.closed_requests
+            .insert(request_id.to_string(), entry.request.expiry);
+        let mut events = Vec::new();
+        for quote in entry.quotes {
+            self.closed_quotes
+                .insert(quote.quote_id.clone(), quote.expiry);
+            events.push(RfqEvent::QuoteClosed {
+                quote,
+                reason: RfqCloseReason::RequestClosed,
+            });
+        }
+        events.push(RfqEvent::RequestClosed {
+            request: entry.request,
+            reason,
+        });
+        events
+    }
+
+    /// Returns an open request and its quotes.
+    pub fn request(&self, request_id: &str) -> Option<&RfqEntry> {
+        self.requests.get(request_id)
+    }
+
+    /// Returns every open request.
+    pub fn requests(&self) -> impl Iterator<Item = &RfqEntry> {
+        self.requests.values()
+    }
+
+    /// Returns the best open quote for a request.
+    pub fn best_quote(&self, request_id: &str) -> Option<&RfqQuote> {
+        self.requests.get(request_id)?.best_quote()
+    }
+}
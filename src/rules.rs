@@ -0,0 +1,349 @@
+//! Client-side message routing rules.
+//!
+//! A [`RuleSet`] is a list of rules, each matching on topic, type and predicates over
+//! payload fields. A [`MessageRouter`] sends every message to the named output
+//! channels of the rules it matches. Rule sets can be loaded from TOML or JSON and
+//! swapped at runtime through a [`RulesHandle`].
+//!
+//! ```toml
+//! [[rules]]
+//! route = "majors"
+//! topic = "crypto_prices"
+//! type = "update"
+//!
+//! [[rules.conditions]]
+//! field = "symbol"
+//! in = ["btcusdt", "ethusdt"]
+//!
+//! [[rules.conditions]]
+//! field = "value"
+//! range = { min = 1000.0 }
+//! ```
+
+use crate::client::RealTimeDataClient;
+use crate::model::{Message, MessageType, Topic};
+use crate::trace::trace_event;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc;
+
+/// Predicate applied to a single payload field.
+#[derive(Debug, Clone)]
+enum Predicate {
+    Equals(Value),
+    In(Vec<Value>),
+    Range { min: Option<f64>, max: Option<f64> },
+    Regex(Regex),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PredicateSpec {
+    Equals(Value),
+    In(Vec<Value>),
+    Range { min: Option<f64>, max: Option<f64> },
+    Regex(String),
+}
+
+#[derive(Deserialize)]
+struct ConditionSpec {
+    field: String,
+    #[serde(flatten)]
+    predicate: PredicateSpec,
+}
+
+/// A predicate on a payload field, addressed by a dot-separated path such as
+/// `symbol` or `pc.0.a`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "ConditionSpec")]
+pub struct Condition {
+    path: Vec<String>,
+    predicate: Predicate,
+}
+
+impl TryFrom<ConditionSpec> for Condition {
+    type Error = String;
+
+    fn try_from(spec: ConditionSpec) -> Result<Self, Self::Error> {
+        let predicate = match spec.predicate {
+            PredicateSpec::Equals(value) => Predicate::Equals(value),
+            PredicateSpec::In(values) => Predicate::In(values),
+            PredicateSpec::Range { min, max } => Predicate::Range { min, max },
+            PredicateSpec::Regex(pattern) => Predicate::Regex(
+                Regex::new(&pattern)
+                    .map_err(|e| format!("Invalid regex for field '{}': {}", spec.field, e))?,
+            ),
+        };
+        Ok(Self::new(&spec.field, predicate))
+    }
+}
+
+impl Condition {
+    fn new(field: &str, predicate: Predicate) -> Self {
+        Self {
+            path: field.split('.').map(str::to_string).collect(),
+            predicate,
+        }
+    }
+
+    /// Field equals `value`
+    pub fn equals(field: &str, value: impl Into<Value>) -> Self {
+        Self::new(field, Predicate::Equals(value.into()))
+    }
+
+    /// Field equals any of `values`
+    pub fn in_set<I, V>(field: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        Self::new(
+            field,
+            Predicate::In(values.into_iter().map(Into::into).collect()),
+        )
+    }
+
+    /// Numeric field (or numeric string) lies within `min..=max`
+    pub fn range(field: &str, min: Option<f64>, max: Option<f64>) -> Self {
+        Self::new(field, Predicate::Range { min, max })
+    }
+
+    /// String field matches the regular expression `pattern`
+    pub fn regex(field: &str, pattern: &str) -> Result<Self, String> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| format!("Invalid regex for field '{}': {}", field, e))?;
+        Ok(Self::new(field, Predicate::Regex(regex)))
+    }
+
+    /// Returns whether the payload field exists and satisfies the predicate.
+    pub fn matches(&self, payload: &Value) -> bool {
+        let Some(value) = lookup(payload, &self.path) else {
+            return false;
+        };
+        match &self.predicate {
+            Predicate::Equals(expected) => value == expected,
+            Predicate::In(values) => values.contains(value),
+            Predicate::Range { min, max } => {
+                let number = match value {
+                    Value::Number(n) => n.as_f64(),
+                    Value::String(s) => s.parse::<f64>().ok(),
+                    _ => None,
+                };
+                number.is_some_and(|n| {
+                    min.is_none_or(|min| n >= min) && max.is_none_or(|max| n <= max)
+                })
+            }
+            Predicate::Regex(regex) => value.as_str().is_some_and(|s| regex.is_match(s)),
+        }
+    }
+}
+
+/// Follows a dot-separated path through objects and arrays.
+fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+/// A routing rule: messages matching the topic, type and every condition are sent
+/// to the output channel named by `route`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    /// Name of the output channel
+    pub route: String,
+
+    /// Topic to match, or any topic if unset
+    #[serde(default)]
+    pub topic: Option<Topic>,
+
+    /// Message type to match, or any type if unset
+    #[serde(default, rename = "type")]
+    pub message_type: Option<MessageType>,
+
+    /// Payload predicates that must all hold
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+}
+
+impl Rule {
+    /// Creates a rule that routes every message to `route`.
+    pub fn new(route: impl Into<String>) -> Self {
+        Self {
+            route: route.into(),
+            topic: None,
+            message_type: None,
+            conditions: Vec::new(),
+        }
+    }
+
+    /// Only match messages of a topic
+    pub fn with_topic(mut self, topic: Topic) -> Self {
+        self.topic = Some(topic);
+        self
+    }
+
+    /// Only match messages of a type
+    pub fn with_message_type(mut self, message_type: MessageType) -> Self {
+        self.message_type = Some(message_type);
+        self
+    }
+
+    /// Add a payload condition
+    pub fn with_condition(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Returns whether the message satisfies the rule.
+    pub fn matches(&self, message: &Message) -> bool {
+        self.topic.as_ref().is_none_or(|t| *t == message.topic)
+            && self
+                .message_type
+                .as_ref()
+                .is_none_or(|t| *t == MessageType::All || *t == message.message_type)
+            && self.conditions.iter().all(|c| c.matches(&message.payload))
+    }
+}
+
+/// An ordered list of routing rules.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RuleSet {
+    /// Rules, evaluated in order
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Creates a rule set from rules.
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    /// Parses a rule set from TOML.
+    pub fn from_toml_str(s: &str) -> Result<Self, String> {
+        toml::from_str(s).map_err(|e| format!("Failed to parse rules: {}", e))
+    }
+
+    /// Parses a rule set from JSON.
+    pub fn from_json_str(s: &str) -> Result<Self, String> {
+        serde_json::from_str(s).map_err(|e| format!("Failed to parse rules: {}", e))
+    }
+
+    /// Loads a rule set from a `.toml` or `.json` file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json_str(&contents),
+            Some("toml") => Self::from_toml_str(&contents),
+            _ => Err(format!(
+                "Unsupported rules file {}: expected .toml or .json",
+                path.display()
+            )),
+        }
+    }
+
+    /// Returns the routes of every matching rule, without duplicates, in rule order.
+    pub fn routes(&self, message: &Message) -> Vec<&str> {
+        let mut routes: Vec<&str> = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.matches(message)) {
+            if !routes.contains(&rule.route.as_str()) {
+                routes.push(&rule.route);
+            }
+        }
+        routes
+    }
+}
+
+/// Cloneable handle for replacing a router's rules while it runs.
+#[derive(Debug, Clone)]
+pub struct RulesHandle {
+    rules: Arc<RwLock<RuleSet>>,
+}
+
+impl RulesHandle {
+    /// Replaces the rule set.
+    pub fn reload(&self, rules: RuleSet) {
+        if let Ok(mut current) = self.rules.write() {
+            *current = rules;
+        }
+    }
+
+    /// Replaces the rule set with the contents of a `.toml` or `.json` file.
+    ///
+    /// The current rules are kept if the file cannot be loaded.
+    pub fn reload_from_file(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        self.reload(RuleSet::from_file(path)?);
+        Ok(())
+    }
+}
+
+/// Routes messages to named output channels according to a [`RuleSet`].
+pub struct MessageRouter {
+    rules: Arc<RwLock<RuleSet>>,
+    outputs: HashMap<String, mpsc::UnboundedSender<Message>>,
+}
+
+impl MessageRouter {
+    /// Creates a router with the given rules and no output channels.
+    pub fn new(rules: RuleSet) -> Self {
+        Self {
+            rules: Arc::new(RwLock::new(rules)),
+            outputs: HashMap::new(),
+        }
+    }
+
+    /// Opens the output channel `name`, replacing any previous receiver for it.
+    pub fn channel(&mut self, name: impl Into<String>) -> mpsc::UnboundedReceiver<Message> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.outputs.insert(name.into(), tx);
+        rx
+    }
+
+    /// Returns a handle for reloading the rules.
+    pub fn rules_handle(&self) -> RulesHandle {
+        RulesHandle {
+            rules: self.rules.clone(),
+        }
+    }
+
+    /// Sends the message to every open channel whose rule matches, returning the
+    /// number of channels it was delivered to.
+    pub fn route(&self, message: &Message) -> usize {
+        let Ok(rules) = self.rules.read() else {
+            return 0;
+        };
+        let mut delivered = 0;
+        for route in rules.routes(message) {
+            match self.outputs.get(route) {
+                Some(tx) if tx.send(message.clone()).is_ok() => delivered += 1,
+                _ => {
+                    trace_event!(debug, route, "No open channel for route");
+                }
+            }
+        }
+        delivered
+    }
+
+    /// Routes messages from the client until its connection ends.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub async fn run(&self, client: &mut RealTimeDataClient) {
+        while let Some(result) = client.recv().await {
+            match result {
+                Ok(message) => {
+                    self.route(&message);
+                }
+                Err(e) => {
+                    trace_event!(warn, error = %e, "Client error while routing");
+                }
+            }
+        }
+    }
+}
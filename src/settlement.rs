@@ -0,0 +1,321 @@
+//! Settlement tracking for the user's CLOB trades.
+//!
+//! A trade is re-sent on the `clob_user` feed each time its settlement status
+//! changes. A [`SettlementTracker`] records every transition and flags trades that
+//! fail or stay in a pending status for too long.
+
+use crate::model::{Message, MessageType, Topic};
+use crate::types::ClobTrade;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_MATCHED_TIMEOUT: Duration = Duration::from_secs(60);
+const DEFAULT_MINED_TIMEOUT: Duration = Duration::from_secs(300);
+const DEFAULT_RETRYING_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Settlement status of a trade.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettlementStatus {
+    /// Matched off-chain, not yet submitted
+    Matched,
+    /// Included in a block
+    Mined,
+    /// Final on-chain
+    Confirmed,
+    /// Submission failed and is being retried
+    Retrying,
+    /// Settlement failed permanently
+    Failed,
+    /// A status this crate does not know
+    Other(String),
+}
+
+impl SettlementStatus {
+    /// Parses a `ClobTrade.status` value.
+    pub fn parse(status: &str) -> Self {
+        match status.to_ascii_uppercase().as_str() {
+            "MATCHED" => Self::Matched,
+            "MINED" => Self::Mined,
+            "CONFIRMED" => Self::Confirmed,
+            "RETRYING" => Self::Retrying,
+            "FAILED" => Self::Failed,
+            _ => Self::Other(status.to_string()),
+        }
+    }
+
+    /// Returns whether settlement has finished, successfully or not.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Confirmed | Self::Failed)
+    }
+
+    /// How far along settlement the status is. A retry can move a trade from MINED
+    /// to RETRYING and back, so they share a stage.
+    fn stage(&self) -> Option<u8> {
+        match self {
+            Self::Matched => Some(0),
+            Self::Mined | Self::Retrying => Some(1),
+            Self::Confirmed | Self::Failed => Some(2),
+            Self::Other(_) => None,
+        }
+    }
+
+    /// Returns whether a trade in this status can move to `next`. Final statuses
+    /// never change, and stale updates for an earlier stage are not transitions.
+    fn can_move_to(&self, next: &Self) -> bool {
+        if self.is_final() {
+            return false;
+        }
+        match (self.stage(), next.stage()) {
+            (Some(current), Some(next)) => next >= current,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for SettlementStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Matched => f.write_str("MATCHED"),
+            Self::Mined => f.write_str("MINED"),
+            Self::Confirmed => f.write_str("CONFIRMED"),
+            Self::Retrying => f.write_str("RETRYING"),
+            Self::Failed => f.write_str("FAILED"),
+            Self::Other(status) => f.write_str(status),
+        }
+    }
+}
+
+/// How long a trade may stay in each pending status before it is flagged as stuck.
+#[derive(Debug, Clone)]
+pub struct SettlementConfig {
+    /// Time allowed in MATCHED
+    pub matched: Duration,
+    /// Time allowed in MINED
+    pub mined: Duration,
+    /// Time allowed in RETRYING
+    pub retrying: Duration,
+}
+
+impl Default for SettlementConfig {
+    fn default() -> Self {
+        Self {
+            matched: DEFAULT_MATCHED_TIMEOUT,
+            mined: DEFAULT_MINED_TIMEOUT,
+            retrying: DEFAULT_RETRYING_TIMEOUT,
+        }
+    }
+}
+
+impl SettlementConfig {
+    /// Time allowed in a status, or `None` if the status is never considered stuck.
+    fn timeout(&self, status: &SettlementStatus) -> Option<Duration> {
+        match status {
+            SettlementStatus::Matched => Some(self.matched),
+            SettlementStatus::Mined => Some(self.mined),
+            SettlementStatus::Retrying => Some(self.retrying),
+            _ => None,
+        }
+    }
+}
+
+/// A recorded status change.
+#[derive(Debug, Clone)]
+pub struct Transition {
+    /// Status entered
+    pub status: SettlementStatus,
+    /// When the status was observed
+    pub at: SystemTime,
+    /// Transaction hash reported with the status, if any
+    pub transaction_hash: Option<String>,
+}
+
+/// Settlement history of a trade.
+#[derive(Debug, Clone)]
+pub struct TradeSettlement {
+    /// Unique identifier for the match record
+    pub trade_id: String,
+    /// Condition ID or market identifier
+    pub market: String,
+    /// ERC1155 token ID of the conditional token
+    pub asset_id: String,
+    /// Latest transaction hash, for reconciliation with the chain
+    pub transaction_hash: Option<String>,
+    /// Every status change, oldest first
+    pub transitions: Vec<Transition>,
+    /// Whether the trade has been flagged as stuck in its current status
+    pub stuck: bool,
+}
+
+impl TradeSettlement {
+    /// Current status.
+    pub fn status(&self) -> &SettlementStatus {
+        // A settlement is only created along with its first transition.
+        &self.transitions[self.transitions.len() - 1].status
+    }
+
+    /// When the current status was entered.
+    pub fn since(&self) -> SystemTime {
+        self.transitions[self.transitions.len() - 1].at
+    }
+}
+
+/// A settlement change worth acting on.
+#[derive(Debug, Clone)]
+pub enum SettlementEvent {
+    /// The trade moved to a new status
+    Transitioned(TradeSettlement),
+    /// The trade failed to settle
+    Failed(TradeSettlement),
+    /// The trade has stayed in a pending status past its threshold
+    Stuck {
+        settlement: TradeSettlement,
+        /// How long it has been in the status
+        elapsed: Duration,
+    },
+}
+
+impl SettlementEvent {
+    /// Returns the settlement the event is about.
+    pub fn settlement(&self) -> &TradeSettlement {
+        match self {
+            Self::Transitioned(settlement)
+            | Self::Failed(settlement)
+            | Self::Stuck { settlement, .. } => settlement,
+        }
+    }
+}
+
+/// Tracks the settlement of the user's trades from `clob_user` trade messages.
+///
+/// Statuses only move forward: MATCHED, then MINED or RETRYING, then CONFIRMED or
+/// FAILED. Stale updates that would move a trade backwards are dropped.
+///
+/// Stuck trades are only detected by [`SettlementTracker::check_stuck`], so call it
+/// periodically.
+#[derive(Default)]
+pub struct SettlementTracker {
+    config: SettlementConfig,
+    trades: HashMap<String, TradeSettlement>,
+}
+
+impl SettlementTracker {
+    /// Creates a tracker with the given stuck thresholds.
+    pub fn new(config: SettlementConfig) -> Self {
+        Self {
+            config,
+            trades: HashMap::new(),
+        }
+    }
+
+    /// Applies a message, ignoring anything other than `clob_user` trades. The
+    /// transition is timestamped with the message's send time.
+    pub fn apply(&mut self, message: &Message) -> Result<Vec<SettlementEvent>, String> {
+        if message.topic != Topic::ClobUser || message.message_type != MessageType::Trade {
+            return Ok(Vec::new());
+        }
+        let at = UNIX_EPOCH + Duration::from_millis(message.timestamp);
+        Ok(self.apply_trade_at(&message.decode()?, at))
+    }
+
+    /// Applies a trade update observed now.
+    pub fn apply_trade(&mut self, trade: &ClobTrade) -> Vec<SettlementEvent> {
+        self.apply_trade_at(trade, SystemTime::now())
+    }
+
+    /// Applies a trade update observed at `at`.
+    pub fn apply_trade_at(&mut self, trade: &ClobTrade, at: SystemTime) -> Vec<SettlementEvent> {
+        let status = SettlementStatus::parse(&trade.status);
+        let transaction_hash = Some(trade.transaction_hash.clone()).filter(|hash| !hash.is_empty());
+        let transition = Transition {
+            status: status.clone(),
+            at,
+            transaction_hash: transaction_hash.clone(),
+        };
+
+        let settlement = match self.trades.get_mut(&trade.id) {
+            Some(settlement) => {
+                if *settlement.status() != status && !settlement.status().can_move_to(&status) {
+                    return Vec::new();
+                }
+                if let Some(hash) = transaction_hash {
+                    settlement.transaction_hash = Some(hash);
+                }
+                if *settlement.status() == status {
+                    return Vec::new();
+                }
+                settlement.transitions.push(transition);
+                settlement.stuck = false;
+                settlement
+            }
+            None => self
+                .trades
+                .entry(trade.id.clone())
+                .or_insert(TradeSettlement {
+                    trade_id: trade.id.clone(),
+                    market: trade.market.clone(),
+                    asset_id: trade.asset_id.clone(),
+                    transaction_hash,
+                    transitions: vec![transition],
+                    stuck: false,
+                }),
+        };
+
+        let mut events = vec![SettlementEvent::Transitioned(settlement.clone())];
+        if status == SettlementStatus::Failed {
+            events.push(SettlementEvent::Failed(settlement.clone()));
+        }
+        events
+    }
+
+    /// Flags trades that have been in a pending status longer than its threshold.
+    ///
+    /// Each trade is reported once per status.
+    pub fn check_stuck(&mut self, now: SystemTime) -> Vec<SettlementEvent> {
+        let mut events = Vec::new();
+        for settlement in self.trades.values_mut().filter(|s| !s.stuck) {
+            let Some(timeout) = self.config.timeout(settlement.status()) else {
+                continue;
+            };
+            let elapsed = now.duration_since(settlement.since()).unwrap_or_default();
+            if elapsed > timeout {
+                settlement.stuck = true;
+                events.push(SettlementEvent::Stuck {
+                    settlement: settlement.clone(),
+                    elapsed,
+                });
+            }
+        }
+        events
+    }
+
+    /// Returns the settlement history of a trade.
+    pub fn trade(&self, trade_id: &str) -> Option<&TradeSettlement> {
+        self.trades.get(trade_id)
+    }
+
+    /// Returns every tracked trade.
+    pub fn trades(&self) -> impl Iterator<Item = &TradeSettlement> {
+        self.trades.values()
+    }
+
+    /// Returns the trades that have not settled yet.
+    pub fn pending(&self) -> impl Iterator<Item = &TradeSettlement> {
+        self.trades().filter(|s| !s.status().is_final())
+    }
+
+    /// Returns the trades that failed to settle.
+    pub fn failed(&self) -> impl Iterator<Item = &TradeSettlement> {
+        self.trades()
+            .filter(|s| *s.status() == SettlementStatus::Failed)
+    }
+
+    /// Forgets confirmed trades, returning how many were removed. Failed trades are
+    /// kept for reconciliation.
+    pub fn prune_confirmed(&mut self) -> usize {
+        let before = self.trades.len();
+        self.trades
+            .retain(|_, s| *s.status() != SettlementStatus::Confirmed);
+        before - self.trades.len()
+    }
+}
@@ -0,0 +1,337 @@
+use crate::client::{RealTimeDataClient, ReconnectConfig};
+use crate::model::{Message, Subscription};
+use crate::trace::trace_event;
+use futures_util::future::select_all;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
+
+const SHARD_RECONNECT_ATTEMPTS: u32 = 3;
+
+/// Strategy used to assign subscriptions to shards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShardKey {
+    /// All subscriptions of a topic share a shard
+    Topic,
+    /// Subscriptions are spread by their filter value (slug, parent entity or token list)
+    Market,
+    /// Token ID filters are split so that each token is placed by its own hash
+    TokenId,
+}
+
+/// Health information for a single shard.
+#[derive(Debug, Clone)]
+pub struct ShardHealth {
+    /// Index of the shard
+    pub index: usize,
+    /// Whether the shard's WebSocket is currently up
+    pub connected: bool,
+    /// Whether the shard lost its WebSocket and is trying to reconnect
+    pub reconnecting: bool,
+    /// Number of subscriptions currently placed on the shard
+    pub subscriptions: usize,
+    /// Number of messages received from the shard
+    pub messages: u64,
+    /// When the last message was received from the shard
+    pub last_message_at: Option<Instant>,
+}
+
+struct Shard {
+    client: RealTimeDataClient,
+    connected: bool,
+    subscriptions: Vec<Subscription>,
+    messages: u64,
+    last_message_at: Option<Instant>,
+}
+
+/// A client that spreads subscriptions across several WebSocket connections and
+/// merges their messages into a single stream.
+///
+/// When a shard's connection ends for good, its subscriptions are moved to the remaining shards.
+pub struct ShardedClient {
+    shards: Vec<Shard>,
+    key: ShardKey,
+    cursor: usize,
+}
+
+impl ShardedClient {
+    /// Creates a sharded client with `shards` default clients.
+    ///
+    /// Each shard gives up after a few reconnect attempts so that its subscriptions
+    /// can be moved to the shards that are still alive.
+    pub fn new(shards: usize, key: ShardKey) -> Self {
+        let reconnect = ReconnectConfig {
+            max_attempts: Some(SHARD_RECONNECT_ATTEMPTS),
+            ..ReconnectConfig::default()
+        };
+        Self::from_clients(
+            (0..shards.max(1))
+                .map(|_| RealTimeDataClient::new().with_reconnect(Some(reconnect.clone())))
+                .collect(),
+            key,
+        )
+    }
+
+    /// Creates a sharded client from preconfigured clients, one per shard.
+    pub fn from_clients(clients: Vec<RealTimeDataClient>, key: ShardKey) -> Self {
+        let shards = clients
+            .into_iter()
+            .map(|client| Shard {
+                client,
+                connected: false,
+                subscriptions: Vec::new(),
+                messages: 0,
+                last_message_at: None,
+            })
+            .collect();
+        Self {
+            shards,
+            key,
+            cursor: 0,
+        }
+    }
+
+    /// Connects every shard.
+    pub async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        for shard in &mut self.shards {
+            shard.client.connect().await?;
+            shard.connected = true;
+        }
+        Ok(())
+    }
+
+    /// Subscribes to data streams, placing each subscription on a shard.
+    pub async fn subscribe(&mut self, subscriptions: Vec<Subscription>) -> Result<(), String> {
+        let mut placed: HashMap<usize, Vec<Subscription>> = HashMap::new();
+        for subscription in subscriptions {
+            for (index, part) in self.place(subscription)? {
+                placed.entry(index).or_default().push(part);
+            }
+        }
+
+        // Validate up front so an invalid entry cannot leave some shards subscribed.
+        for (index, subscriptions) in &placed {
+            for subscription in subscriptions {
+                self.shards[*index].client.validate(subscription)?;
+            }
+        }
+
+        for (index, subscriptions) in placed {
+            let shard = &mut self.shards[index];
+            shard.client.subscribe(subscriptions.clone()).await?;
+            shard.subscriptions.extend(subscriptions);
+        }
+        Ok(())
+    }
+
+    /// Unsubscribes from data streams on whichever shards hold them.
+    pub async fn unsubscribe(&mut self, subscriptions: Vec<Subscription>) -> Result<(), String> {
+        for subscription in subscriptions {
+            let tokens = token_ids(&subscription);
+            for shard in self.shards.iter_mut().filter(|s| s.connected) {
+                let (removed, kept) =
+                    remove_subscription(&mut shard.subscriptions, &subscription, tokens.as_deref());
+                if !removed.is_empty() {
+                    shard.client.unsubscribe(removed).await?;
+                }
+                if !kept.is_empty() {
+                    shard.client.subscribe(kept).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Receives the next message from any shard.
+    ///
+    /// Returns `None` once every shard connection has ended.
+    pub async fn recv(&mut self) -> Option<Result<Message, String>> {
+        loop {
+            if self.live_shards().is_empty() {
+                return None;
+            }
+
+            // Rotate the polling order so one busy shard cannot starve the others.
+            self.cursor = (self.cursor + 1) % self.shards.len();
+            let cursor = self.cursor;
+            let (head, tail) = self.shards.split_at_mut(cursor);
+            let futures = tail
+                .iter_mut()
+                .zip(cursor..)
+                .chain(head.iter_mut().zip(0..))
+                .filter(|(shard, _)| shard.connected)
+                .map(|(shard, index)| Box::pin(async move { (index, shard.client.recv().await) }));
+
+            let ((index, result), _, _) = select_all(futures).await;
+            match result {
+                Some(message) => {
+                    let shard = &mut self.shards[index];
+                    shard.messages += 1;
+                    shard.last_message_at = Some(Instant::now());
+                    return Some(message);
+                }
+                None => self.rebalance(index).await,
+            }
+        }
+    }
+
+    /// Closes every shard connection.
+    pub async fn disconnect(&self) -> Result<(), String> {
+        for shard in self.shards.iter().filter(|s| s.connected) {
+            shard.client.disconnect().await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the health of every shard.
+    pub fn health(&self) -> Vec<ShardHealth> {
+        self.shards
+            .iter()
+            .enumerate()
+            .map(|(index, shard)| {
+                // A shard that gave up is no longer polled, whatever its metrics say.
+                let up = shard.client.metrics().connected;
+                ShardHealth {
+                    index,
+                    connected: shard.connected && up,
+                    reconnecting: shard.connected && !up,
+                    subscriptions: shard.subscriptions.len(),
+                    messages: shard.messages,
+                    last_message_at: shard.last_message_at,
+                }
+            })
+            .collect()
+    }
+
+    fn live_shards(&self) -> Vec<usize> {
+        self.shards
+            .iter()
+            .enumerate()
+            .filter(|(_, shard)| shard.connected)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Marks a shard as dead and moves its subscriptions to the remaining shards.
+    async fn rebalance(&mut self, dead: usize) {
+        let orphaned = {
+            let shard = &mut self.shards[dead];
+            shard.connected = false;
+            std::mem::take(&mut shard.subscriptions)
+        };
+        trace_event!(
+            warn,
+            shard = dead,
+            subscriptions = orphaned.len(),
+            "Shard connection ended, rebalancing"
+        );
+        if orphaned.is_empty() || self.live_shards().is_empty() {
+            return;
+        }
+
+        // Subscriptions that cannot be placed again are dropped with the dead shard.
+        let _ = self.subscribe(orphaned).await;
+    }
+
+    /// Splits a subscription into the parts each live shard should carry.
+    fn place(&self, subscription: Subscription) -> Result<Vec<(usize, Subscription)>, String> {
+        let live = self.live_shards();
+        if live.is_empty() {
+            return Err("No connected shards".to_string());
+        }
+        let pick = |value: &str| live[(hash(value) % live.len() as u64) as usize];
+
+        if self.key == ShardKey::TokenId {
+            if let Some(tokens) = token_ids(&subscription) {
+                let mut groups: HashMap<usize, Vec<String>> = HashMap::new();
+                for token in tokens {
+                    groups.entry(pick(&token)).or_default().push(token);
+                }
+                return groups
+                    .into_iter()
+                    .map(|(index, tokens)| Ok((index, with_tokens(&subscription, tokens)?)))
+                    .collect();
+            }
+        }
+
+        let value = match self.key {
+            ShardKey::Topic => subscription.topic.to_string(),
+            ShardKey::Market | ShardKey::TokenId => subscription
+                .filters
+                .clone()
+                .unwrap_or_else(|| subscription.topic.to_string()),
+        };
+        Ok(vec![(pick(&value), subscription)])
+    }
+}
+
+fn hash(value: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns the token IDs of a subscription whose filter is a token list.
+fn token_ids(subscription: &Subscription) -> Option<Vec<String>> {
+    subscription
+        .filters
+        .as_deref()
+        .and_then(|filters| serde_json::from_str::<Vec<String>>(filters).ok())
+}
+
+fn with_tokens(subscription: &Subscription, tokens: Vec<String>) -> Result<Subscription, String> {
+    let filters = serde_json::to_string(&tokens).map_err(|e| e.to_string())?;
+    Ok(subscription.clone().with_raw_filter(filters))
+}
+
+/// Removes `target` from `held`, returning the subscriptions to unsubscribe on the wire
+/// and the narrower subscriptions to make in their place.
+///
+/// Token list filters are matched per token, since a list may have been split across shards.
+/// The server only knows the subscriptions as they were made, so a partly removed token
+/// list is unsubscribed whole and its remaining tokens are subscribed again.
+fn remove_subscription(
+    held: &mut Vec<Subscription>,
+    target: &Subscription,
+    tokens: Option<&[String]>,
+) -> (Vec<Subscription>, Vec<Subscription>) {
+    let mut removed = Vec::new();
+    let mut replacements = Vec::new();
+    held.retain_mut(|subscription| {
+        if subscription.topic != target.topic
+            || subscription.subscription_type != target.subscription_type
+        {
+            return true;
+        }
+
+        match (tokens, token_ids(subscription)) {
+            (Some(tokens), Some(held_tokens)) => {
+                let (matched, kept): (Vec<String>, Vec<String>) = held_tokens
+                    .into_iter()
+                    .partition(|token| tokens.contains(token));
+                if matched.is_empty() {
+                    return true;
+                }
+                removed.push(subscription.clone());
+                if kept.is_empty() {
+                    return false;
+                }
+                match with_tokens(subscription, kept) {
+                    Ok(rest) => {
+                        replacements.push(rest.clone());
+                        *subscription = rest;
+                        true
+                    }
+                    Err(_) => false,
+                }
+            }
+            _ if subscription.filters == target.filters => {
+                removed.push(subscription.clone());
+                false
+            }
+            _ => true,
+        }
+    });
+    (removed, replacements)
+}
@@ -0,0 +1,330 @@
+//! Rolling trade tape over the `activity` topic.
+//!
+//! A [`TradeTape`] keeps, per market, the most recent and the largest trades, and
+//! buy/sell volume over sliding windows. Volume is aggregated into fixed-width time
+//! buckets, so memory depends on the longest window rather than the trade rate, and
+//! markets that stop trading are dropped once the longest window has passed.
+
+use crate::model::{Message, MessageType, Topic};
+use crate::types::TradeActivity;
+use crate::util::unix_now;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+const DEFAULT_LAST_TRADES: usize = 100;
+const DEFAULT_LARGEST_TRADES: usize = 10;
+const DEFAULT_BUCKET: Duration = Duration::from_secs(1);
+
+/// Settings for a [`TradeTape`].
+#[derive(Debug, Clone)]
+pub struct TapeConfig {
+    /// Number of recent trades kept per market
+    pub last_trades: usize,
+    /// Number of largest trades kept per market
+    pub largest_trades: usize,
+    /// Sliding windows to aggregate volume over; markets with no trades within the
+    /// longest one are dropped
+    pub windows: Vec<Duration>,
+    /// Width of the aggregation buckets; windows are accurate to one bucket
+    pub bucket: Duration,
+}
+
+impl Default for TapeConfig {
+    fn default() -> Self {
+        Self {
+            last_trades: DEFAULT_LAST_TRADES,
+            largest_trades: DEFAULT_LARGEST_TRADES,
+            windows: vec![
+                Duration::from_secs(60),
+                Duration::from_secs(5 * 60),
+                Duration::from_secs(60 * 60),
+            ],
+            bucket: DEFAULT_BUCKET,
+        }
+    }
+}
+
+impl TapeConfig {
+    fn bucket_secs(&self) -> i64 {
+        self.bucket.as_secs().max(1) as i64
+    }
+
+    fn longest_window(&self) -> Duration {
+        self.windows.iter().copied().max().unwrap_or_default()
+    }
+}
+
+/// Buy and sell activity over a window.
+#[derive(Debug, Clone, Default)]
+pub struct WindowStats {
+    /// Length of the window
+    pub window: Duration,
+    /// Shares bought
+    pub buy_volume: f64,
+    /// Shares sold
+    pub sell_volume: f64,
+    /// Number of buys
+    pub buy_count: u64,
+    /// Number of sells
+    pub sell_count: u64,
+    /// Sum of price times size over all trades
+    pub notional: f64,
+}
+
+impl WindowStats {
+    /// Shares traded on both sides.
+    pub fn volume(&self) -> f64 {
+        self.buy_volume + self.sell_volume
+    }
+
+    /// Number of trades on both sides.
+    pub fn count(&self) -> u64 {
+        self.buy_count + self.sell_count
+    }
+
+    /// Volume-weighted average price, if anything traded.
+    pub fn vwap(&self) -> Option<f64> {
+        let volume = self.volume();
+        (volume > 0.0).then(|| self.notional / volume)
+    }
+
+    fn add(&mut self, other: &WindowStats) {
+        self.buy_volume += other.buy_volume;
+        self.sell_volume += other.sell_volume;
+        self.buy_count += other.buy_count;
+        self.sell_count += other.sell_count;
+        self.notional += other.notional;
+    }
+}
+
+struct Bucket {
+    /// Start of the bucket in UNIX seconds
+    start: i64,
+    stats: WindowStats,
+}
+
+/// The tape of a single market.
+pub struct MarketTape {
+    /// Slug of the market
+    pub slug: String,
+    /// Condition ID of the market
+    pub condition_id: String,
+    recent: VecDeque<TradeActivity>,
+    largest: Vec<TradeActivity>,
+    buckets: VecDeque<Bucket>,
+    /// When the last trade was recorded, in UNIX seconds
+    last_recorded: i64,
+}
+
+impl MarketTape {
+    fn new(trade: &TradeActivity) -> Self {
+        Self {
+            slug: trade.slug.clone(),
+            condition_id: trade.condition_id.clone(),
+            recent: VecDeque::new(),
+            largest: Vec::new(),
+            buckets: VecDeque::new(),
+            last_recorded: 0,
+        }
+    }
+
+    /// Drops buckets that end before the longest window ending at `now`.
+    fn prune(&mut self, config: &TapeConfig, now: i64) {
+        let width = config.bucket_secs();
+        let horizon = now - config.longest_window().as_secs() as i64;
+        while self
+            .buckets
+            .front()
+            .is_some_and(|bucket| bucket.start + width <= horizon)
+        {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn record(&mut self, trade: &TradeActivity, config: &TapeConfig, now: i64) {
+        self.last_recorded = now;
+        self.recent.push_front(trade.clone());
+        self.recent.truncate(config.last_trades);
+
+        if config.largest_trades > 0 {
+            let position = self
+                .largest
+                .partition_point(|other| notional(other) >= notional(trade));
+            if position < config.largest_trades {
+                self.largest.insert(position, trade.clone());
+                self.largest.truncate(config.largest_trades);
+            }
+        }
+
+        self.prune(config, now);
+        if trade.timestamp < now - config.longest_window().as_secs() as i64 {
+            return;
+        }
+
+        let width = config.bucket_secs();
+        let start = trade.timestamp - trade.timestamp.rem_euclid(width);
+        let index = match self.buckets.binary_search_by_key(&start, |b| b.start) {
+            Ok(index) => index,
+            Err(index) => {
+                self.buckets.insert(
+                    index,
+                    Bucket {
+                        start,
+                        stats: WindowStats::default(),
+                    },
+                );
+                index
+            }
+        };
+        let stats = &mut self.buckets[index].stats;
+        let size = trade.size as f64;
+        if trade.side.eq_ignore_ascii_case("SELL") {
+            stats.sell_volume += size;
+            stats.sell_count += 1;
+        } else {
+            stats.buy_volume += size;
+            stats.buy_count += 1;
+        }
+        stats.notional += trade.price * size;
+    }
+
+    /// Most recent trades, newest first.
+    pub fn recent(&self) -> impl Iterator<Item = &TradeActivity> {
+        self.recent.iter()
+    }
+
+    /// Largest trades by notional (price times size) since the tape started,
+    /// largest first.
+    pub fn largest(&self) -> &[TradeActivity] {
+        &self.largest
+    }
+
+    /// Activity over the last `window` up to now.
+    pub fn stats(&self, window: Duration) -> WindowStats {
+        self.stats_at(window, unix_now())
+    }
+
+    /// Activity over the `window` ending at `now` (UNIX seconds).
+    pub fn stats_at(&self, window: Duration, now: i64) -> WindowStats {
+        let since = now - window.as_secs() as i64;
+        let mut total = WindowStats {
+            window,
+            ..Default::default()
+        };
+        for bucket in self.buckets.iter().rev() {
+            if bucket.start < since {
+                break;
+            }
+            if bucket.start <= now {
+                total.add(&bucket.stats);
+            }
+        }
+        total
+    }
+}
+
+/// Rolling trade tapes per market, built from `activity` trades.
+///
+/// Trade timestamps are UNIX seconds. Windows end at the current wall-clock time,
+/// and trades older than the longest window only reach the recent and largest
+/// lists. A market with no trade recorded within the longest window is dropped,
+/// along with its recent and largest trades; recording sweeps for such markets at
+/// most once per bucket, and [`TradeTape::prune`] sweeps on demand.
+#[derive(Default)]
+pub struct TradeTape {
+    config: TapeConfig,
+    /// Tapes by condition ID
+    markets: HashMap<String, MarketTape>,
+    /// Condition IDs by market slug
+    slugs: HashMap<String, String>,
+    /// When markets were last swept, in UNIX seconds
+    swept_at: i64,
+}
+
+impl TradeTape {
+    /// Creates an empty tape.
+    pub fn new(config: TapeConfig) -> Self {
+        Self {
+            config,
+            markets: HashMap::new(),
+            slugs: HashMap::new(),
+            swept_at: 0,
+        }
+    }
+
+    /// Applies an `activity` `trades` message, ignoring anything else. Returns
+    /// whether a trade was recorded.
+    pub fn apply(&mut self, message: &Message) -> Result<bool, String> {
+        if message.topic != Topic::Activity || message.message_type != MessageType::Trades {
+            return Ok(false);
+        }
+        self.record(&message.decode()?);
+        Ok(true)
+    }
+
+    /// Records a trade.
+    pub fn record(&mut self, trade: &TradeActivity) {
+        let now = unix_now();
+        self.slugs
+            .insert(trade.slug.clone(), trade.condition_id.clone());
+        self.markets
+            .entry(trade.condition_id.clone())
+            .or_insert_with(|| MarketTape::new(trade))
+            .record(trade, &self.config, now);
+        if now - self.swept_at >= self.config.bucket_secs() {
+            self.prune_at(now);
+        }
+    }
+
+    /// Drops expired buckets and the markets with no trade within the longest
+    /// window, returning how many markets were dropped.
+    pub fn prune(&mut self) -> usize {
+        self.prune_at(unix_now())
+    }
+
+    /// Like [`TradeTape::prune`], with the window ending at `now` (UNIX seconds).
+    pub fn prune_at(&mut self, now: i64) -> usize {
+        self.swept_at = now;
+        let horizon = now - self.config.longest_window().as_secs() as i64;
+        let before = self.markets.len();
+        for market in self.markets.values_mut() {
+            market.prune(&self.config, now);
+        }
+        self.markets
+            .retain(|_, market| market.last_recorded >= horizon);
+        let markets = &self.markets;
+        self.slugs
+            .retain(|_, condition_id| markets.contains_key(condition_id));
+        before - self.markets.len()
+    }
+
+    /// Returns the tape of a market by slug or condition ID.
+    pub fn market(&self, key: &str) -> Option<&MarketTape> {
+        self.markets
+            .get(key)
+            .or_else(|| self.markets.get(self.slugs.get(key)?))
+    }
+
+    /// Returns every market tape.
+    pub fn markets(&self) -> impl Iterator<Item = &MarketTape> {
+        self.markets.values()
+    }
+
+    /// Returns a market's activity over each configured window, shortest first.
+    pub fn window_stats(&self, key: &str) -> Vec<WindowStats> {
+        let Some(market) = self.market(key) else {
+            return Vec::new();
+        };
+        let now = unix_now();
+        let mut windows = self.config.windows.clone();
+        windows.sort();
+        windows
+            .into_iter()
+            .map(|window| market.stats_at(window, now))
+            .collect()
+    }
+}
+
+fn notional(trade: &TradeActivity) -> f64 {
+    trade.price * trade.size as f64
+}
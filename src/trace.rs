@@ -0,0 +1,11 @@
+//! Thin wrappers around the `tracing` macros so that instrumentation compiles away
+//! when the `tracing` feature is disabled.
+
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+    };
+}
+
+pub(crate) use trace_event;
@@ -1,3 +1,4 @@
+use crate::model::Topic;
 use serde::{Deserialize, Serialize};
 
 // ============================================================================
@@ -41,9 +42,10 @@ pub enum WebSocketResponse {
     #[serde(rename = "quote_expired")]
     QuoteExpired(RfqQuote),
 
-    // Crypto/Equity prices
+    // Crypto/Equity prices; the payload is kept as is, since only the topic tells
+    // the variants apart. Decode it with `PriceUpdate::from_payload`.
     #[serde(rename = "update")]
-    PriceUpdate(PriceUpdate),
+    PriceUpdate(serde_json::Value),
 
     // CLOB User (authenticated)
     #[serde(rename = "order")]
@@ -241,7 +243,12 @@ pub struct RfqQuote {
 // Crypto/Equity Prices
 // ============================================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Payload of an `update` message on a price topic.
+///
+/// `CryptoPrice` and `EquityPrice` have identical fields, so the variant can only be
+/// told from the message's topic. Decode it with [`PriceUpdate::from_payload`] or
+/// [`crate::Message::price_update`].
+#[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum PriceUpdate {
     CryptoPrice(CryptoPrice),
@@ -250,6 +257,33 @@ pub enum PriceUpdate {
     EquityPriceHistorical(EquityPriceHistorical),
 }
 
+impl PriceUpdate {
+    /// Decodes a price payload according to the topic it arrived on.
+    ///
+    /// `crypto_prices` and `crypto_prices_chainlink` yield the crypto variants and
+    /// `equity_prices` the equity variants. Payloads with a `data` array are
+    /// historical.
+    pub fn from_payload(topic: &Topic, payload: &serde_json::Value) -> Result<Self, String> {
+        let historical = payload.get("data").is_some_and(serde_json::Value::is_array);
+        let decoded = match (topic, historical) {
+            (Topic::CryptoPrices | Topic::CryptoPricesChainlink, false) => {
+                CryptoPrice::deserialize(payload).map(Self::CryptoPrice)
+            }
+            (Topic::CryptoPrices | Topic::CryptoPricesChainlink, true) => {
+                CryptoPriceHistorical::deserialize(payload).map(Self::CryptoPriceHistorical)
+            }
+            (Topic::EquityPrices, false) => {
+                EquityPrice::deserialize(payload).map(Self::EquityPrice)
+            }
+            (Topic::EquityPrices, true) => {
+                EquityPriceHistorical::deserialize(payload).map(Self::EquityPriceHistorical)
+            }
+            _ => return Err(format!("Topic {} does not carry price updates", topic)),
+        };
+        decoded.map_err(|e| format!("Failed to decode {} price update: {}", topic, e))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CryptoPrice {
     /// Symbol of the asset
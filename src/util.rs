@@ -0,0 +1,22 @@
+//! Small helpers shared by the trackers built on top of the message stream.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Parses a decimal string field, naming the field in the error.
+pub(crate) fn parse_number(field: &str, value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .map_err(|e| format!("Invalid {} '{}': {}", field, value, e))
+}
+
+/// Seconds since the UNIX epoch at `at`, or 0 if it is earlier.
+pub(crate) fn unix_seconds(at: SystemTime) -> i64 {
+    at.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Seconds since the UNIX epoch now.
+pub(crate) fn unix_now() -> i64 {
+    unix_seconds(SystemTime::now())
+}
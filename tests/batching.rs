@@ -0,0 +1,139 @@
+use futures_util::StreamExt;
+use polymarket_rtds::{
+    BatchConfig, MessageType, RealTimeDataClient, Subscription, SubscriptionFilter, Topic,
+};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+const WINDOW: Duration = Duration::from_millis(50);
+
+/// WebSocket stand-in that forwards every subscribe and unsubscribe frame it receives.
+async fn connect() -> (RealTimeDataClient, mpsc::UnboundedReceiver<Value>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        while let Some(Ok(msg)) = ws.next().await {
+            if let WsMessage::Text(text) = msg {
+                if let Ok(frame) = serde_json::from_str::<Value>(&text) {
+                    let _ = frame_tx.send(frame);
+                }
+            }
+        }
+    });
+
+    let mut client = RealTimeDataClient::new()
+        .with_host(format!("ws://{}", addr))
+        .with_batching(Some(BatchConfig {
+            window: WINDOW,
+            max_per_frame: 100,
+        }));
+    client.connect().await.unwrap();
+    (client, frame_rx)
+}
+
+fn symbol(symbol: &str) -> Subscription {
+    Subscription::new(Topic::CryptoPrices, MessageType::Update)
+        .with_filter(SubscriptionFilter::symbol(symbol))
+        .unwrap()
+}
+
+/// Returns the action and symbols of the next frame.
+async fn next_frame(frames: &mut mpsc::UnboundedReceiver<Value>) -> (String, Vec<String>) {
+    let frame = tokio::time::timeout(Duration::from_secs(5), frames.recv())
+        .await
+        .expect("timed out waiting for a frame")
+        .unwrap();
+    let symbols = frame["subscriptions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| {
+            let filters: Value = serde_json::from_str(s["filters"].as_str().unwrap()).unwrap();
+            filters["symbol"].as_str().unwrap().to_string()
+        })
+        .collect();
+    (frame["action"].as_str().unwrap().to_string(), symbols)
+}
+
+fn frame(action: &str, symbols: &[&str]) -> (String, Vec<String>) {
+    (
+        action.to_string(),
+        symbols.iter().map(|s| s.to_string()).collect(),
+    )
+}
+
+#[tokio::test]
+async fn merges_changes_within_a_window() {
+    let (client, mut frames) = connect().await;
+    client.subscribe(vec![symbol("btcusdt")]).await.unwrap();
+    client.subscribe(vec![symbol("ethusdt")]).await.unwrap();
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame("subscribe", &["btcusdt", "ethusdt"])
+    );
+}
+
+#[tokio::test]
+async fn subscribe_then_unsubscribe_of_new_stream_cancels_out() {
+    let (client, mut frames) = connect().await;
+    client.subscribe(vec![symbol("btcusdt")]).await.unwrap();
+    client.unsubscribe(vec![symbol("btcusdt")]).await.unwrap();
+    client.subscribe(vec![symbol("ethusdt")]).await.unwrap();
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame("subscribe", &["ethusdt"])
+    );
+}
+
+#[tokio::test]
+async fn resubscribe_then_unsubscribe_of_active_stream_unsubscribes() {
+    let (client, mut frames) = connect().await;
+    client.subscribe(vec![symbol("btcusdt")]).await.unwrap();
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame("subscribe", &["btcusdt"])
+    );
+
+    client.subscribe(vec![symbol("btcusdt")]).await.unwrap();
+    client.unsubscribe(vec![symbol("btcusdt")]).await.unwrap();
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame("unsubscribe", &["btcusdt"])
+    );
+}
+
+#[tokio::test]
+async fn unsubscribe_then_subscribe_of_active_stream_cancels_out() {
+    let (client, mut frames) = connect().await;
+    client.subscribe(vec![symbol("btcusdt")]).await.unwrap();
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame("subscribe", &["btcusdt"])
+    );
+
+    client.unsubscribe(vec![symbol("btcusdt")]).await.unwrap();
+    client.subscribe(vec![symbol("btcusdt")]).await.unwrap();
+    tokio::time::sleep(WINDOW * 4).await;
+    client.subscribe(vec![symbol("ethusdt")]).await.unwrap();
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame("subscribe", &["ethusdt"])
+    );
+}
+
+#[tokio::test]
+async fn unsubscribe_then_subscribe_of_inactive_stream_subscribes() {
+    let (client, mut frames) = connect().await;
+    client.unsubscribe(vec![symbol("btcusdt")]).await.unwrap();
+    client.subscribe(vec![symbol("btcusdt")]).await.unwrap();
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame("subscribe", &["btcusdt"])
+    );
+}
@@ -0,0 +1,111 @@
+use polymarket_rtds::{
+    ActivitySubscription, ClobApiKeyCreds, ClobMarketSubscription, ClobUserSubscription,
+    CommentsSubscription, CryptoPriceSubscription, EquityPriceSubscription, MessageType,
+    RfqSubscription, Subscription, Topic,
+};
+
+fn creds() -> ClobApiKeyCreds {
+    ClobApiKeyCreds::new("key", "secret", "passphrase")
+}
+
+#[test]
+fn builds_valid_wire_subscriptions() {
+    let cases: Vec<(Subscription, Topic, MessageType, Option<&str>)> = vec![
+        (
+            ActivitySubscription::trades()
+                .event_slug("election")
+                .build(),
+            Topic::Activity,
+            MessageType::Trades,
+            Some(r#"{"event_slug":"election"}"#),
+        ),
+        (
+            CommentsSubscription::all()
+                .parent_entity(100, "Event")
+                .build(),
+            Topic::Comments,
+            MessageType::All,
+            Some(r#"{"parentEntityID":100,"parentEntityType":"Event"}"#),
+        ),
+        (
+            RfqSubscription::quote_created().build(),
+            Topic::Rfq,
+            MessageType::QuoteCreated,
+            None,
+        ),
+        (
+            CryptoPriceSubscription::update().symbol("btcusdt").build(),
+            Topic::CryptoPrices,
+            MessageType::Update,
+            Some(r#"{"symbol":"btcusdt"}"#),
+        ),
+        (
+            EquityPriceSubscription::update().build(),
+            Topic::EquityPrices,
+            MessageType::Update,
+            None,
+        ),
+        (
+            ClobMarketSubscription::price_change("1")
+                .token_id("2")
+                .build(),
+            Topic::ClobMarket,
+            MessageType::PriceChange,
+            Some(r#"["1","2"]"#),
+        ),
+        (
+            ClobMarketSubscription::agg_orderbook().build(),
+            Topic::ClobMarket,
+            MessageType::AggOrderbook,
+            None,
+        ),
+        (
+            ClobMarketSubscription::market_resolved().build(),
+            Topic::ClobMarket,
+            MessageType::MarketResolved,
+            None,
+        ),
+    ];
+    for (subscription, topic, message_type, filters) in cases {
+        assert_eq!(subscription.topic, topic);
+        assert_eq!(subscription.subscription_type, message_type);
+        assert_eq!(subscription.filters.as_deref(), filters);
+        assert!(
+            subscription.validate().is_ok(),
+            "{:?}: {:?}",
+            subscription,
+            subscription.validate()
+        );
+    }
+}
+
+#[test]
+fn the_last_activity_filter_wins() {
+    let subscription = ActivitySubscription::all()
+        .event_slug("election")
+        .market_slug("senate")
+        .build();
+    assert_eq!(
+        subscription.filters.as_deref(),
+        Some(r#"{"market_slug":"senate"}"#)
+    );
+}
+
+#[test]
+fn clob_user_subscriptions_carry_their_credentials() {
+    let subscription = ClobUserSubscription::trades(creds()).build();
+    assert_eq!(subscription.clob_auth, Some(creds()));
+    assert!(subscription.filters.is_none());
+    assert!(subscription.validate().is_ok());
+
+    // Without credentials the subscription is only valid for a client with a
+    // credential provider.
+    let subscription = ClobUserSubscription::all(None).build();
+    assert!(subscription.clob_auth.is_none());
+    let error = subscription.validate().unwrap_err();
+    assert!(
+        error.contains("requires clob_auth credentials"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,79 @@
+use polymarket_rtds::{CandleAggregator, CandleConfig};
+use std::time::{Duration, UNIX_EPOCH};
+
+const MINUTE: i64 = 60_000;
+const GRACE: i64 = 2_000;
+
+fn aggregator() -> CandleAggregator {
+    CandleAggregator::new(CandleConfig::default())
+}
+
+#[test]
+fn builds_wall_clock_aligned_bars() {
+    let mut candles = aggregator();
+    candles.record("btcusdt", 10 * MINUTE + 5_000, 100.0, 1.0);
+    candles.record("btcusdt", 10 * MINUTE + 20_000, 110.0, 2.0);
+    candles.record("btcusdt", 10 * MINUTE + 10_000, 90.0, 1.0);
+
+    let bar = candles.current("btcusdt").unwrap();
+    assert_eq!(bar.start, 10 * MINUTE);
+    assert_eq!(bar.end(), 11 * MINUTE);
+    assert_eq!(
+        (bar.open, bar.high, bar.low, bar.close),
+        (100.0, 110.0, 90.0, 110.0)
+    );
+    assert_eq!(bar.volume, 4.0);
+    assert_eq!(bar.ticks, 3);
+}
+
+#[test]
+fn keeps_bars_open_for_late_ticks_during_the_grace_window() {
+    let mut candles = aggregator();
+    candles.record("btcusdt", 10 * MINUTE + 59_000, 100.0, 0.0);
+    assert!(candles
+        .record("btcusdt", 11 * MINUTE + GRACE - 1, 101.0, 0.0)
+        .is_empty());
+    // Still inside the grace window, so the first bar takes the tick.
+    candles.record("btcusdt", 10 * MINUTE + 59_500, 99.0, 0.0);
+
+    let closed = candles.record("btcusdt", 11 * MINUTE + GRACE, 102.0, 0.0);
+    assert_eq!(closed.len(), 1);
+    assert_eq!(closed[0].low, 99.0);
+    assert_eq!(closed[0].ticks, 2);
+}
+
+#[test]
+fn closes_empty_intervals_once_the_grace_window_passes() {
+    let mut candles = aggregator();
+    candles.record("btcusdt", 10 * MINUTE, 100.0, 0.0);
+    // A tick three minutes later closes the first bar and the two empty intervals.
+    let closed = candles.record("btcusdt", 13 * MINUTE + GRACE, 103.0, 0.0);
+    assert_eq!(closed.len(), 1);
+    assert_eq!(closed[0].start, 10 * MINUTE);
+
+    // A late tick for an empty interval that already closed cannot open a bar
+    // older than the one just returned.
+    assert!(candles
+        .record("btcusdt", 12 * MINUTE + 30_000, 102.0, 0.0)
+        .is_empty());
+    assert_eq!(candles.late_ticks(), 1);
+    assert_eq!(candles.current("btcusdt").unwrap().start, 13 * MINUTE);
+}
+
+#[test]
+fn closes_idle_keys_by_wall_clock() {
+    let mut candles = aggregator();
+    candles.record("ethusdt", 10 * MINUTE, 100.0, 0.0);
+    let at = |millis: i64| UNIX_EPOCH + Duration::from_millis(millis as u64);
+
+    assert!(candles.close_due(at(11 * MINUTE + GRACE - 1)).is_empty());
+    let closed = candles.close_due(at(11 * MINUTE + GRACE));
+    assert_eq!(closed.len(), 1);
+
+    // The empty interval after it is closed too once its grace window passes.
+    candles.close_due(at(12 * MINUTE + GRACE));
+    assert!(candles
+        .record("ethusdt", 11 * MINUTE + 30_000, 101.0, 0.0)
+        .is_empty());
+    assert_eq!(candles.late_ticks(), 1);
+}
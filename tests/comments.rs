@@ -0,0 +1,153 @@
+use polymarket_rtds::{Comment, CommentNode, CommentStore, Reaction};
+use serde_json::json;
+
+fn comment(id: &str, parent: Option<&str>, created_at: &str) -> Comment {
+    serde_json::from_value(json!({
+        "id": id,
+        "body": format!("comment {}", id),
+        "parentEntityType": "Event",
+        "parentEntityID": 100,
+        "parentCommentID": parent,
+        "userAddress": "0xuser",
+        "replyAddress": null,
+        "createdAt": created_at,
+        "updatedAt": created_at,
+    }))
+    .unwrap()
+}
+
+fn reaction(id: &str, comment_id: i64) -> Reaction {
+    serde_json::from_value(json!({
+        "id": id,
+        "commentID": comment_id,
+        "reactionType": "HEART",
+        "icon": "heart",
+        "userAddress": "0xuser",
+        "createdAt": "2025-01-01T00:00:00Z",
+    }))
+    .unwrap()
+}
+
+fn ids(nodes: &[CommentNode]) -> Vec<&str> {
+    nodes.iter().map(|node| node.comment.id.as_str()).collect()
+}
+
+#[test]
+fn builds_threads_oldest_first() {
+    let mut store = CommentStore::new();
+    store.add_comment(comment("1", None, "2025-01-01T00:00:02Z"));
+    store.add_comment(comment("2", None, "2025-01-01T00:00:01Z"));
+    store.add_comment(comment("3", Some("1"), "2025-01-01T00:00:04Z"));
+    store.add_comment(comment("4", Some("1"), "2025-01-01T00:00:03Z"));
+    // The parent of this reply has not been seen, so it starts its own thread.
+    store.add_comment(comment("5", Some("9"), "2025-01-01T00:00:05Z"));
+
+    let threads = store.thread("Event", 100);
+    assert_eq!(ids(&threads), vec!["2", "1", "5"]);
+    assert_eq!(ids(&threads[1].replies), vec!["4", "3"]);
+}
+
+#[test]
+fn keeps_removed_parents_only_while_they_have_replies() {
+    let mut store = CommentStore::new();
+    store.add_comment(comment("1", None, "2025-01-01T00:00:01Z"));
+    store.add_comment(comment("2", Some("1"), "2025-01-01T00:00:02Z"));
+    store.add_comment(comment("3", Some("2"), "2025-01-01T00:00:03Z"));
+
+    assert!(store.remove_comment("1"));
+    assert!(store.remove_comment("2"));
+    let threads = store.thread("Event", 100);
+    assert!(threads[0].removed && threads[0].replies[0].removed);
+
+    // Removing the last reply drops the whole chain of placeholders.
+    assert!(store.remove_comment("3"));
+    assert!(store.thread("Event", 100).is_empty());
+    assert!(store.comment("1").is_none());
+}
+
+#[test]
+fn survives_self_parented_comments() {
+    let mut store = CommentStore::new();
+    store.add_comment(comment("1", Some("1"), "2025-01-01T00:00:01Z"));
+
+    let node = store.subtree("1").unwrap();
+    assert!(node.replies.is_empty());
+    assert_eq!(ids(&store.thread("Event", 100)), vec!["1"]);
+    assert!(store.remove_comment("1"));
+}
+
+#[test]
+fn survives_mutual_parents() {
+    let mut store = CommentStore::new();
+    store.add_comment(comment("1", Some("2"), "2025-01-01T00:00:01Z"));
+    store.add_comment(comment("2", Some("1"), "2025-01-01T00:00:02Z"));
+    store.add_comment(comment("3", Some("2"), "2025-01-01T00:00:03Z"));
+
+    let node = store.subtree("1").unwrap();
+    assert_eq!(ids(&node.replies), vec!["2"]);
+    assert_eq!(ids(&node.replies[0].replies), vec!["3"]);
+
+    // The loop is shown once, starting from its smallest ID.
+    let threads = store.thread("Event", 100);
+    assert_eq!(ids(&threads), vec!["1"]);
+}
+
+#[test]
+fn stops_at_the_depth_limit() {
+    let mut store = CommentStore::new();
+    store.add_comment(comment("0", None, "2025-01-01T00:00:00Z"));
+    for id in 1..1000 {
+        let parent = (id - 1).to_string();
+        store.add_comment(comment(
+            &id.to_string(),
+            Some(&parent),
+            "2025-01-01T00:00:00Z",
+        ));
+    }
+
+    let mut depth = 0;
+    let mut node = &store.thread("Event", 100)[0];
+    while let Some(reply) = node.replies.first() {
+        node = reply;
+        depth += 1;
+    }
+    assert_eq!(depth, 256);
+}
+
+#[test]
+fn counts_reactions_and_ranks_comments() {
+    let mut store = CommentStore::new();
+    store.add_comment(comment("1", None, "2025-01-01T00:00:01Z"));
+    store.add_comment(comment("2", None, "2025-01-01T00:00:02Z"));
+    assert!(store.add_reaction(&reaction("r1", 2)));
+    assert!(!store.add_reaction(&reaction("r1", 2)));
+    assert!(store.add_reaction(&reaction("r2", 2)));
+    assert!(store.add_reaction(&reaction("r3", 1)));
+
+    let top = store.top_comments("Event", 100, Some("HEART"), 1);
+    assert_eq!(top[0].0.id, "2");
+    assert_eq!(top[0].1, 2);
+
+    assert!(store.remove_reaction(&reaction("r2", 2)));
+    assert!(!store.remove_reaction(&reaction("r2", 2)));
+    assert_eq!(store.reactions("2")["HEART"], 1);
+}
+
+#[test]
+fn bounds_reactions_to_unseen_comments() {
+    let mut store = CommentStore::new().with_orphan_reaction_limit(2);
+    assert!(store.add_reaction(&reaction("r1", 7)));
+    assert!(store.add_reaction(&reaction("r2", 8)));
+    assert!(!store.add_reaction(&reaction("r3", 9)));
+    assert_eq!(store.orphan_reactions(), 2);
+
+    // Once the comment arrives its reactions no longer count towards the limit.
+    store.add_comment(comment("7", None, "2025-01-01T00:00:01Z"));
+    assert_eq!(store.orphan_reactions(), 1);
+    assert!(store.add_reaction(&reaction("r3", 9)));
+
+    assert_eq!(store.prune_orphan_reactions(), 2);
+    assert_eq!(store.orphan_reactions(), 0);
+    assert!(store.reactions("8").is_empty());
+    assert_eq!(store.reactions("7")["HEART"], 1);
+}
@@ -0,0 +1,167 @@
+use futures_util::StreamExt;
+use polymarket_rtds::{
+    ClobApiKeyCreds, ClobUserSubscription, RealTimeDataClient, RotatingCredentials, Secret,
+};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// Writes `contents` to a file named `name` in a fresh temporary directory.
+fn write_file(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "polymarket-rtds-credentials-{}-{}",
+        std::process::id(),
+        name
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn loads_credentials_from_the_environment() {
+    std::env::set_var("CLOB_API_KEY", "env-key");
+    std::env::set_var("CLOB_API_SECRET", "env-secret");
+    std::env::set_var("CLOB_PASSPHRASE", "env-passphrase");
+    let creds = ClobApiKeyCreds::from_env().unwrap();
+    assert_eq!(
+        creds,
+        ClobApiKeyCreds::new("env-key", "env-secret", "env-passphrase")
+    );
+
+    std::env::remove_var("CLOB_PASSPHRASE");
+    let error = ClobApiKeyCreds::from_env().unwrap_err();
+    assert!(error.contains("CLOB_PASSPHRASE"), "{}", error);
+}
+
+#[test]
+fn loads_credentials_from_json_and_toml_files() {
+    let expected = ClobApiKeyCreds::new("file-key", "file-secret", "file-passphrase");
+
+    let json = write_file(
+        "creds.json",
+        r#"{"key":"file-key","secret":"file-secret","passphrase":"file-passphrase"}"#,
+    );
+    assert_eq!(ClobApiKeyCreds::from_file(json).unwrap(), expected);
+
+    let toml = write_file(
+        "creds.toml",
+        "key = \"file-key\"\nsecret = \"file-secret\"\npassphrase = \"file-passphrase\"\n",
+    );
+    assert_eq!(ClobApiKeyCreds::from_file(toml).unwrap(), expected);
+
+    let yaml = write_file("creds.yaml", "key: file-key\n");
+    assert!(ClobApiKeyCreds::from_file(yaml).is_err());
+}
+
+#[test]
+fn file_errors_do_not_quote_secrets() {
+    let toml = write_file(
+        "broken.toml",
+        "key = \"file-key\"\nsecret = \"hunter2\" \"oops\"\n",
+    );
+    let error = ClobApiKeyCreds::from_file(toml).unwrap_err();
+    assert!(!error.contains("hunter2"), "{}", error);
+}
+
+#[test]
+fn redacts_secrets_in_debug_output() {
+    let creds = ClobApiKeyCreds::new("key", "hunter2", "swordfish");
+    let debug = format!("{:?}", creds);
+    assert!(debug.contains("key"), "{}", debug);
+    assert!(
+        !debug.contains("hunter2") && !debug.contains("swordfish"),
+        "{}",
+        debug
+    );
+    assert_eq!(Secret::new("hunter2").to_string(), "[REDACTED]");
+
+    // The secrets still reach the server in subscription frames.
+    let frame = serde_json::to_string(&creds).unwrap();
+    assert!(frame.contains("hunter2"), "{}", frame);
+}
+
+/// WebSocket stand-in that forwards the action and API key of every frame.
+async fn spawn_server() -> (String, mpsc::UnboundedReceiver<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        while let Some(Ok(msg)) = ws.next().await {
+            if let WsMessage::Text(text) = msg {
+                if let Ok(frame) = serde_json::from_str::<Value>(&text) {
+                    let key = frame["subscriptions"][0]["clob_auth"]["key"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string();
+                    let action = frame["action"].as_str().unwrap().to_string();
+                    let _ = frame_tx.send((action, key));
+                }
+            }
+        }
+    });
+    (format!("ws://{}", addr), frame_rx)
+}
+
+async fn next_frame(frames: &mut mpsc::UnboundedReceiver<(String, String)>) -> (String, String) {
+    tokio::time::timeout(Duration::from_secs(5), frames.recv())
+        .await
+        .expect("timed out waiting for a frame")
+        .unwrap()
+}
+
+fn frame(action: &str, key: &str) -> (String, String) {
+    (action.to_string(), key.to_string())
+}
+
+#[tokio::test]
+async fn reauthenticates_without_a_gap() {
+    let (host, mut frames) = spawn_server().await;
+    let credentials = Arc::new(RotatingCredentials::new(Some(ClobApiKeyCreds::new(
+        "old-key",
+        "secret",
+        "passphrase",
+    ))));
+    let mut client = RealTimeDataClient::new()
+        .with_host(host)
+        .with_credential_provider(credentials.clone());
+    client.connect().await.unwrap();
+
+    client
+        .subscribe(vec![ClobUserSubscription::all(None).build()])
+        .await
+        .unwrap();
+    assert_eq!(next_frame(&mut frames).await, frame("subscribe", "old-key"));
+
+    credentials.rotate(ClobApiKeyCreds::new("new-key", "secret", "passphrase"));
+    // The new subscription is in place before the old one is removed.
+    assert_eq!(next_frame(&mut frames).await, frame("subscribe", "new-key"));
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame("unsubscribe", "old-key")
+    );
+
+    // Re-authenticating with unchanged credentials sends nothing.
+    client.reauthenticate().await.unwrap();
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), frames.recv())
+            .await
+            .is_err()
+    );
+}
+
+#[test]
+fn clob_user_subscriptions_need_credentials_without_a_provider() {
+    let subscription = ClobUserSubscription::orders(None).build();
+    assert!(subscription.validate().is_err());
+    let subscription =
+        ClobUserSubscription::orders(ClobApiKeyCreds::new("key", "secret", "passphrase")).build();
+    assert!(subscription.validate().is_ok());
+}
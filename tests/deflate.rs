@@ -0,0 +1,237 @@
+use flate2::{Compress, Compression, FlushCompress};
+use polymarket_rtds::{RealTimeDataClient, Topic};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+
+const MESSAGE: &str = r#"{"topic":"crypto_prices","type":"update","timestamp":1,"payload":{"symbol":"btcusdt","timestamp":1,"value":1.0},"connection_id":"test"}"#;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_PING: u8 = 0x9;
+
+/// WebSocket stand-in that accepts the handshake with the given
+/// `Sec-WebSocket-Extensions` response and then writes `frames` verbatim.
+async fn spawn_server(extension: &'static str, frames: Vec<Vec<u8>>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await.unwrap());
+        }
+        let head = String::from_utf8(head).unwrap();
+        assert!(head.contains("permessage-deflate"));
+        let key = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("sec-websocket-key")
+                    .then_some(value)
+            })
+            .unwrap();
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\nSec-WebSocket-Extensions: {}\r\n\r\n",
+            derive_accept_key(key.trim().as_bytes()),
+            extension
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        for frame in frames {
+            stream.write_all(&frame).await.unwrap();
+        }
+        // Keep the socket open until the client goes away.
+        let mut sink = Vec::new();
+        let _ = stream.read_to_end(&mut sink).await;
+    });
+    addr
+}
+
+/// Builds an unmasked server frame.
+fn frame(fin: bool, rsv1: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![(u8::from(fin) << 7) | (u8::from(rsv1) << 6) | opcode];
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Compresses a message the way a permessage-deflate sender does, with a sync flush
+/// and the trailing `00 00 ff ff` removed.
+fn compress(compressor: &mut Compress, data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 64);
+    let start = compressor.total_in();
+    loop {
+        let consumed = (compressor.total_in() - start) as usize;
+        if out.capacity() - out.len() < 64 {
+            out.reserve(out.capacity());
+        }
+        compressor
+            .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+            .unwrap();
+        if (compressor.total_in() - start) as usize == data.len() && out.len() < out.capacity() {
+            break;
+        }
+    }
+    assert!(out.ends_with(&[0x00, 0x00, 0xff, 0xff]));
+    out.truncate(out.len() - 4);
+    out
+}
+
+/// Compresses a message into a single final DEFLATE block, without relying on
+/// earlier messages.
+fn compress_final(data: &[u8]) -> Vec<u8> {
+    let mut compressor = Compress::new(Compression::default(), false);
+    let mut out = Vec::with_capacity(data.len() + 64);
+    compressor
+        .compress_vec(data, &mut out, FlushCompress::Finish)
+        .unwrap();
+    out
+}
+
+fn client(addr: SocketAddr) -> RealTimeDataClient {
+    RealTimeDataClient::new()
+        .with_host(format!("ws://{}", addr))
+        .with_compression(true)
+        .with_reconnect(None)
+}
+
+async fn recv(client: &mut RealTimeDataClient) -> Option<Result<polymarket_rtds::Message, String>> {
+    tokio::time::timeout(Duration::from_secs(30), client.recv())
+        .await
+        .expect("timed out waiting for a message")
+}
+
+#[tokio::test]
+async fn inflates_single_frame_messages() {
+    let mut compressor = Compress::new(Compression::default(), false);
+    // The second message is compressed against the first, as context takeover allows.
+    let frames = vec![
+        frame(
+            true,
+            true,
+            OPCODE_TEXT,
+            &compress(&mut compressor, MESSAGE.as_bytes()),
+        ),
+        frame(
+            true,
+            true,
+            OPCODE_TEXT,
+            &compress(&mut compressor, MESSAGE.as_bytes()),
+        ),
+    ];
+    let addr = spawn_server("permessage-deflate", frames).await;
+
+    let mut client = client(addr);
+    client.connect().await.unwrap();
+    for _ in 0..2 {
+        let message = recv(&mut client).await.unwrap().unwrap();
+        assert_eq!(message.topic, Topic::CryptoPrices);
+    }
+
+    let metrics = client.metrics();
+    assert!(metrics.compression_negotiated);
+    assert_eq!(metrics.decompressed_bytes, 2 * MESSAGE.len() as u64);
+    assert!(metrics.compressed_bytes > 0);
+    assert!(metrics.compression_ratio().unwrap() > 1.0);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn inflates_fragmented_messages() {
+    let mut compressor = Compress::new(Compression::default(), false);
+    let compressed = compress(&mut compressor, MESSAGE.as_bytes());
+    let (first, rest) = compressed.split_at(compressed.len() / 3);
+    let (second, third) = rest.split_at(rest.len() / 2);
+    let frames = vec![
+        frame(false, true, OPCODE_TEXT, first),
+        frame(false, false, OPCODE_CONTINUATION, second),
+        frame(true, false, OPCODE_CONTINUATION, third),
+    ];
+    let addr = spawn_server("permessage-deflate", frames).await;
+
+    let mut client = client(addr);
+    client.connect().await.unwrap();
+    let message = recv(&mut client).await.unwrap().unwrap();
+    assert_eq!(message.topic, Topic::CryptoPrices);
+    assert_eq!(client.metrics().decompressed_bytes, MESSAGE.len() as u64);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn passes_control_frames_between_fragments() {
+    let mut compressor = Compress::new(Compression::default(), false);
+    let compressed = compress(&mut compressor, MESSAGE.as_bytes());
+    let (first, second) = compressed.split_at(compressed.len() / 2);
+    let frames = vec![
+        frame(false, true, OPCODE_TEXT, first),
+        frame(true, false, OPCODE_PING, b"keepalive"),
+        frame(true, false, OPCODE_CONTINUATION, second),
+    ];
+    let addr = spawn_server("permessage-deflate", frames).await;
+
+    let mut client = client(addr);
+    client.connect().await.unwrap();
+    let message = recv(&mut client).await.unwrap().unwrap();
+    assert_eq!(message.topic, Topic::CryptoPrices);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn resets_context_with_server_no_context_takeover() {
+    // Each message ends with a final block, which only decodes if the inflater is
+    // reset between messages.
+    let frames = vec![
+        frame(true, true, OPCODE_TEXT, &compress_final(MESSAGE.as_bytes())),
+        frame(true, true, OPCODE_TEXT, &compress_final(MESSAGE.as_bytes())),
+    ];
+    let addr = spawn_server(
+        "permessage-deflate; server_no_context_takeover; client_max_window_bits=15",
+        frames,
+    )
+    .await;
+
+    let mut client = client(addr);
+    client.connect().await.unwrap();
+    for _ in 0..2 {
+        let message = recv(&mut client).await.unwrap().unwrap();
+        assert_eq!(message.topic, Topic::CryptoPrices);
+    }
+    assert_eq!(
+        client.metrics().decompressed_bytes,
+        2 * MESSAGE.len() as u64
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn drops_connection_on_messages_over_the_size_limit() {
+    // A sync-flushed chunk from a fresh compressor does not reference earlier data,
+    // so repeating it yields a valid stream that inflates to 65 MiB.
+    let chunk = compress(
+        &mut Compress::new(Compression::fast(), false),
+        &vec![b' '; 1 << 20],
+    );
+    let mut compressed = Vec::with_capacity(chunk.len() * 65);
+    for _ in 0..65 {
+        compressed.extend_from_slice(&chunk);
+    }
+    let frames = vec![frame(true, true, OPCODE_TEXT, &compressed)];
+    let addr = spawn_server("permessage-deflate", frames).await;
+
+    let mut client = client(addr);
+    client.connect().await.unwrap();
+    assert!(recv(&mut client).await.is_none());
+    assert_eq!(client.metrics().decompressed_bytes, 0);
+}
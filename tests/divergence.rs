@@ -0,0 +1,187 @@
+use polymarket_rtds::divergence::normalize_symbol;
+use polymarket_rtds::{
+    DivergenceConfig, DivergenceEvent, DivergenceMonitor, Message, SpreadSample,
+};
+use serde_json::json;
+use std::time::Duration;
+
+const EXCHANGE: bool = false;
+const CHAINLINK: bool = true;
+
+fn monitor() -> DivergenceMonitor {
+    DivergenceMonitor::new(DivergenceConfig {
+        threshold: 0.01,
+        max_skew: Duration::from_secs(1),
+    })
+}
+
+fn spread(events: &[DivergenceEvent]) -> &SpreadSample {
+    match events.first() {
+        Some(DivergenceEvent::Spread(sample)) => sample,
+        other => panic!("expected a spread sample, got {:?}", other),
+    }
+}
+
+fn price(topic: &str, symbol: &str, timestamp: i64, value: f64) -> Message {
+    serde_json::from_value(json!({
+        "topic": topic,
+        "type": "update",
+        "timestamp": 1,
+        "payload": { "symbol": symbol, "timestamp": timestamp, "value": value },
+        "connection_id": "test",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn normalizes_symbols_with_their_quote_currency() {
+    assert_eq!(normalize_symbol("BTCUSDT"), "btc/usdt");
+    assert_eq!(normalize_symbol("btcusdc"), "btc/usdc");
+    assert_eq!(normalize_symbol("BTC/USD"), "btc/usd");
+    assert_eq!(normalize_symbol("eth-usdt"), "eth/usdt");
+    assert_eq!(normalize_symbol("BTC"), "btc");
+    // A bare quote currency is not split into an empty base.
+    assert_eq!(normalize_symbol("usdt"), "usdt");
+}
+
+#[test]
+fn pairs_ticks_within_the_allowed_skew() {
+    let mut monitor = monitor();
+    assert!(monitor
+        .record(CHAINLINK, "btc/usd", 10_000, 100.0)
+        .is_empty());
+    // Too far from the Chainlink tick to be compared.
+    assert!(monitor
+        .record(EXCHANGE, "btcusdt", 11_500, 100.5)
+        .is_empty());
+
+    let events = monitor.record(EXCHANGE, "btcusdt", 10_400, 100.5);
+    let sample = spread(&events);
+    assert_eq!(sample.symbol, "btc/usdt");
+    assert_eq!(sample.chainlink_timestamp, 10_000);
+    assert_eq!(sample.exchange_timestamp, 10_400);
+    assert_eq!(sample.spread, 0.5);
+    assert_eq!(sample.relative_spread, 0.005);
+    assert_eq!(events.len(), 1);
+}
+
+#[test]
+fn raises_events_when_the_spread_crosses_the_threshold() {
+    let mut monitor = monitor();
+    monitor.record(CHAINLINK, "btc/usd", 10_000, 100.0);
+
+    let events = monitor.record(EXCHANGE, "btcusdt", 10_000, 102.0);
+    assert!(matches!(
+        events[..],
+        [
+            DivergenceEvent::Spread(_),
+            DivergenceEvent::DivergenceStarted(_)
+        ]
+    ));
+    assert!(monitor.is_diverged("BTCUSDT"));
+    assert!(monitor.is_diverged("btc"));
+
+    // Still diverged, so only the sample is reported.
+    assert_eq!(monitor.record(EXCHANGE, "btcusdt", 10_100, 98.0).len(), 1);
+
+    let events = monitor.record(CHAINLINK, "btc/usd", 10_200, 98.5);
+    assert!(matches!(
+        events[..],
+        [
+            DivergenceEvent::Spread(_),
+            DivergenceEvent::DivergenceEnded(_)
+        ]
+    ));
+    assert!(!monitor.is_diverged("btcusdt"));
+}
+
+#[test]
+fn tracks_each_quote_currency_separately() {
+    let mut monitor = monitor();
+    monitor.record(EXCHANGE, "btcusdt", 10_000, 100.0);
+    monitor.record(EXCHANGE, "btcusdc", 10_000, 103.0);
+
+    // One Chainlink tick is compared with both markets.
+    let events = monitor.record(CHAINLINK, "btc/usd", 10_000, 100.0);
+    let samples: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            DivergenceEvent::Spread(sample) => Some((sample.symbol.as_str(), sample.spread)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(samples, vec![("btc/usdc", 3.0), ("btc/usdt", 0.0)]);
+
+    assert!(monitor.is_diverged("btcusdc"));
+    assert!(!monitor.is_diverged("btcusdt"));
+}
+
+#[test]
+fn only_monitors_the_configured_symbols() {
+    let mut monitor = monitor().with_symbol("eth").with_symbol("btcusdc");
+    monitor.record(CHAINLINK, "btc/usd", 10_000, 100.0);
+    monitor.record(CHAINLINK, "sol/usd", 10_000, 100.0);
+    assert!(monitor
+        .record(EXCHANGE, "btcusdt", 10_000, 100.0)
+        .is_empty());
+    assert!(monitor
+        .record(EXCHANGE, "solusdt", 10_000, 100.0)
+        .is_empty());
+    assert_eq!(monitor.record(EXCHANGE, "btcusdc", 10_000, 100.0).len(), 1);
+
+    let symbols: Vec<_> = monitor
+        .subscriptions()
+        .into_iter()
+        .map(|subscription| {
+            (
+                subscription.topic.to_string(),
+                subscription.filters.unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        symbols,
+        vec![
+            (
+                "crypto_prices".to_string(),
+                r#"{"symbol":"btcusdc"}"#.to_string()
+            ),
+            (
+                "crypto_prices_chainlink".to_string(),
+                r#"{"symbol":"btc/usd"}"#.to_string()
+            ),
+            (
+                "crypto_prices".to_string(),
+                r#"{"symbol":"ethusdt"}"#.to_string()
+            ),
+            (
+                "crypto_prices_chainlink".to_string(),
+                r#"{"symbol":"eth/usd"}"#.to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn applies_price_messages() {
+    let mut monitor = monitor();
+    assert!(monitor
+        .apply(&price(
+            "crypto_prices_chainlink",
+            "eth/usd",
+            10_000,
+            2_000.0
+        ))
+        .unwrap()
+        .is_empty());
+    let events = monitor
+        .apply(&price("crypto_prices", "ethusdt", 10_000, 2_010.0))
+        .unwrap();
+    assert_eq!(spread(&events).symbol, "eth/usdt");
+
+    // Equity prices are not compared.
+    assert!(monitor
+        .apply(&price("equity_prices", "aapl", 10_000, 200.0))
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,180 @@
+use futures_util::{SinkExt, StreamExt};
+use polymarket_rtds::{
+    CompositeFilter, FilterField, MessageType, RealTimeDataClient, Subscription,
+    SubscriptionFilter, Topic,
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+fn trade(id: u64, slug: &str, event_slug: &str) -> Value {
+    json!({
+        "topic": "activity",
+        "type": "trades",
+        "timestamp": id,
+        "payload": { "id": id, "slug": slug, "eventSlug": event_slug },
+        "connection_id": "test",
+    })
+}
+
+/// WebSocket stand-in that waits for `frames` subscribe frames, then sends `messages`.
+async fn spawn_server(frames: usize, messages: Vec<Value>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let mut received = 0;
+        while received < frames {
+            match ws.next().await {
+                Some(Ok(WsMessage::Text(text))) if text.contains("\"subscribe\"") => received += 1,
+                Some(Ok(_)) => {}
+                _ => return,
+            }
+        }
+        for message in messages {
+            ws.send(WsMessage::Text(message.to_string().into()))
+                .await
+                .unwrap();
+        }
+        while let Some(Ok(_)) = ws.next().await {}
+    });
+    format!("ws://{}", addr)
+}
+
+async fn received_ids(client: &mut RealTimeDataClient, count: usize) -> Vec<u64> {
+    let mut ids = Vec::new();
+    for _ in 0..count {
+        let message = tokio::time::timeout(Duration::from_secs(5), client.recv())
+            .await
+            .expect("timed out waiting for a message")
+            .unwrap()
+            .unwrap();
+        ids.push(message.payload["id"].as_u64().unwrap());
+    }
+    ids
+}
+
+#[test]
+fn expands_the_server_field_into_one_subscription_per_value() {
+    let filter = CompositeFilter::new()
+        .event_slugs(["election"])
+        .market_slugs(["senate", "house"]);
+    assert_eq!(
+        filter.fields(),
+        vec![FilterField::EventSlug, FilterField::MarketSlug]
+    );
+
+    let subscriptions = filter
+        .subscriptions(Topic::Activity, MessageType::Trades)
+        .unwrap();
+    let filters: Vec<_> = subscriptions
+        .iter()
+        .map(|s| s.filters.clone().unwrap())
+        .collect();
+    assert_eq!(
+        filters,
+        vec![r#"{"market_slug":"senate"}"#, r#"{"market_slug":"house"}"#]
+    );
+}
+
+#[test]
+fn sends_token_ids_as_one_list() {
+    let subscriptions = CompositeFilter::new()
+        .token_ids(["1", "2"])
+        .subscriptions(Topic::ClobMarket, MessageType::AggOrderbook)
+        .unwrap();
+    assert_eq!(subscriptions.len(), 1);
+    assert_eq!(subscriptions[0].filters.as_deref(), Some(r#"["1","2"]"#));
+}
+
+#[test]
+fn rejects_fields_the_topic_does_not_carry_and_empty_lists() {
+    let error = CompositeFilter::new()
+        .symbols(["btcusdt"])
+        .subscriptions(Topic::Activity, MessageType::Trades)
+        .unwrap_err();
+    assert!(error.contains("symbol"), "{}", error);
+
+    let error = CompositeFilter::new()
+        .event_slugs(Vec::<String>::new())
+        .subscriptions(Topic::Activity, MessageType::Trades)
+        .unwrap_err();
+    assert!(error.contains("no values"), "{}", error);
+}
+
+#[test]
+fn matches_every_clause() {
+    let filter = CompositeFilter::new()
+        .event_slugs(["election"])
+        .market_slugs(["senate"]);
+    let payload = |slug: &str, event_slug: &str| json!({ "slug": slug, "eventSlug": event_slug });
+    assert!(filter.matches(&payload("senate", "election")));
+    assert!(!filter.matches(&payload("senate", "sports")));
+    assert!(!filter.matches(&payload("house", "election")));
+    assert!(!filter.matches(&json!({ "slug": "senate" })));
+}
+
+#[tokio::test]
+async fn applies_the_local_part_of_a_composite_filter() {
+    let host = spawn_server(
+        1,
+        vec![
+            trade(1, "senate", "election"),
+            trade(2, "senate", "sports"),
+            trade(3, "senate", "election"),
+        ],
+    )
+    .await;
+    let mut client = RealTimeDataClient::new().with_host(host);
+    client.connect().await.unwrap();
+
+    let filter = CompositeFilter::new()
+        .event_slugs(["election"])
+        .market_slugs(["senate"]);
+    let subscriptions = filter
+        .subscriptions(Topic::Activity, MessageType::Trades)
+        .unwrap();
+    client
+        .subscribe_filtered(subscriptions, &filter)
+        .await
+        .unwrap();
+
+    assert_eq!(received_ids(&mut client, 2).await, vec![1, 3]);
+}
+
+#[tokio::test]
+async fn a_plain_subscription_on_the_same_topic_keeps_the_composite_filter() {
+    let host = spawn_server(
+        2,
+        vec![
+            trade(1, "senate", "election"),
+            // Only matches the composite subscription's server filter.
+            trade(2, "senate", "sports"),
+            // Matches the plain subscription.
+            trade(3, "nba-finals", "nba"),
+            trade(4, "senate", "election"),
+        ],
+    )
+    .await;
+    let mut client = RealTimeDataClient::new().with_host(host);
+    client.connect().await.unwrap();
+
+    let filter = CompositeFilter::new()
+        .event_slugs(["election"])
+        .market_slugs(["senate"]);
+    let subscriptions = filter
+        .subscriptions(Topic::Activity, MessageType::Trades)
+        .unwrap();
+    client
+        .subscribe_filtered(subscriptions, &filter)
+        .await
+        .unwrap();
+    let plain = Subscription::new(Topic::Activity, MessageType::Trades)
+        .with_filter(SubscriptionFilter::event_slug("nba"))
+        .unwrap();
+    client.subscribe(vec![plain]).await.unwrap();
+
+    assert_eq!(received_ids(&mut client, 3).await, vec![1, 3, 4]);
+}
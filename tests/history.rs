@@ -0,0 +1,166 @@
+use polymarket_rtds::{Message, PriceDataPoint, PriceHistory};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn message(topic: &str, message_type: &str, payload: Value) -> Message {
+    serde_json::from_value(json!({
+        "topic": topic,
+        "type": message_type,
+        "timestamp": 1,
+        "payload": payload,
+        "connection_id": "test",
+    }))
+    .unwrap()
+}
+
+fn live(topic: &str, symbol: &str, timestamp: i64, value: f64) -> Message {
+    message(
+        topic,
+        "update",
+        json!({ "symbol": symbol, "timestamp": timestamp, "value": value }),
+    )
+}
+
+fn historical(topic: &str, symbol: &str, points: &[(i64, f64)]) -> Message {
+    let data: Vec<_> = points
+        .iter()
+        .map(|&(timestamp, value)| json!({ "timestamp": timestamp, "value": value }))
+        .collect();
+    message(topic, "update", json!({ "symbol": symbol, "data": data }))
+}
+
+fn points(points: &[PriceDataPoint]) -> Vec<(i64, f64)> {
+    points
+        .iter()
+        .map(|point| (point.timestamp, point.value))
+        .collect()
+}
+
+#[test]
+fn merges_the_backfill_with_live_updates() {
+    let mut history = PriceHistory::new();
+    let backfill = historical("crypto_prices", "btcusdt", &[(1_000, 1.0), (2_000, 2.0)]);
+    assert_eq!(history.apply(&backfill).unwrap(), 2);
+    // The live feed overlaps the last historical point.
+    assert_eq!(
+        history
+            .apply(&live("crypto_prices", "btcusdt", 2_000, 2.0))
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        history
+            .apply(&live("crypto_prices", "btcusdt", 3_000, 3.0))
+            .unwrap(),
+        1
+    );
+    // Replaying the backfill after a reconnect adds nothing.
+    assert_eq!(history.apply(&backfill).unwrap(), 0);
+
+    assert_eq!(history.len("btcusdt"), 3);
+    assert_eq!(history.latest("btcusdt").unwrap().value, 3.0);
+}
+
+#[test]
+fn keeps_symbols_and_topics_apart() {
+    let mut history = PriceHistory::new();
+    history
+        .apply(&live("crypto_prices", "btcusdt", 1_000, 1.0))
+        .unwrap();
+    history
+        .apply(&live("crypto_prices_chainlink", "btc/usd", 1_000, 2.0))
+        .unwrap();
+    history
+        .apply(&historical("equity_prices", "aapl", &[(1_000, 3.0)]))
+        .unwrap();
+
+    let mut symbols: Vec<_> = history.symbols().collect();
+    symbols.sort();
+    assert_eq!(symbols, vec!["aapl", "btc/usd", "btcusdt"]);
+    assert_eq!(history.value_at("btc/usd", 1_000), Some(2.0));
+}
+
+#[test]
+fn ignores_other_messages_and_reports_bad_payloads() {
+    let mut history = PriceHistory::new();
+    let trade = message("activity", "trades", json!({ "symbol": "btcusdt" }));
+    assert_eq!(history.apply(&trade).unwrap(), 0);
+    assert!(history
+        .apply(&message("crypto_prices", "update", json!({ "symbol": 1 })))
+        .is_err());
+    assert_eq!(history.symbols().count(), 0);
+}
+
+#[test]
+fn replaces_the_value_at_an_existing_timestamp() {
+    let mut history = PriceHistory::new();
+    assert!(history.insert("btcusdt", 1_000, 1.0));
+    assert!(!history.insert("btcusdt", 1_000, 1.5));
+    assert_eq!(history.len("btcusdt"), 1);
+    assert_eq!(history.value_at("btcusdt", 1_000), Some(1.5));
+}
+
+#[test]
+fn drops_points_outside_the_retention_window() {
+    let mut history = PriceHistory::new().with_retention(Duration::from_secs(2));
+    history.seed(
+        "btcusdt",
+        &[
+            PriceDataPoint {
+                timestamp: 1_000,
+                value: 1.0,
+            },
+            PriceDataPoint {
+                timestamp: 2_000,
+                value: 2.0,
+            },
+        ],
+    );
+    history.insert("btcusdt", 4_000, 4.0);
+    assert_eq!(
+        points(&history.range("btcusdt", 0, 10_000)),
+        vec![(2_000, 2.0), (4_000, 4.0)]
+    );
+
+    // A late point older than the window is dropped straight away.
+    history.insert("btcusdt", 1_500, 1.5);
+    assert_eq!(history.len("btcusdt"), 2);
+}
+
+#[test]
+fn looks_up_values_and_ranges() {
+    let mut history = PriceHistory::new();
+    for (timestamp, value) in [(1_000, 1.0), (2_000, 2.0), (3_000, 3.0)] {
+        history.insert("btcusdt", timestamp, value);
+    }
+
+    assert_eq!(history.value_at("btcusdt", 999), None);
+    assert_eq!(history.value_at("btcusdt", 2_500), Some(2.0));
+    assert_eq!(history.value_at("ethusdt", 2_500), None);
+
+    assert_eq!(
+        points(&history.range("btcusdt", 2_000, 3_000)),
+        vec![(2_000, 2.0), (3_000, 3.0)]
+    );
+    assert!(history.range("btcusdt", 3_000, 2_000).is_empty());
+}
+
+#[test]
+fn resamples_to_aligned_intervals() {
+    let mut history = PriceHistory::new();
+    history.insert("btcusdt", 1_500, 1.0);
+    history.insert("btcusdt", 2_200, 2.0);
+    history.insert("btcusdt", 2_700, 2.5);
+
+    let resampled = history.resample("btcusdt", 0, 4_000, Duration::from_secs(1));
+    // Nothing is known before the first tick, each interval takes its last value,
+    // and the empty intervals carry it forward.
+    assert_eq!(
+        points(&resampled),
+        vec![(1_000, 1.0), (2_000, 2.5), (3_000, 2.5), (4_000, 2.5)]
+    );
+
+    // A start between boundaries is aligned down to the interval.
+    let resampled = history.resample("btcusdt", 2_300, 2_999, Duration::from_secs(1));
+    assert_eq!(points(&resampled), vec![(2_000, 2.5)]);
+}
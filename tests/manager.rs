@@ -0,0 +1,165 @@
+use futures_util::StreamExt;
+use polymarket_rtds::{
+    ActivitySubscription, ClobUserSubscription, RealTimeDataClient, SubscriptionManager,
+};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// WebSocket stand-in that forwards the action and filters of every frame.
+async fn spawn_server() -> (String, mpsc::UnboundedReceiver<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        while let Some(Ok(msg)) = ws.next().await {
+            if let WsMessage::Text(text) = msg {
+                if let Ok(frame) = serde_json::from_str::<Value>(&text) {
+                    let filters = frame["subscriptions"][0]["filters"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string();
+                    let action = frame["action"].as_str().unwrap().to_string();
+                    let _ = frame_tx.send((action, filters));
+                }
+            }
+        }
+    });
+    (format!("ws://{}", addr), frame_rx)
+}
+
+async fn connect() -> (
+    RealTimeDataClient,
+    SubscriptionManager,
+    mpsc::UnboundedReceiver<(String, String)>,
+) {
+    let (host, frames) = spawn_server().await;
+    let mut client = RealTimeDataClient::new().with_host(host);
+    client.connect().await.unwrap();
+    let manager = client.subscription_manager().unwrap();
+    (client, manager, frames)
+}
+
+async fn next_frame(frames: &mut mpsc::UnboundedReceiver<(String, String)>) -> (String, String) {
+    tokio::time::timeout(Duration::from_secs(5), frames.recv())
+        .await
+        .expect("timed out waiting for a frame")
+        .unwrap()
+}
+
+async fn assert_no_frame(frames: &mut mpsc::UnboundedReceiver<(String, String)>) {
+    let frame = tokio::time::timeout(Duration::from_millis(200), frames.recv()).await;
+    assert!(frame.is_err(), "unexpected frame: {:?}", frame);
+}
+
+fn frame(action: &str, filters: &str) -> (String, String) {
+    (action.to_string(), filters.to_string())
+}
+
+const ELECTION: &str = r#"{"event_slug":"election"}"#;
+
+#[tokio::test]
+async fn sends_identical_subscriptions_once() {
+    let (_client, manager, mut frames) = connect().await;
+    let subscription = ActivitySubscription::trades()
+        .event_slug("election")
+        .build();
+
+    let _first = manager.subscribe(subscription.clone()).unwrap();
+    let _second = manager.subscribe(subscription.clone()).unwrap();
+    assert_eq!(next_frame(&mut frames).await, frame("subscribe", ELECTION));
+    assert_no_frame(&mut frames).await;
+    assert_eq!(manager.holders(&subscription), 2);
+    assert_eq!(manager.active().len(), 1);
+
+    // A different filter is a different stream.
+    let _other = manager
+        .subscribe(ActivitySubscription::trades().event_slug("nba").build())
+        .unwrap();
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame("subscribe", r#"{"event_slug":"nba"}"#)
+    );
+    assert_eq!(manager.active().len(), 2);
+}
+
+#[tokio::test]
+async fn cloning_a_handle_counts_as_a_holder() {
+    let (_client, manager, mut frames) = connect().await;
+    let subscription = ActivitySubscription::trades()
+        .event_slug("election")
+        .build();
+
+    let handle = manager.subscribe(subscription.clone()).unwrap();
+    assert_eq!(next_frame(&mut frames).await, frame("subscribe", ELECTION));
+    let clone = handle.clone();
+    assert_eq!(manager.holders(&subscription), 2);
+    assert_eq!(clone.subscription().filters.as_deref(), Some(ELECTION));
+
+    drop(handle);
+    assert_eq!(manager.holders(&subscription), 1);
+    assert_no_frame(&mut frames).await;
+
+    drop(clone);
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame("unsubscribe", ELECTION)
+    );
+}
+
+#[tokio::test]
+async fn an_early_drop_leaves_the_subscription_active() {
+    let (_client, manager, mut frames) = connect().await;
+    let subscription = ActivitySubscription::trades()
+        .event_slug("election")
+        .build();
+
+    let early = manager.subscribe(subscription.clone()).unwrap();
+    let late = manager.subscribe(subscription.clone()).unwrap();
+    assert_eq!(next_frame(&mut frames).await, frame("subscribe", ELECTION));
+
+    drop(early);
+    assert_no_frame(&mut frames).await;
+    assert_eq!(manager.holders(&subscription), 1);
+    assert_eq!(manager.active().len(), 1);
+
+    drop(late);
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame("unsubscribe", ELECTION)
+    );
+    assert_eq!(manager.holders(&subscription), 0);
+    assert!(manager.active().is_empty());
+
+    // Subscribing again after the last drop sends a new subscription.
+    let _again = manager.subscribe(subscription).unwrap();
+    assert_eq!(next_frame(&mut frames).await, frame("subscribe", ELECTION));
+}
+
+#[tokio::test]
+async fn rejects_invalid_subscriptions() {
+    let (_client, manager, mut frames) = connect().await;
+    let error = manager
+        .subscribe(ClobUserSubscription::all(None).build())
+        .unwrap_err();
+    assert!(
+        error.contains("requires clob_auth credentials"),
+        "{}",
+        error
+    );
+    assert!(manager.active().is_empty());
+    assert_no_frame(&mut frames).await;
+}
+
+#[test]
+fn needs_a_connection() {
+    let client = RealTimeDataClient::new();
+    assert_eq!(
+        client.subscription_manager().err().as_deref(),
+        Some("Socket not connected")
+    );
+}
@@ -0,0 +1,142 @@
+use polymarket_rtds::MetricsSnapshot;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn snapshot() -> MetricsSnapshot {
+    let mut snapshot = MetricsSnapshot {
+        connected: true,
+        frames_received: 12,
+        reconnects: 1,
+        rate_limit_delay: Duration::from_millis(1500),
+        ..Default::default()
+    };
+    snapshot
+        .messages_received
+        .insert(("crypto_prices".to_string(), "update".to_string()), 7);
+    snapshot
+        .messages_received
+        .insert(("activity".to_string(), "trades".to_string()), 3);
+    snapshot
+}
+
+/// Returns the sample lines of a Prometheus text body, skipping comments.
+fn samples(body: &str) -> Vec<&str> {
+    body.lines().filter(|line| !line.starts_with('#')).collect()
+}
+
+#[test]
+fn renders_every_metric_with_help_and_type() {
+    let body = snapshot().to_prometheus();
+    assert!(body.ends_with('\n'));
+
+    let lines: Vec<_> = body.lines().collect();
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(rest) = line.strip_prefix("# HELP ") {
+            let name = rest.split(' ').next().unwrap();
+            assert!(
+                lines[index + 1].starts_with(&format!("# TYPE {} ", name)),
+                "{}",
+                body
+            );
+        }
+    }
+    for sample in samples(&body) {
+        let (name, value) = sample.rsplit_once(' ').unwrap();
+        let name = name.split('{').next().unwrap();
+        assert!(
+            body.contains(&format!("# TYPE {} ", name)),
+            "{} has no TYPE line",
+            name
+        );
+        assert!(value.parse::<f64>().is_ok(), "{}", sample);
+    }
+}
+
+#[test]
+fn renders_values_and_labels() {
+    let body = snapshot().to_prometheus();
+    let samples = samples(&body);
+    assert!(samples.contains(&"rtds_connected 1"));
+    assert!(samples.contains(&"rtds_frames_received_total 12"));
+    assert!(samples.contains(&"rtds_reconnects_total 1"));
+    assert!(samples.contains(&"rtds_rate_limit_delay_seconds_total 1.5"));
+    assert!(samples.contains(&r#"rtds_messages_received_total{topic="activity",type="trades"} 3"#));
+    assert!(
+        samples.contains(&r#"rtds_messages_received_total{topic="crypto_prices",type="update"} 7"#)
+    );
+}
+
+#[test]
+fn omits_optional_metrics_until_they_are_known() {
+    let body = snapshot().to_prometheus();
+    assert!(!body.contains("rtds_ping_rtt_seconds"));
+    assert!(!body.contains("rtds_last_message_timestamp_seconds"));
+    assert!(!body.contains("rtds_compression_ratio"));
+
+    let body = MetricsSnapshot {
+        ping_rtt: Some(Duration::from_millis(250)),
+        last_message_at: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+        compressed_bytes: 100,
+        decompressed_bytes: 400,
+        ..snapshot()
+    }
+    .to_prometheus();
+    let samples = samples(&body);
+    assert!(samples.contains(&"rtds_ping_rtt_seconds 0.25"));
+    assert!(samples.contains(&"rtds_last_message_timestamp_seconds 1700000000"));
+    assert!(samples.contains(&"rtds_compression_ratio 4"));
+}
+
+#[cfg(feature = "prometheus")]
+mod listener {
+    use polymarket_rtds::RealTimeDataClient;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    async fn serve() -> (std::net::SocketAddr, tokio::task::JoinHandle<()>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let server = RealTimeDataClient::new()
+            .metrics_handle()
+            .serve_prometheus(addr)
+            .await
+            .unwrap();
+        (addr, server)
+    }
+
+    #[tokio::test]
+    async fn serves_the_text_format() {
+        let (addr, server) = serve().await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+        assert!(response.contains("\r\n\r\n# HELP rtds_connected "));
+        assert!(response.contains("\nrtds_connected 0\n"));
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn closes_connections_that_never_finish_the_request() {
+        let (addr, server) = serve().await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\n")
+            .await
+            .unwrap();
+
+        let mut response = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut response))
+            .await
+            .expect("the listener kept the connection open");
+        assert!(read.is_err() || response.is_empty());
+        server.abort();
+    }
+}
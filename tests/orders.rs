@@ -0,0 +1,77 @@
+use polymarket_rtds::{ClobOrder, ClobTrade, OrderEvent, OrderPhase, OrderTracker};
+use serde_json::json;
+
+fn order(id: &str, size_matched: &str) -> ClobOrder {
+    serde_json::from_value(json!({
+        "asset_id": "asset",
+        "created_at": "1753314064",
+        "expiration": "0",
+        "id": id,
+        "maker_address": "0xmaker",
+        "market": "market",
+        "order_type": "GTC",
+        "original_size": "10",
+        "outcome": "YES",
+        "owner": "owner",
+        "price": "0.5",
+        "side": "BUY",
+        "size_matched": size_matched,
+        "status": "LIVE",
+        "type": "PLACEMENT",
+    }))
+    .unwrap()
+}
+
+fn trade(id: &str, taker_order_id: &str, size: &str) -> ClobTrade {
+    serde_json::from_value(json!({
+        "asset_id": "asset",
+        "fee_rate_bps": "0",
+        "id": id,
+        "last_update": "1753314064",
+        "maker_address": "0xmaker",
+        "maker_orders": [],
+        "market": "market",
+        "match_time": "1753314064",
+        "outcome": "YES",
+        "owner": "owner",
+        "price": "0.5",
+        "side": "BUY",
+        "size": size,
+        "status": "MATCHED",
+        "taker_order_id": taker_order_id,
+        "transaction_hash": "0xhash",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn applies_fills_that_arrive_before_the_order() {
+    let mut tracker = OrderTracker::new();
+    assert!(tracker
+        .apply_trade(&trade("t1", "o1", "4"))
+        .unwrap()
+        .is_empty());
+    // The same match reported again is still counted once.
+    assert!(tracker
+        .apply_trade(&trade("t1", "o1", "4"))
+        .unwrap()
+        .is_empty());
+
+    let events = tracker.apply_order(&order("o1", "0")).unwrap();
+    assert!(matches!(events[0], OrderEvent::Opened(_)));
+    match &events[1] {
+        OrderEvent::PartiallyFilled { order, trade_id } => {
+            assert_eq!(order.size_matched, 4.0);
+            assert_eq!(trade_id.as_deref(), Some("t1"));
+        }
+        other => panic!("expected PartiallyFilled, got {:?}", other),
+    }
+
+    // The held fill is not applied a second time.
+    tracker.apply_trade(&trade("t1", "o1", "4")).unwrap();
+    let events = tracker.apply_trade(&trade("t2", "o1", "6")).unwrap();
+    let state = tracker.order("o1").unwrap();
+    assert_eq!(state.phase, OrderPhase::Filled);
+    assert_eq!(state.trade_ids, ["t1", "t2"]);
+    assert!(matches!(events[..], [OrderEvent::Filled { .. }]));
+}
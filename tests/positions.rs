@@ -0,0 +1,141 @@
+use polymarket_rtds::{ClobTrade, Fill, PositionLedger};
+use serde_json::json;
+
+const OWNER: &str = "owner";
+
+fn trade(id: &str, side: &str, size: &str, price: &str, status: &str) -> ClobTrade {
+    serde_json::from_value(json!({
+        "asset_id": "asset",
+        "fee_rate_bps": "0",
+        "id": id,
+        "last_update": "1753314064",
+        "maker_address": "0xmaker",
+        "maker_orders": [],
+        "market": "market",
+        "match_time": "1753314064",
+        "outcome": "YES",
+        "owner": OWNER,
+        "price": price,
+        "side": side,
+        "size": size,
+        "status": status,
+        "taker_order_id": format!("order-{}", id),
+        "transaction_hash": "0xhash",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn reverses_fills_of_trades_that_fail_after_matching() {
+    let mut ledger = PositionLedger::new(OWNER);
+    ledger
+        .apply_trade(&trade("t1", "BUY", "10", "0.40", "MATCHED"))
+        .unwrap();
+    ledger
+        .apply_trade(&trade("t2", "BUY", "10", "0.60", "MATCHED"))
+        .unwrap();
+    ledger
+        .apply_trade(&trade("t3", "SELL", "5", "0.70", "MATCHED"))
+        .unwrap();
+    let position = ledger.position("asset").unwrap();
+    assert_eq!(position.size, 15.0);
+    assert!((position.average_price - 0.5).abs() < 1e-9);
+
+    assert!(ledger
+        .apply_trade(&trade("t2", "BUY", "10", "0.60", "FAILED"))
+        .unwrap()
+        .is_empty());
+    let position = ledger.position("asset").unwrap();
+    assert_eq!(position.size, 5.0);
+    assert!((position.average_price - 0.4).abs() < 1e-9);
+    assert!((position.realized_pnl - 1.5).abs() < 1e-9);
+
+    // A late resend of the failed trade is not applied again.
+    assert!(ledger
+        .apply_trade(&trade("t2", "BUY", "10", "0.60", "MINED"))
+        .unwrap()
+        .is_empty());
+    assert_eq!(ledger.position("asset").unwrap().size, 5.0);
+}
+
+#[test]
+fn ignores_zero_size_fills() {
+    let mut ledger = PositionLedger::new(OWNER);
+    ledger.apply_fill(&Fill {
+        trade_id: "t1".to_string(),
+        order_id: "o1".to_string(),
+        asset_id: "asset".to_string(),
+        market: "market".to_string(),
+        outcome: "YES".to_string(),
+        side: "BUY".to_string(),
+        size: 0.0,
+        price: 0.5,
+        fee: 0.0,
+    });
+    let position = ledger.position("asset").unwrap();
+    assert_eq!(position.size, 0.0);
+    assert_eq!(position.average_price, 0.0);
+    assert_eq!(position.total_pnl(), 0.0);
+}
+
+#[test]
+fn does_not_reapply_fills_replayed_from_rest() {
+    let mut ledger = PositionLedger::new(OWNER);
+    let replayed = Fill {
+        trade_id: "t1".to_string(),
+        order_id: "order-t1".to_string(),
+        asset_id: "asset".to_string(),
+        market: "market".to_string(),
+        outcome: "YES".to_string(),
+        side: "BUY".to_string(),
+        size: 10.0,
+        price: 0.4,
+        fee: 0.0,
+    };
+    assert!(ledger.apply_fill(&replayed));
+    assert!(!ledger.apply_fill(&replayed));
+
+    // The same fill arriving on the stream is not counted twice.
+    assert!(ledger
+        .apply_trade(&trade("t1", "BUY", "10", "0.40", "MATCHED"))
+        .unwrap()
+        .is_empty());
+    assert_eq!(ledger.position("asset").unwrap().size, 10.0);
+}
+
+#[test]
+fn reverses_failed_trades_after_earlier_ones_confirm() {
+    let mut ledger = PositionLedger::new(OWNER);
+    ledger
+        .apply_trade(&trade("t1", "BUY", "10", "0.40", "MATCHED"))
+        .unwrap();
+    ledger
+        .apply_trade(&trade("t2", "BUY", "10", "0.60", "MATCHED"))
+        .unwrap();
+    ledger
+        .apply_trade(&trade("t3", "SELL", "5", "0.70", "MATCHED"))
+        .unwrap();
+    // t3 confirms before t2 settles, so it is kept for replay; t1 is folded in.
+    ledger
+        .apply_trade(&trade("t3", "SELL", "5", "0.70", "CONFIRMED"))
+        .unwrap();
+    ledger
+        .apply_trade(&trade("t1", "BUY", "10", "0.40", "CONFIRMED"))
+        .unwrap();
+
+    ledger
+        .apply_trade(&trade("t2", "BUY", "10", "0.60", "FAILED"))
+        .unwrap();
+    let position = ledger.position("asset").unwrap();
+    assert_eq!(position.size, 5.0);
+    assert!((position.average_price - 0.4).abs() < 1e-9);
+    assert!((position.realized_pnl - 1.5).abs() < 1e-9);
+
+    // Once every trade is final, a new fill still applies on top of them.
+    ledger
+        .apply_trade(&trade("t4", "BUY", "5", "0.60", "CONFIRMED"))
+        .unwrap();
+    let position = ledger.position("asset").unwrap();
+    assert_eq!(position.size, 10.0);
+    assert!((position.average_price - 0.5).abs() < 1e-9);
+}
@@ -0,0 +1,173 @@
+use polymarket_rtds::{Message, PriceUpdate, Topic, WebSocketResponse};
+use serde_json::{json, Value};
+
+fn message(topic: &str, payload: Value) -> Message {
+    serde_json::from_value(json!({
+        "topic": topic,
+        "type": "update",
+        "timestamp": 1753314064237u64,
+        "payload": payload,
+        "connection_id": "test",
+    }))
+    .unwrap()
+}
+
+fn live(symbol: &str) -> Value {
+    json!({
+        "symbol": symbol,
+        "timestamp": 1753314064213i64,
+        "value": 3456.78,
+        "full_accuracy_value": "3456.780000000000000000",
+    })
+}
+
+fn historical(symbol: &str) -> Value {
+    json!({
+        "symbol": symbol,
+        "data": [
+            { "timestamp": 1753314060000i64, "value": 3455.1 },
+            { "timestamp": 1753314061000i64, "value": 3455.9 },
+        ],
+    })
+}
+
+#[test]
+fn crypto_prices_live_update() {
+    match message("crypto_prices", live("ethusdt"))
+        .price_update()
+        .unwrap()
+    {
+        PriceUpdate::CryptoPrice(price) => {
+            assert_eq!(price.symbol, "ethusdt");
+            assert_eq!(price.timestamp, 1753314064213);
+            assert_eq!(price.value, 3456.78);
+            assert_eq!(
+                price.full_accuracy_value.as_deref(),
+                Some("3456.780000000000000000")
+            );
+        }
+        other => panic!("expected CryptoPrice, got {:?}", other),
+    }
+}
+
+#[test]
+fn chainlink_live_update() {
+    match message("crypto_prices_chainlink", live("eth/usd"))
+        .price_update()
+        .unwrap()
+    {
+        PriceUpdate::CryptoPrice(price) => assert_eq!(price.symbol, "eth/usd"),
+        other => panic!("expected CryptoPrice, got {:?}", other),
+    }
+}
+
+#[test]
+fn equity_prices_live_update() {
+    match message("equity_prices", live("AAPL"))
+        .price_update()
+        .unwrap()
+    {
+        PriceUpdate::EquityPrice(price) => {
+            assert_eq!(price.symbol, "AAPL");
+            assert_eq!(price.value, 3456.78);
+        }
+        other => panic!("expected EquityPrice, got {:?}", other),
+    }
+}
+
+#[test]
+fn live_update_without_full_accuracy_value() {
+    let payload = json!({ "symbol": "AAPL", "timestamp": 1, "value": 1.5 });
+    match message("equity_prices", payload).price_update().unwrap() {
+        PriceUpdate::EquityPrice(price) => assert!(price.full_accuracy_value.is_none()),
+        other => panic!("expected EquityPrice, got {:?}", other),
+    }
+}
+
+#[test]
+fn crypto_prices_historical_payload() {
+    match message("crypto_prices", historical("btcusdt"))
+        .price_update()
+        .unwrap()
+    {
+        PriceUpdate::CryptoPriceHistorical(history) => {
+            assert_eq!(history.symbol, "btcusdt");
+            assert_eq!(history.data.len(), 2);
+            assert_eq!(history.data[1].timestamp, 1753314061000);
+            assert_eq!(history.data[1].value, 3455.9);
+        }
+        other => panic!("expected CryptoPriceHistorical, got {:?}", other),
+    }
+}
+
+#[test]
+fn chainlink_historical_payload() {
+    match message("crypto_prices_chainlink", historical("btc/usd"))
+        .price_update()
+        .unwrap()
+    {
+        PriceUpdate::CryptoPriceHistorical(history) => assert_eq!(history.symbol, "btc/usd"),
+        other => panic!("expected CryptoPriceHistorical, got {:?}", other),
+    }
+}
+
+#[test]
+fn equity_prices_historical_payload() {
+    match message("equity_prices", historical("TSLA"))
+        .price_update()
+        .unwrap()
+    {
+        PriceUpdate::EquityPriceHistorical(history) => {
+            assert_eq!(history.symbol, "TSLA");
+            assert_eq!(history.data.len(), 2);
+        }
+        other => panic!("expected EquityPriceHistorical, got {:?}", other),
+    }
+}
+
+#[test]
+fn from_payload_matches_message_decoding() {
+    let payload = live("MSFT");
+    assert!(matches!(
+        PriceUpdate::from_payload(&Topic::EquityPrices, &payload),
+        Ok(PriceUpdate::EquityPrice(_))
+    ));
+    assert!(matches!(
+        PriceUpdate::from_payload(&Topic::CryptoPrices, &payload),
+        Ok(PriceUpdate::CryptoPrice(_))
+    ));
+}
+
+#[test]
+fn rejects_topics_without_price_updates() {
+    let error = PriceUpdate::from_payload(&Topic::Activity, &live("btcusdt")).unwrap_err();
+    assert!(error.contains("activity"), "{}", error);
+}
+
+#[test]
+fn rejects_malformed_payloads() {
+    let missing_value = json!({ "symbol": "btcusdt", "timestamp": 1 });
+    assert!(message("crypto_prices", missing_value)
+        .price_update()
+        .is_err());
+
+    let bad_points = json!({ "symbol": "AAPL", "data": [{ "timestamp": "soon" }] });
+    assert!(message("equity_prices", bad_points).price_update().is_err());
+}
+
+#[test]
+fn websocket_responses_keep_the_update_payload_for_from_payload() {
+    let mut raw = live("AAPL");
+    raw["type"] = json!("update");
+    let payload = match serde_json::from_value::<WebSocketResponse>(raw).unwrap() {
+        WebSocketResponse::PriceUpdate(payload) => payload,
+        other => panic!("unexpected response: {:?}", other),
+    };
+    match PriceUpdate::from_payload(&Topic::EquityPrices, &payload).unwrap() {
+        PriceUpdate::EquityPrice(price) => {
+            assert_eq!(price.symbol, "AAPL");
+            assert_eq!(price.value, 3456.78);
+        }
+        other => panic!("unexpected variant: {:?}", other),
+    }
+}
@@ -0,0 +1,176 @@
+use futures_util::StreamExt;
+use polymarket_rtds::{
+    ClobApiKeyCreds, MessageType, RateLimit, RateLimitConfig, RealTimeDataClient,
+    RotatingCredentials, Subscription, SubscriptionFilter, Topic,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// Control budget used by the tests: two frames at once, then one every 100ms.
+const CONTROL: RateLimit = RateLimit {
+    burst: 2,
+    per_second: 10.0,
+};
+const INTERVAL: Duration = Duration::from_millis(100);
+
+fn config(control: RateLimit) -> RateLimitConfig {
+    RateLimitConfig {
+        control,
+        ..RateLimitConfig::default()
+    }
+}
+
+/// WebSocket stand-in that reports each subscribe frame and when it arrived.
+async fn spawn_server() -> (SocketAddr, mpsc::UnboundedReceiver<(Instant, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        while let Some(Ok(msg)) = ws.next().await {
+            if let WsMessage::Text(text) = msg {
+                if text.contains("\"subscribe\"") {
+                    let _ = frame_tx.send((Instant::now(), text.to_string()));
+                }
+            }
+        }
+    });
+    (addr, frame_rx)
+}
+
+async fn connect() -> (
+    RealTimeDataClient,
+    mpsc::UnboundedReceiver<(Instant, String)>,
+) {
+    let (addr, frames) = spawn_server().await;
+    let mut client = RealTimeDataClient::new()
+        .with_host(format!("ws://{}", addr))
+        .with_rate_limit(Some(config(CONTROL)))
+        .unwrap();
+    client.connect().await.unwrap();
+    (client, frames)
+}
+
+fn creds(key: &str) -> ClobApiKeyCreds {
+    ClobApiKeyCreds {
+        key: key.to_string(),
+        secret: "secret".into(),
+        passphrase: "passphrase".into(),
+    }
+}
+
+async fn subscribe(client: &RealTimeDataClient, symbol: &str) {
+    let subscription = Subscription::new(Topic::CryptoPrices, MessageType::Update)
+        .with_filter(SubscriptionFilter::symbol(symbol))
+        .unwrap();
+    client.subscribe(vec![subscription]).await.unwrap();
+}
+
+async fn next_frame(frames: &mut mpsc::UnboundedReceiver<(Instant, String)>) -> (Instant, String) {
+    tokio::time::timeout(Duration::from_secs(5), frames.recv())
+        .await
+        .expect("timed out waiting for a frame")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn queues_frames_over_the_burst() {
+    let (client, mut frames) = connect().await;
+    let start = Instant::now();
+    for symbol in ["btcusdt", "ethusdt", "solusdt", "xrpusdt"] {
+        subscribe(&client, symbol).await;
+    }
+
+    let mut arrivals = Vec::new();
+    for _ in 0..4 {
+        arrivals.push(next_frame(&mut frames).await.0 - start);
+    }
+    // The burst goes out at once; the rest wait for the bucket to refill.
+    assert!(arrivals[1] < INTERVAL / 2, "{:?}", arrivals);
+    assert!(arrivals[2] >= INTERVAL * 9 / 10, "{:?}", arrivals);
+    assert!(arrivals[3] >= INTERVAL * 19 / 10, "{:?}", arrivals);
+
+    let metrics = client.metrics();
+    assert_eq!(metrics.rate_limited_frames, 2);
+    assert!(metrics.rate_limit_delay >= INTERVAL * 27 / 10);
+    assert_eq!(metrics.outbound_queue, 0);
+}
+
+#[tokio::test]
+async fn refills_the_burst_while_idle() {
+    let (client, mut frames) = connect().await;
+    subscribe(&client, "btcusdt").await;
+    subscribe(&client, "ethusdt").await;
+    for _ in 0..2 {
+        next_frame(&mut frames).await;
+    }
+
+    // Waiting two intervals earns back the whole burst.
+    tokio::time::sleep(INTERVAL * 2).await;
+    let start = Instant::now();
+    subscribe(&client, "solusdt").await;
+    subscribe(&client, "xrpusdt").await;
+    for _ in 0..2 {
+        assert!(next_frame(&mut frames).await.0 - start < INTERVAL / 2);
+    }
+    assert_eq!(client.metrics().rate_limited_frames, 0);
+}
+
+#[tokio::test]
+async fn queued_frames_use_the_credentials_current_when_sent() {
+    let (addr, mut frames) = spawn_server().await;
+    let credentials = Arc::new(RotatingCredentials::new(Some(creds("old-key"))));
+    let mut client = RealTimeDataClient::new()
+        .with_host(format!("ws://{}", addr))
+        .with_rate_limit(Some(config(RateLimit {
+            burst: 1,
+            per_second: 10.0,
+        })))
+        .unwrap()
+        .with_credential_provider(credentials.clone());
+    client.connect().await.unwrap();
+
+    let orders = Subscription::new(Topic::ClobUser, MessageType::Order);
+    let trades = Subscription::new(Topic::ClobUser, MessageType::Trade);
+    client.subscribe(vec![orders]).await.unwrap();
+    client.subscribe(vec![trades]).await.unwrap();
+    let (_, first) = next_frame(&mut frames).await;
+    assert!(first.contains("old-key"), "{}", first);
+
+    // The second frame is still waiting for a token when the keys rotate.
+    credentials.rotate(creds("new-key"));
+    let (_, second) = next_frame(&mut frames).await;
+    assert!(second.contains("\"trade\""), "{}", second);
+    assert!(second.contains("new-key"), "{}", second);
+}
+
+#[test]
+fn rejects_budgets_that_never_refill() {
+    for per_second in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let error = RealTimeDataClient::new()
+            .with_rate_limit(Some(config(RateLimit {
+                burst: 1,
+                per_second,
+            })))
+            .err()
+            .unwrap();
+        assert!(error.contains("control"), "{}", error);
+    }
+}
+
+#[test]
+fn rejects_empty_bursts() {
+    let mut rate_limit = RateLimitConfig::default();
+    rate_limit.ping.burst = 0;
+    let error = RealTimeDataClient::new()
+        .with_rate_limit(Some(rate_limit))
+        .err()
+        .unwrap();
+    assert!(error.contains("ping"), "{}", error);
+}
@@ -0,0 +1,196 @@
+use futures_util::{SinkExt, StreamExt};
+use polymarket_rtds::{
+    dedup_key, Message, MessageType, RealTimeDataClient, ReconnectConfig, RedundantClient,
+    Subscription, Topic,
+};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+const TICK: Duration = Duration::from_millis(20);
+
+/// WebSocket stand-in that sends a price tick every `TICK` on each connection and
+/// reports the subscribe frames it receives.
+async fn spawn_server(listener: TcpListener) -> mpsc::UnboundedReceiver<Value> {
+    let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let frame_tx = frame_tx.clone();
+            tokio::spawn(async move {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let mut ticks = tokio::time::interval(TICK);
+                for timestamp in 0u64.. {
+                    tokio::select! {
+                        _ = ticks.tick() => {
+                            let message = json!({
+                                "topic": "crypto_prices",
+                                "type": "update",
+                                "timestamp": timestamp,
+                                "payload": { "symbol": "btcusdt", "timestamp": timestamp, "value": 1.0 },
+                                "connection_id": "test",
+                            });
+                            if ws.send(WsMessage::Text(message.to_string().into())).await.is_err() {
+                                return;
+                            }
+                        }
+                        msg = ws.next() => match msg {
+                            Some(Ok(WsMessage::Text(text))) => {
+                                if let Ok(frame) = serde_json::from_str(&text) {
+                                    let _ = frame_tx.send(frame);
+                                }
+                            }
+                            Some(Ok(_)) => {}
+                            _ => return,
+                        },
+                    }
+                }
+            });
+        }
+    });
+    frame_rx
+}
+
+/// A listener that accepts connections but never answers the handshake.
+async fn spawn_black_hole() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            held.push(stream);
+        }
+    });
+    addr
+}
+
+fn client(addr: SocketAddr) -> RealTimeDataClient {
+    RealTimeDataClient::new().with_host(format!("ws://{}", addr))
+}
+
+fn prices() -> Subscription {
+    Subscription::new(Topic::CryptoPrices, MessageType::Update)
+}
+
+fn retry_quickly() -> Option<ReconnectConfig> {
+    Some(ReconnectConfig {
+        initial_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(50),
+        max_attempts: None,
+    })
+}
+
+#[tokio::test]
+async fn keeps_delivering_while_a_connection_retries() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let healthy = listener.local_addr().unwrap();
+    let _frames = spawn_server(listener).await;
+    let black_hole = spawn_black_hole().await;
+
+    let mut redundant = RedundantClient::from_clients(vec![client(healthy), client(black_hole)])
+        .with_reconnect(retry_quickly())
+        .with_connect_timeout(Duration::from_millis(300));
+    redundant.connect().await.unwrap();
+    redundant.subscribe(vec![prices()]).await.unwrap();
+
+    // The black hole is retried in the background the whole time; each retry takes
+    // the full connect timeout.
+    let mut last = Instant::now();
+    let mut longest_gap = Duration::ZERO;
+    for _ in 0..50 {
+        let message = tokio::time::timeout(Duration::from_secs(5), redundant.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.topic, Topic::CryptoPrices);
+        longest_gap = longest_gap.max(last.elapsed());
+        last = Instant::now();
+    }
+    assert!(
+        longest_gap < Duration::from_millis(200),
+        "{:?}",
+        longest_gap
+    );
+
+    let health = redundant.health();
+    assert!(health[0].connected);
+    assert!(!health[1].connected);
+}
+
+#[tokio::test]
+async fn restores_and_resubscribes_connections_that_come_back() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let first = listener.local_addr().unwrap();
+    let _first_frames = spawn_server(listener).await;
+    // Reserve a port for the second server, which is not up yet.
+    let second = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let mut redundant = RedundantClient::from_clients(vec![client(first), client(second)])
+        .with_reconnect(retry_quickly());
+    redundant.connect().await.unwrap();
+    redundant.subscribe(vec![prices()]).await.unwrap();
+    assert!(!redundant.health()[1].connected);
+
+    let mut second_frames = spawn_server(TcpListener::bind(second).await.unwrap()).await;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !redundant.health()[1].connected {
+        assert!(
+            Instant::now() < deadline,
+            "second connection never came back"
+        );
+        tokio::time::timeout(Duration::from_secs(1), redundant.recv())
+            .await
+            .unwrap();
+    }
+    let frame = tokio::time::timeout(Duration::from_secs(5), second_frames.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(frame["action"], "subscribe");
+    assert_eq!(frame["subscriptions"][0]["topic"], "crypto_prices");
+
+    redundant.disconnect().await.unwrap();
+}
+
+fn trade(size: u64, price: f64, side: &str, outcome: &str) -> Message {
+    serde_json::from_value(json!({
+        "topic": "activity",
+        "type": "trades",
+        "timestamp": 1,
+        "payload": {
+            "transactionHash": "0xhash",
+            "asset": "asset",
+            "proxyWallet": "0xwallet",
+            "side": side,
+            "size": size,
+            "price": price,
+            "outcome": outcome,
+        },
+        "connection_id": "test",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn fills_in_one_transaction_have_distinct_keys() {
+    let fill = trade(10, 0.5, "BUY", "Yes");
+    assert_eq!(dedup_key(&fill), dedup_key(&trade(10, 0.5, "BUY", "Yes")));
+    for other in [
+        trade(20, 0.5, "BUY", "Yes"),
+        trade(10, 0.6, "BUY", "Yes"),
+        trade(10, 0.5, "SELL", "Yes"),
+        trade(10, 0.5, "BUY", "No"),
+    ] {
+        assert_ne!(dedup_key(&fill), dedup_key(&other));
+    }
+}
@@ -0,0 +1,217 @@
+use polymarket_rtds::{
+    RfqAction, RfqBook, RfqCloseReason, RfqEvent, RfqQuote, RfqRequest, RfqState,
+};
+use serde_json::json;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const EXPIRY: i64 = 1_700_000_000;
+
+fn at(seconds: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds as u64)
+}
+
+fn request(id: &str, state: &str) -> RfqRequest {
+    serde_json::from_value(json!({
+        "requestId": id,
+        "proxyAddress": "0xproxy",
+        "market": "0xmarket",
+        "token": "1",
+        "complement": "2",
+        "state": state,
+        "side": "BUY",
+        "sizeIn": 50.0,
+        "sizeOut": 100.0,
+        "price": 0.5,
+        "expiry": EXPIRY,
+    }))
+    .unwrap()
+}
+
+fn quote(id: &str, request_id: &str, state: &str, size_in: f64) -> RfqQuote {
+    serde_json::from_value(json!({
+        "quoteId": id,
+        "requestId": request_id,
+        "proxyAddress": "0xmaker",
+        "token": "1",
+        "state": state,
+        "side": "BUY",
+        "sizeIn": size_in,
+        "sizeOut": 100.0,
+        "condition": "0xmarket",
+        "complement": "2",
+        "expiry": EXPIRY,
+    }))
+    .unwrap()
+}
+
+fn open_book() -> RfqBook {
+    let mut book = RfqBook::new();
+    book.apply_request(RfqAction::Created, request("r1", "STATE_ACCEPTING_QUOTES"))
+        .unwrap();
+    book.apply_quote(RfqAction::Created, quote("q1", "r1", "STATE_ACTIVE", 50.0))
+        .unwrap();
+    book
+}
+
+#[test]
+fn closes_a_request_and_its_quotes_on_cancel() {
+    let mut book = open_book();
+    let events = book
+        .apply_request(RfqAction::Canceled, request("r1", "STATE_CANCELED"))
+        .unwrap();
+    assert!(matches!(
+        events[0],
+        RfqEvent::QuoteClosed {
+            reason: RfqCloseReason::RequestClosed,
+            ..
+        }
+    ));
+    assert!(matches!(
+        events[1],
+        RfqEvent::RequestClosed {
+            reason: RfqCloseReason::Canceled,
+            ..
+        }
+    ));
+    assert!(book.request("r1").is_none());
+}
+
+#[test]
+fn ignores_a_close_event_after_local_expiry() {
+    let mut book = open_book();
+    let events = book.expire_due(at(EXPIRY));
+    assert_eq!(events.len(), 2);
+    assert!(book.request("r1").is_none());
+
+    // The server's own expiry and cancel events arrive after the book expired the
+    // request itself.
+    for action in [RfqAction::Expired, RfqAction::Canceled] {
+        let events = book
+            .apply_request(action, request("r1", "STATE_EXPIRED"))
+            .unwrap();
+        assert!(events.is_empty());
+    }
+    let events = book
+        .apply_quote(RfqAction::Expired, quote("q1", "r1", "STATE_EXPIRED", 50.0))
+        .unwrap();
+    assert!(events.is_empty());
+}
+
+#[test]
+fn ignores_a_quote_close_after_local_expiry() {
+    let mut book = open_book();
+    let mut late = quote("q2", "r1", "STATE_ACTIVE", 40.0);
+    late.expiry = EXPIRY - 10;
+    book.apply_quote(RfqAction::Created, late.clone()).unwrap();
+
+    let events = book.expire_due(at(EXPIRY - 10));
+    assert_eq!(events.len(), 1);
+    assert!(book
+        .apply_quote(RfqAction::Expired, late)
+        .unwrap()
+        .is_empty());
+    assert_eq!(book.request("r1").unwrap().quotes.len(), 1);
+}
+
+#[test]
+fn ignores_replayed_creates() {
+    let mut book = open_book();
+    assert!(book
+        .apply_request(RfqAction::Created, request("r1", "STATE_ACCEPTING_QUOTES"))
+        .unwrap()
+        .is_empty());
+    assert!(book
+        .apply_quote(RfqAction::Created, quote("q1", "r1", "STATE_ACTIVE", 50.0))
+        .unwrap()
+        .is_empty());
+    assert_eq!(book.request("r1").unwrap().quotes.len(), 1);
+
+    // A create replayed after the request closed does not reopen it.
+    book.apply_request(RfqAction::Canceled, request("r1", "STATE_CANCELED"))
+        .unwrap();
+    assert!(book
+        .apply_request(RfqAction::Created, request("r1", "STATE_ACCEPTING_QUOTES"))
+        .unwrap()
+        .is_empty());
+    assert!(book.request("r1").is_none());
+}
+
+#[test]
+fn still_rejects_events_for_unknown_entries() {
+    let mut book = open_book();
+    assert!(book
+        .apply_request(RfqAction::Edited, request("r2", "STATE_ACCEPTING_QUOTES"))
+        .is_err());
+    assert!(book
+        .apply_quote(RfqAction::Created, quote("q9", "r2", "STATE_ACTIVE", 50.0))
+        .is_err());
+    assert!(book
+        .apply_quote(
+            RfqAction::Canceled,
+            quote("q9", "r1", "STATE_CANCELED", 50.0)
+        )
+        .is_err());
+}
+
+#[test]
+fn closes_entries_by_state() {
+    let mut book = open_book();
+    let events = book
+        .apply_quote(RfqAction::Edited, quote("q1", "r1", "STATE_FILLED", 50.0))
+        .unwrap();
+    assert!(matches!(
+        events[0],
+        RfqEvent::QuoteClosed {
+            reason: RfqCloseReason::Filled,
+            ..
+        }
+    ));
+
+    let events = book
+        .apply_request(RfqAction::Edited, request("r1", "STATE_FILLED"))
+        .unwrap();
+    match &events[..] {
+        [RfqEvent::RequestClosed { request, reason }] => {
+            assert_eq!(request.state, "STATE_FILLED");
+            assert_eq!(*reason, RfqCloseReason::Filled);
+        }
+        other => panic!("unexpected events: {:?}", other),
+    }
+
+    // A request that arrives already canceled never opens.
+    assert!(book
+        .apply_request(RfqAction::Created, request("r2", "STATE_CANCELED"))
+        .unwrap()
+        .is_empty());
+    assert!(book.request("r2").is_none());
+}
+
+#[test]
+fn parses_states() {
+    assert_eq!(RfqState::parse("STATE_CANCELED"), RfqState::Canceled);
+    assert_eq!(RfqState::parse("cancelled"), RfqState::Canceled);
+    assert_eq!(RfqState::parse("STATE_EXPIRED"), RfqState::Expired);
+    assert_eq!(RfqState::parse("STATE_FILLED"), RfqState::Filled);
+    assert_eq!(RfqState::parse("STATE_ACCEPTING_QUOTES"), RfqState::Open);
+    assert_eq!(RfqState::parse(""), RfqState::Open);
+}
+
+#[test]
+fn forgets_closed_entries_an_hour_after_expiry() {
+    let mut book = open_book();
+    book.expire_due(at(EXPIRY));
+    book.expire_due(at(EXPIRY + 3600));
+
+    // Past the retention window a late event is rejected again.
+    assert!(book
+        .apply_request(RfqAction::Expired, request("r1", "STATE_EXPIRED"))
+        .is_err());
+}
+
+#[test]
+fn ranks_quotes_by_price() {
+    let mut book = open_book();
+    book.apply_quote(RfqAction::Created, quote("q2", "r1", "STATE_ACTIVE", 40.0))
+        .unwrap();
+    assert_eq!(book.best_quote("r1").unwrap().quote_id, "q2");
+}
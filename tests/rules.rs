@@ -0,0 +1,223 @@
+use polymarket_rtds::{Condition, Message, MessageRouter, MessageType, Rule, RuleSet, Topic};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+const RULES_TOML: &str = r#"
+[[rules]]
+route = "majors"
+topic = "crypto_prices"
+type = "update"
+
+[[rules.conditions]]
+field = "symbol"
+in = ["btcusdt", "ethusdt"]
+
+[[rules.conditions]]
+field = "value"
+range = { min = 1000.0 }
+
+[[rules]]
+route = "trades"
+topic = "activity"
+"#;
+
+const RULES_JSON: &str = r#"{
+    "rules": [
+        {
+            "route": "majors",
+            "topic": "crypto_prices",
+            "type": "update",
+            "conditions": [
+                { "field": "symbol", "in": ["btcusdt", "ethusdt"] },
+                { "field": "value", "range": { "min": 1000.0 } }
+            ]
+        },
+        { "route": "trades", "topic": "activity" }
+    ]
+}"#;
+
+fn message(topic: &str, message_type: &str, payload: Value) -> Message {
+    serde_json::from_value(json!({
+        "topic": topic,
+        "type": message_type,
+        "timestamp": 1,
+        "payload": payload,
+        "connection_id": "test",
+    }))
+    .unwrap()
+}
+
+fn price(symbol: &str, value: f64) -> Message {
+    message(
+        "crypto_prices",
+        "update",
+        json!({ "symbol": symbol, "timestamp": 1, "value": value }),
+    )
+}
+
+fn trade() -> Message {
+    message("activity", "trades", json!({ "slug": "senate" }))
+}
+
+/// Writes `contents` to a file named `name` in a fresh temporary directory.
+fn write_file(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "polymarket-rtds-rules-{}-{}",
+        std::process::id(),
+        name
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn assert_loaded(rules: &RuleSet) {
+    assert_eq!(rules.rules.len(), 2);
+    assert_eq!(rules.rules[0].topic, Some(Topic::CryptoPrices));
+    assert_eq!(rules.rules[0].message_type, Some(MessageType::Update));
+    assert_eq!(rules.rules[1].message_type, None);
+
+    assert_eq!(rules.routes(&price("btcusdt", 60_000.0)), vec!["majors"]);
+    assert!(rules.routes(&price("btcusdt", 10.0)).is_empty());
+    assert!(rules.routes(&price("solusdt", 60_000.0)).is_empty());
+    assert_eq!(rules.routes(&trade()), vec!["trades"]);
+}
+
+#[test]
+fn loads_rules_from_toml_and_json() {
+    assert_loaded(&RuleSet::from_toml_str(RULES_TOML).unwrap());
+    assert_loaded(&RuleSet::from_json_str(RULES_JSON).unwrap());
+    assert_loaded(&RuleSet::from_file(write_file("rules.toml", RULES_TOML)).unwrap());
+    assert_loaded(&RuleSet::from_file(write_file("rules.json", RULES_JSON)).unwrap());
+}
+
+#[test]
+fn rejects_invalid_rule_files() {
+    let error = RuleSet::from_file(write_file("rules.yaml", RULES_TOML)).unwrap_err();
+    assert!(error.contains("expected .toml or .json"), "{}", error);
+
+    let error = RuleSet::from_toml_str(
+        "[[rules]]\nroute = \"bad\"\n[[rules.conditions]]\nfield = \"slug\"\nregex = \"(\"\n",
+    )
+    .unwrap_err();
+    assert!(
+        error.contains("Invalid regex for field 'slug'"),
+        "{}",
+        error
+    );
+
+    let error = RuleSet::from_json_str(r#"{"rules":[{"topic":"activity"}]}"#).unwrap_err();
+    assert!(error.contains("route"), "{}", error);
+}
+
+#[test]
+fn equals_compares_the_whole_value() {
+    let condition = Condition::equals("side", "BUY");
+    assert!(condition.matches(&json!({ "side": "BUY" })));
+    assert!(!condition.matches(&json!({ "side": "SELL" })));
+    assert!(!condition.matches(&json!({})));
+
+    let condition = Condition::equals("size", 5);
+    assert!(condition.matches(&json!({ "size": 5 })));
+    assert!(!condition.matches(&json!({ "size": "5" })));
+}
+
+#[test]
+fn in_set_matches_any_value() {
+    let condition = Condition::in_set("symbol", ["btcusdt", "ethusdt"]);
+    assert!(condition.matches(&json!({ "symbol": "ethusdt" })));
+    assert!(!condition.matches(&json!({ "symbol": "solusdt" })));
+    assert!(!condition.matches(&json!({ "symbol": null })));
+}
+
+#[test]
+fn range_is_inclusive_and_reads_numeric_strings() {
+    let condition = Condition::range("price", Some(0.2), Some(0.8));
+    assert!(condition.matches(&json!({ "price": 0.2 })));
+    assert!(condition.matches(&json!({ "price": 0.8 })));
+    assert!(condition.matches(&json!({ "price": "0.5" })));
+    assert!(!condition.matches(&json!({ "price": 0.9 })));
+    assert!(!condition.matches(&json!({ "price": "high" })));
+    assert!(!condition.matches(&json!({ "price": true })));
+
+    let unbounded = Condition::range("price", None, Some(0.5));
+    assert!(unbounded.matches(&json!({ "price": -100 })));
+}
+
+#[test]
+fn regex_matches_string_fields_only() {
+    let condition = Condition::regex("slug", "^nba-").unwrap();
+    assert!(condition.matches(&json!({ "slug": "nba-finals" })));
+    assert!(!condition.matches(&json!({ "slug": "wnba-finals" })));
+    assert!(!condition.matches(&json!({ "slug": 1 })));
+
+    let error = Condition::regex("slug", "[").unwrap_err();
+    assert!(
+        error.contains("Invalid regex for field 'slug'"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn follows_nested_paths() {
+    let condition = Condition::equals("pc.1.a", "2");
+    assert!(condition.matches(&json!({ "pc": [{ "a": "1" }, { "a": "2" }] })));
+    assert!(!condition.matches(&json!({ "pc": [{ "a": "1" }] })));
+    assert!(!condition.matches(&json!({ "pc": { "a": "2" } })));
+}
+
+#[test]
+fn routes_each_message_once_per_channel() {
+    let rules = RuleSet::new(vec![
+        Rule::new("all"),
+        Rule::new("all").with_topic(Topic::Activity),
+        Rule::new("trades")
+            .with_topic(Topic::Activity)
+            .with_message_type(MessageType::Trades),
+        Rule::new("unopened"),
+    ]);
+    let mut router = MessageRouter::new(rules);
+    let mut all = router.channel("all");
+    let mut trades = router.channel("trades");
+
+    assert_eq!(router.route(&trade()), 2);
+    assert_eq!(all.try_recv().unwrap().topic, Topic::Activity);
+    assert!(all.try_recv().is_err());
+    assert!(trades.try_recv().is_ok());
+
+    assert_eq!(router.route(&price("btcusdt", 1.0)), 1);
+    assert!(trades.try_recv().is_err());
+}
+
+#[test]
+fn reloads_rules_while_routing() {
+    let mut router = MessageRouter::new(RuleSet::new(vec![
+        Rule::new("majors").with_condition(Condition::equals("symbol", "btcusdt"))
+    ]));
+    let mut majors = router.channel("majors");
+    let mut trades = router.channel("trades");
+    let handle = router.rules_handle();
+
+    assert_eq!(router.route(&price("btcusdt", 1.0)), 1);
+    assert_eq!(router.route(&trade()), 0);
+
+    handle
+        .reload_from_file(write_file("reload.toml", RULES_TOML))
+        .unwrap();
+    assert_eq!(router.route(&price("btcusdt", 1.0)), 0);
+    assert_eq!(router.route(&trade()), 1);
+    assert!(majors.try_recv().is_ok());
+    assert!(majors.try_recv().is_err());
+    assert!(trades.try_recv().is_ok());
+
+    // A file that fails to load leaves the current rules in place.
+    assert!(handle
+        .reload_from_file(write_file("broken.json", "{"))
+        .is_err());
+    assert_eq!(router.route(&trade()), 1);
+
+    handle.reload(RuleSet::default());
+    assert_eq!(router.route(&trade()), 0);
+}
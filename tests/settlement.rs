@@ -0,0 +1,93 @@
+use polymarket_rtds::{ClobTrade, SettlementConfig, SettlementStatus, SettlementTracker};
+use serde_json::json;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn trade(status: &str, transaction_hash: &str) -> ClobTrade {
+    serde_json::from_value(json!({
+        "asset_id": "asset",
+        "fee_rate_bps": "0",
+        "id": "t1",
+        "last_update": "1753314064",
+        "maker_address": "0xmaker",
+        "maker_orders": [],
+        "market": "market",
+        "match_time": "1753314064",
+        "outcome": "YES",
+        "owner": "owner",
+        "price": "0.5",
+        "side": "BUY",
+        "size": "10",
+        "status": status,
+        "taker_order_id": "o1",
+        "transaction_hash": transaction_hash,
+    }))
+    .unwrap()
+}
+
+fn statuses(tracker: &SettlementTracker) -> Vec<SettlementStatus> {
+    tracker
+        .trade("t1")
+        .unwrap()
+        .transitions
+        .iter()
+        .map(|transition| transition.status.clone())
+        .collect()
+}
+
+#[test]
+fn ignores_stale_updates_after_confirmation() {
+    let mut tracker = SettlementTracker::new(SettlementConfig::default());
+    let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+    assert_eq!(
+        tracker.apply_trade_at(&trade("MATCHED", ""), at(1)).len(),
+        1
+    );
+    assert_eq!(
+        tracker
+            .apply_trade_at(&trade("CONFIRMED", "0xfinal"), at(2))
+            .len(),
+        1
+    );
+
+    // MINED and MATCHED resends arriving late do not reopen the trade.
+    assert!(tracker
+        .apply_trade_at(&trade("MINED", "0xstale"), at(3))
+        .is_empty());
+    assert!(tracker
+        .apply_trade_at(&trade("MATCHED", ""), at(4))
+        .is_empty());
+    assert!(tracker
+        .apply_trade_at(&trade("FAILED", ""), at(5))
+        .is_empty());
+
+    let settlement = tracker.trade("t1").unwrap();
+    assert_eq!(*settlement.status(), SettlementStatus::Confirmed);
+    assert_eq!(settlement.transaction_hash.as_deref(), Some("0xfinal"));
+    assert_eq!(
+        statuses(&tracker),
+        [SettlementStatus::Matched, SettlementStatus::Confirmed]
+    );
+    assert_eq!(tracker.pending().count(), 0);
+}
+
+#[test]
+fn ignores_stale_updates_for_an_earlier_stage() {
+    let mut tracker = SettlementTracker::new(SettlementConfig::default());
+    let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+    tracker.apply_trade_at(&trade("MINED", "0xfirst"), at(1));
+    assert!(tracker
+        .apply_trade_at(&trade("MATCHED", ""), at(2))
+        .is_empty());
+
+    // A retry may move a mined trade to RETRYING and back.
+    tracker.apply_trade_at(&trade("RETRYING", ""), at(3));
+    tracker.apply_trade_at(&trade("MINED", "0xsecond"), at(4));
+    assert_eq!(
+        statuses(&tracker),
+        [
+            SettlementStatus::Mined,
+            SettlementStatus::Retrying,
+            SettlementStatus::Mined
+        ]
+    );
+}
@@ -0,0 +1,162 @@
+use futures_util::StreamExt;
+use polymarket_rtds::{
+    ClobMarketSubscription, RealTimeDataClient, ReconnectConfig, ShardKey, ShardedClient,
+};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, Notify};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// A subscribe or unsubscribe frame: the connection it arrived on, the action and
+/// the token IDs of each subscription.
+type Frame = (usize, String, Vec<Vec<String>>);
+
+/// WebSocket stand-in that accepts any number of connections and forwards their
+/// frames. Notifying `drop_first` closes the first connection.
+async fn spawn_server() -> (String, mpsc::UnboundedReceiver<Frame>, Arc<Notify>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+    let drop_first = Arc::new(Notify::new());
+    let notify = drop_first.clone();
+    tokio::spawn(async move {
+        for connection in 0.. {
+            let (stream, _) = listener.accept().await.unwrap();
+            let frame_tx = frame_tx.clone();
+            let notify = notify.clone();
+            tokio::spawn(async move {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                loop {
+                    let msg = tokio::select! {
+                        msg = ws.next() => msg,
+                        _ = notify.notified(), if connection == 0 => {
+                            let _ = ws.close(None).await;
+                            return;
+                        }
+                    };
+                    match msg {
+                        Some(Ok(WsMessage::Text(text))) => {
+                            if let Ok(frame) = serde_json::from_str::<Value>(&text) {
+                                let _ = frame_tx.send(decode(connection, &frame));
+                            }
+                        }
+                        Some(Ok(_)) => {}
+                        _ => return,
+                    }
+                }
+            });
+        }
+    });
+    (format!("ws://{}", addr), frame_rx, drop_first)
+}
+
+fn decode(connection: usize, frame: &Value) -> Frame {
+    let tokens = frame["subscriptions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| serde_json::from_str(s["filters"].as_str().unwrap()).unwrap())
+        .collect();
+    (
+        connection,
+        frame["action"].as_str().unwrap().to_string(),
+        tokens,
+    )
+}
+
+async fn next_frame(frames: &mut mpsc::UnboundedReceiver<Frame>) -> Frame {
+    tokio::time::timeout(Duration::from_secs(5), frames.recv())
+        .await
+        .expect("timed out waiting for a frame")
+        .unwrap()
+}
+
+fn frame(connection: usize, action: &str, tokens: &[&str]) -> Frame {
+    (
+        connection,
+        action.to_string(),
+        vec![tokens.iter().map(|t| t.to_string()).collect()],
+    )
+}
+
+async fn connect(host: &str) -> ShardedClient {
+    let client = RealTimeDataClient::new()
+        .with_host(host)
+        .with_reconnect(Some(ReconnectConfig {
+            initial_delay: Duration::from_millis(200),
+            ..Default::default()
+        }));
+    let mut sharded = ShardedClient::from_clients(vec![client], ShardKey::TokenId);
+    sharded.connect().await.unwrap();
+    sharded
+        .subscribe(vec![ClobMarketSubscription::agg_orderbook()
+            .token_ids(["a", "b", "c"])
+            .build()])
+        .await
+        .unwrap();
+    sharded
+}
+
+#[tokio::test]
+async fn partial_removal_replaces_the_held_subscription() {
+    let (host, mut frames, _) = spawn_server().await;
+    let mut sharded = connect(&host).await;
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame(0, "subscribe", &["a", "b", "c"])
+    );
+
+    sharded
+        .unsubscribe(vec![ClobMarketSubscription::agg_orderbook()
+            .token_id("b")
+            .build()])
+        .await
+        .unwrap();
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame(0, "unsubscribe", &["a", "b", "c"])
+    );
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame(0, "subscribe", &["a", "c"])
+    );
+    assert_eq!(sharded.health()[0].subscriptions, 1);
+}
+
+#[tokio::test]
+async fn replays_only_the_remaining_tokens_after_reconnecting() {
+    let (host, mut frames, drop_first) = spawn_server().await;
+    let mut sharded = connect(&host).await;
+    sharded
+        .unsubscribe(vec![ClobMarketSubscription::agg_orderbook()
+            .token_id("b")
+            .build()])
+        .await
+        .unwrap();
+    for _ in 0..3 {
+        next_frame(&mut frames).await;
+    }
+    assert!(sharded.health()[0].connected);
+
+    drop_first.notify_one();
+    let mut reconnecting = false;
+    for _ in 0..100 {
+        let health = &sharded.health()[0];
+        if health.reconnecting {
+            assert!(!health.connected);
+            reconnecting = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert!(reconnecting, "shard never reported reconnecting");
+
+    assert_eq!(
+        next_frame(&mut frames).await,
+        frame(1, "subscribe", &["a", "c"])
+    );
+    let health = &sharded.health()[0];
+    assert!(health.connected && !health.reconnecting);
+}
@@ -0,0 +1,79 @@
+use polymarket_rtds::{TapeConfig, TradeActivity, TradeTape};
+use serde_json::json;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+fn trade(slug: &str, condition_id: &str, timestamp: i64) -> TradeActivity {
+    serde_json::from_value(json!({
+        "asset": "asset",
+        "bio": "",
+        "conditionId": condition_id,
+        "eventSlug": "event",
+        "icon": "",
+        "name": "",
+        "outcome": "Yes",
+        "outcomeIndex": 0,
+        "price": 0.5,
+        "profileImage": "",
+        "proxyWallet": "0xwallet",
+        "pseudonym": "",
+        "side": "BUY",
+        "size": 10,
+        "slug": slug,
+        "timestamp": timestamp,
+        "title": "",
+        "transactionHash": "0xhash",
+    }))
+    .unwrap()
+}
+
+fn tape() -> TradeTape {
+    TradeTape::new(TapeConfig {
+        windows: vec![WINDOW],
+        ..Default::default()
+    })
+}
+
+#[test]
+fn drops_markets_without_trades_in_the_longest_window() {
+    let mut tape = tape();
+    let now = unix_now();
+    tape.record(&trade("quiet", "0xquiet", now));
+    tape.record(&trade("busy", "0xbusy", now));
+    assert_eq!(tape.prune_at(now + 30), 0);
+    assert!(tape.market("quiet").is_some());
+
+    assert_eq!(tape.prune_at(now + 2 * WINDOW.as_secs() as i64), 2);
+    assert!(tape.market("quiet").is_none());
+    assert!(tape.market("0xbusy").is_none());
+    assert_eq!(tape.markets().count(), 0);
+}
+
+#[test]
+fn window_stats_look_up_markets_by_slug_or_condition_id() {
+    let mut tape = tape();
+    let now = unix_now();
+    tape.record(&trade("market", "0xmarket", now));
+    tape.record(&trade(
+        "market",
+        "0xmarket",
+        now - 2 * WINDOW.as_secs() as i64,
+    ));
+
+    for key in ["market", "0xmarket"] {
+        let stats = tape.window_stats(key);
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].buy_count, 1);
+        assert_eq!(stats[0].buy_volume, 10.0);
+    }
+    assert_eq!(tape.market("market").unwrap().recent().count(), 2);
+    assert!(tape.window_stats("unknown").is_empty());
+}
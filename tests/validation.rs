@@ -0,0 +1,139 @@
+use polymarket_rtds::{
+    ActivitySubscription, ClobApiKeyCreds, ClobUserSubscription, MessageType, RealTimeDataClient,
+    Subscription, Topic,
+};
+
+fn creds() -> ClobApiKeyCreds {
+    ClobApiKeyCreds::new("key", "secret", "passphrase")
+}
+
+fn with_filters(topic: Topic, message_type: MessageType, filters: &str) -> Subscription {
+    let mut subscription = Subscription::new(topic, message_type);
+    subscription.filters = Some(filters.to_string());
+    subscription
+}
+
+fn assert_rejected(subscription: &Subscription, expected: &str) {
+    let error = subscription.validate().unwrap_err();
+    assert!(error.contains(expected), "{}", error);
+}
+
+#[test]
+fn rejects_message_types_the_topic_does_not_publish() {
+    let subscription = Subscription::new(Topic::Activity, MessageType::AggOrderbook);
+    assert_rejected(&subscription, "is not published on topic 'activity'");
+}
+
+#[test]
+fn rejects_invalid_filters() {
+    let cases = [
+        (
+            Topic::Activity,
+            MessageType::Trades,
+            "not json",
+            "not valid JSON",
+        ),
+        (
+            Topic::Activity,
+            MessageType::Trades,
+            r#"{"event_slug":"a","market_slug":"b"}"#,
+            "exactly one",
+        ),
+        (
+            Topic::Activity,
+            MessageType::Trades,
+            r#"{"slug":"a"}"#,
+            "unknown field 'slug'",
+        ),
+        (
+            Topic::Activity,
+            MessageType::Trades,
+            r#"{"event_slug":1}"#,
+            "must be a string",
+        ),
+        (
+            Topic::Comments,
+            MessageType::CommentCreated,
+            r#"{"parentEntityID":"100","parentEntityType":"Event"}"#,
+            "'parentEntityID' must be a number",
+        ),
+        (
+            Topic::CryptoPrices,
+            MessageType::Update,
+            r#"{"symbols":"btcusdt"}"#,
+            "unknown field 'symbols'",
+        ),
+        (
+            Topic::ClobMarket,
+            MessageType::AggOrderbook,
+            "[]",
+            "token ID list is empty",
+        ),
+        (
+            Topic::ClobMarket,
+            MessageType::AggOrderbook,
+            "[1,2]",
+            "list of token IDs",
+        ),
+        (
+            Topic::ClobMarket,
+            MessageType::MarketCreated,
+            r#"["1"]"#,
+            "does not accept filters",
+        ),
+        (
+            Topic::Rfq,
+            MessageType::RequestCreated,
+            "{}",
+            "does not accept filters",
+        ),
+    ];
+    for (topic, message_type, filters, expected) in cases {
+        let subscription = with_filters(topic, message_type, filters);
+        assert_rejected(&subscription, expected);
+        assert_rejected(
+            &subscription,
+            &format!("for topic '{}'", subscription.topic),
+        );
+    }
+}
+
+#[test]
+fn requires_a_token_id_for_price_changes() {
+    let subscription = Subscription::new(Topic::ClobMarket, MessageType::PriceChange);
+    assert_rejected(&subscription, "requires a token ID filter");
+}
+
+#[test]
+fn requires_credentials_for_clob_user_only() {
+    let subscription = Subscription::new(Topic::ClobUser, MessageType::All);
+    assert_rejected(&subscription, "requires clob_auth credentials");
+
+    let subscription =
+        Subscription::new(Topic::Activity, MessageType::Trades).with_clob_auth(creds());
+    assert_rejected(&subscription, "does not accept clob_auth credentials");
+}
+
+#[tokio::test]
+async fn the_client_rejects_invalid_subscriptions_before_sending() {
+    let client = RealTimeDataClient::new();
+    let error = client
+        .subscribe(vec![
+            ActivitySubscription::trades().build(),
+            ClobUserSubscription::trades(None).build(),
+        ])
+        .await
+        .unwrap_err();
+    assert!(
+        error.contains("requires clob_auth credentials"),
+        "{}",
+        error
+    );
+
+    // Valid subscriptions get past validation and only fail for lack of a connection.
+    let error = client
+        .subscribe(vec![ActivitySubscription::trades().build()])
+        .await
+        .unwrap_err();
+    assert_eq!(error, "Socket not connected");
+}